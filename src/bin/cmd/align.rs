@@ -0,0 +1,272 @@
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use clap::ArgMatches;
+use famlib::align::{AlignMode, PairAligner, PairAlignment, ProfileAligner};
+use famlib::fastaio::write_sequence_collection;
+use famlib::matrices::ScoringMatrix;
+use famlib::progressive::ProgressiveAligner;
+use famlib::seqs::{AnnotatedSequence, SequenceAccesors};
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, inputformat, Command, ToError};
+
+pub struct AlignCommand {}
+
+/// Options of the align pair command.
+pub struct AlignPairOptions {
+    pub aligner: PairAligner,
+    pub out_format: String,
+}
+
+/// Gets a built-in scoring matrix by name, or reads it from a file in NCBI
+/// format.
+fn scoring_matrix(name: &str) -> io::Result<ScoringMatrix> {
+    match ScoringMatrix::by_name(name) {
+        Some(x) => Ok(x),
+        None => {
+            let file = File::open(name).map_err(
+                |_| format!("Invalid matrix: {}.\n", name).to_io_error()
+            )?;
+            ScoringMatrix::from_ncbi(BufReader::new(file))
+                .map_err(|e| e.to_string().to_io_error())
+        }
+    }
+}
+
+/// Creates an aligner from the mode, matrix and gap penalties arguments.
+pub fn aligner(m: &ArgMatches) -> io::Result<PairAligner> {
+    let mode = m.value_of("mode").unwrap();
+    let mode = AlignMode::from_name(mode).ok_or_else(
+        || format!("Invalid mode: {}.\n", mode).to_io_error()
+    )?;
+    let matrix = match (m.value_of("matrix"), m.is_present("is_nucleic")) {
+        (Some(x), _) => scoring_matrix(x)?,
+        (None, true) => ScoringMatrix::match_mismatch("ACGTN", 5, -4)
+            .map_err(|e| e.to_string().to_io_error())?,
+        (None, false) => ScoringMatrix::blosum62(),
+    };
+    let parse = |name: &str, msg: &str| {
+        m.value_of(name)
+            .unwrap()
+            .parse::<f64>()
+            .map_err(|_| msg.to_io_error())
+    };
+    Ok(
+        PairAligner::new(&matrix)
+            .with_mode(mode)
+            .with_gap_open(parse("gap-open", "Cannot parse gap open.\n")?)
+            .with_gap_extend(
+                parse("gap-extend", "Cannot parse gap extend.\n")?
+            )
+    )
+}
+
+fn write_row<W: Write>(
+    writer: &mut W,
+    result: &PairAlignment,
+) -> io::Result<()> {
+    let ids = result
+        .alignment
+        .iter()
+        .map(|x| x.id().to_string())
+        .collect::<Vec<_>>();
+    writeln!(
+        writer,
+        "{}\t{}\t{}\t{:.4}\t{}\t{}\t{}\t{}\t{}\t{}",
+        ids[0],
+        ids[1],
+        result.score,
+        result.identity,
+        result.alignment.length(),
+        result.range1.0 + 1,
+        result.range1.1,
+        result.range2.0 + 1,
+        result.range2.1,
+        result.cigar
+    )
+}
+
+/// Options of the align add command.
+pub struct AlignAddOptions {
+    pub aligner: PairAligner,
+    pub keep_length: bool,
+}
+
+/// Options of the align msa command.
+pub struct AlignMsaOptions {
+    pub aligner: PairAligner,
+    pub kmer_size: usize,
+    pub refinement: usize,
+    pub threads: usize,
+}
+
+impl AlignCommand {
+    /// Aligns each input sequence to each target sequence. If there are no
+    /// targets, all pairs of input sequences are aligned.
+    pub fn align_pair(
+        fs: DataSource,
+        targets: Option<DataSource>,
+        fo: DataSink,
+        options: AlignPairOptions,
+    ) -> io::Result<()> {
+        let get_seqs = |x: DataSource| {
+            x.get_sequence_collection().ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )
+        };
+        let queries = get_seqs(fs)?;
+        let targets = targets.map(get_seqs).transpose()?;
+        let pairs: Vec<(&AnnotatedSequence, &AnnotatedSequence)> =
+            match &targets {
+                Some(targets) => queries
+                    .iter()
+                    .flat_map(|q| targets.iter().map(move |t| (q, t)))
+                    .collect(),
+                None => queries
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, q)| {
+                        queries.iter().skip(i + 1).map(move |t| (q, t))
+                    })
+                    .collect(),
+            };
+        let aligner = options.aligner;
+        let mut writer = fo.writer()?;
+        if options.out_format == "table" {
+            writeln!(
+                writer,
+                "query\ttarget\tscore\tidentity\tlength\tquery_start\t\
+                query_end\ttarget_start\ttarget_end\tcigar"
+            )?;
+        }
+        for (query, target) in pairs {
+            let result = aligner
+                .align(query, target)
+                .map_err(|e| e.to_string().to_io_error())?;
+            match options.out_format.as_str() {
+                "table" => write_row(&mut writer, &result)?,
+                "fasta" => {
+                    write_sequence_collection(&result.alignment, &mut writer)?
+                }
+                x => {
+                    return format!("Invalid output format: {}.\n", x)
+                        .to_error()
+                }
+            }
+        }
+        writer.flush()
+    }
+
+    /// Adds new sequences to an alignment, keeping its columns.
+    pub fn align_add(
+        fs: DataSource,
+        new_seqs: DataSource,
+        fo: DataSink,
+        options: AlignAddOptions,
+    ) -> io::Result<()> {
+        let mut msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let new_seqs = new_seqs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequences to add.\n".to_io_error()
+            )?;
+        let mut profile = ProfileAligner::new(&msa)
+            .with_aligner(options.aligner);
+        if options.keep_length {
+            profile = profile.keep_length();
+        }
+        let addition = profile
+            .add(&new_seqs)
+            .map_err(|e| e.to_string().to_io_error())?;
+        addition.apply(&mut msa)?;
+        fo.write_fasta(&msa)
+    }
+
+    /// Aligns all input sequences by progressive alignment.
+    pub fn align_msa(
+        fs: DataSource,
+        fo: DataSink,
+        options: AlignMsaOptions,
+    ) -> io::Result<()> {
+        let seqs = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?;
+        let msa = ProgressiveAligner::new(&seqs)
+            .with_aligner(options.aligner)
+            .with_kmer_size(options.kmer_size)
+            .with_refinement(options.refinement)
+            .with_threads(options.threads)
+            .align()
+            .map_err(|e| e.to_string().to_io_error())?;
+        fo.write_fasta(&msa)
+    }
+}
+
+impl Command for AlignCommand {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("align") {
+            if let Some(m1) = m.subcommand_matches("pair") {
+                let input = datasource(m1);
+                let output = datasink(m1);
+                let targets = m1
+                    .value_of("target")
+                    .map(|x| DataSource::from(x, inputformat(m1)));
+                let options = AlignPairOptions {
+                    aligner: aligner(m1)?,
+                    out_format: m1.value_of("out-format").unwrap().to_string(),
+                };
+                Self::align_pair(input, targets, output, options)?
+            }
+            if let Some(m1) = m.subcommand_matches("add") {
+                let input = datasource(m1);
+                let output = datasink(m1);
+                let new_seqs = DataSource::from(
+                    m1.value_of("add").unwrap(),
+                    inputformat(m1)
+                );
+                let options = AlignAddOptions {
+                    aligner: aligner(m1)?,
+                    keep_length: m1.is_present("keeplength"),
+                };
+                Self::align_add(input, new_seqs, output, options)?
+            }
+            if let Some(m1) = m.subcommand_matches("msa") {
+                let input = datasource(m1);
+                let output = datasink(m1);
+                let parse = |name: &str, msg: &str| {
+                    m1.value_of(name)
+                        .unwrap()
+                        .parse::<usize>()
+                        .map_err(|_| msg.to_io_error())
+                };
+                let threads = m1
+                    .value_of("threads")
+                    .map(|x| x.parse::<usize>())
+                    .transpose()
+                    .map_err(|_| "Cannot parse threads.\n".to_io_error())?
+                    .unwrap_or(0);
+                let options = AlignMsaOptions {
+                    aligner: aligner(m1)?,
+                    kmer_size: parse("kmer_size", "Cannot parse k-mer size.\n")?,
+                    refinement: parse("refine", "Cannot parse refine.\n")?,
+                    threads,
+                };
+                Self::align_msa(input, output, options)?
+            }
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("align")
+            .is_some()
+    }
+}
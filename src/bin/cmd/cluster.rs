@@ -0,0 +1,209 @@
+use std::{fs::File, io::{self, BufWriter, Write}};
+use clap::ArgMatches;
+use famlib::clustering::{
+    lcs_identity,
+    CdHit,
+    Cluster,
+    Clusterer,
+    Hobohm1,
+    Hobohm2
+};
+use famlib::seqs::{SequenceAccesors, SequenceCollection};
+use famlib::similarity::{metric_by_name, SimilarityMetric};
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, Command, ToError};
+
+pub struct ClusterCommand {}
+
+/// Options of the cluster command.
+pub struct ClusterOptions {
+    pub method: String,
+    pub identity: f64,
+    pub metric: Option<String>,
+    pub representative: Option<String>,
+    pub exclude_gaps: bool,
+    pub members: Option<String>,
+}
+
+/// Sorts sequence indexes so that preferred representatives come first.
+fn candidate_order(
+    seqs: &SequenceCollection,
+    criterion: &str,
+) -> io::Result<Vec<usize>> {
+    let gaps = |i: usize| {
+        seqs.get(i)
+            .and_then(|x| x.seq())
+            .map_or(0, |x| {
+                x.iter().filter(|c| **c == '-' || **c == '.').count()
+            })
+    };
+    let residues = |i: usize| {
+        seqs.get(i).map_or(0, |x| x.len()) - gaps(i)
+    };
+    let mut order = (0..seqs.size()).collect::<Vec<_>>();
+    match criterion {
+        "first" => {}
+        "longest" => order.sort_by_key(|x| std::cmp::Reverse(residues(*x))),
+        "fewest-gaps" => order.sort_by_key(|x| gaps(*x)),
+        x => return Err(format!("Invalid representative: {}.\n", x)
+            .to_io_error()),
+    }
+    Ok(order)
+}
+
+impl ClusterCommand {
+    pub fn cluster_command(
+        fs: DataSource,
+        fo: DataSink,
+        options: ClusterOptions,
+    ) -> io::Result<()> {
+        let input = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?;
+        let method = options.method.as_str();
+        let metric_given = options.metric.is_some() || options.exclude_gaps;
+        if method == "cdhit" && metric_given {
+            return "The cdhit method does not support --metric or \
+                --exclude-gaps.\n".to_error();
+        }
+        // Hobohm2 chooses representatives by their number of neighbours,
+        // the input order only breaks ties.
+        if method == "hobohm2" && options.representative.is_some() {
+            return "The hobohm2 method does not support --representative.\n"
+                .to_error();
+        }
+        let representative = match (&options.representative, method) {
+            (Some(x), _) => x.as_str(),
+            (None, "cdhit") => "longest",
+            (None, _) => "first",
+        };
+        // Clusterers prefer the first sequences as representatives.
+        let order = candidate_order(&input, representative)?;
+        let ordered = order
+            .iter()
+            .filter_map(|x| input.get(*x).cloned())
+            .collect::<SequenceCollection>();
+        let metric = options.metric.as_deref().unwrap_or("identity");
+        let metric = metric_by_name(metric, options.exclude_gaps)
+            .map_err(|e| format!("{}.\n", e).to_io_error())?;
+        let clusters = match method {
+            "hobohm1" | "hobohm2" => {
+                let msa = ordered
+                    .clone()
+                    .to_msa()
+                    .map_err(
+                        |_| "Input is not an alignment.\n".to_io_error()
+                    )?;
+                let mut clusterer: Box<dyn Clusterer> =
+                    match method {
+                        "hobohm1" => Box::new(
+                            Hobohm1::new(&msa)
+                                .with_similarity(options.identity)
+                                .with_metric(&metric)
+                        ),
+                        _ => Box::new(
+                            Hobohm2::new(&msa)
+                                .with_similarity(options.identity)
+                                .with_metric(&metric)
+                        ),
+                    };
+                clusterer
+                    .clusterize()
+                    .map_err(|e| e.to_string().to_io_error())?;
+                clusterer.take_clusters()
+            }
+            "cdhit" => {
+                let mut clusterer = CdHit::new(&ordered)
+                    .with_similarity(options.identity)
+                    .keep_order();
+                clusterer
+                    .clusterize()
+                    .map_err(|e| e.to_string().to_io_error())?;
+                clusterer.take_clusters()
+            }
+            x => return format!("Invalid method: {}.\n", x).to_error(),
+        }
+        .ok_or_else(|| "No clusters were found.\n".to_io_error())?;
+        // Map cluster indexes back to input order.
+        let mut clusters = clusters
+            .into_iter()
+            .map(|c| {
+                let mut members = c.members
+                    .iter()
+                    .map(|x| order[*x])
+                    .collect::<Vec<_>>();
+                members.sort();
+                Cluster::new(order[c.representative]).with_members(members)
+            })
+            .collect::<Vec<_>>();
+        clusters.sort_by_key(|c| c.representative);
+        let output = clusters
+            .iter()
+            .filter_map(|c| input.get(c.representative).cloned())
+            .collect::<SequenceCollection>();
+        if let Some(path) = &options.members {
+            let similarity = |a: &Vec<char>, b: &Vec<char>| {
+                match method {
+                    "cdhit" => Ok(lcs_identity(a, b)),
+                    _ => metric.similarity(a, b),
+                }
+            };
+            let mut writer = BufWriter::new(File::create(path)?);
+            writeln!(writer, "representative\tmember\tsimilarity")?;
+            for cluster in &clusters {
+                let repr = input.get(cluster.representative).unwrap();
+                for m in &cluster.members {
+                    let member = input.get(*m).unwrap();
+                    let value = match *m == cluster.representative {
+                        true => 1f64,
+                        false => similarity(
+                            repr.seq().unwrap(),
+                            member.seq().unwrap()
+                        ).map_err(|e| e.to_string().to_io_error())?,
+                    };
+                    writeln!(
+                        writer,
+                        "{}\t{}\t{:.4}",
+                        repr.id(),
+                        member.id(),
+                        value
+                    )?;
+                }
+            }
+            writer.flush()?;
+        }
+        fo.write_fasta(&output)
+    }
+}
+
+impl Command for ClusterCommand {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("cluster") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let identity = m
+                .value_of("identity")
+                .unwrap()
+                .parse::<f64>()
+                .map_err(|_| "Cannot parse identity.\n".to_io_error())?;
+            let options = ClusterOptions {
+                method: m.value_of("method").unwrap().to_string(),
+                identity,
+                metric: m.value_of("metric").map(String::from),
+                representative: m.value_of("representative").map(String::from),
+                exclude_gaps: m.is_present("exclude_gaps"),
+                members: m.value_of("members").map(String::from),
+            };
+            Self::cluster_command(input, output, options)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("cluster")
+            .is_some()
+    }
+}
@@ -0,0 +1,118 @@
+use std::io;
+use clap::ArgMatches;
+use famlib::clustering::{Clusterer, Hobohm1};
+use famlib::coevolution::{Correction, MutualInformation};
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, reference, Command, Reference, ToError};
+
+pub struct Coevolution {}
+
+/// Options of the coevolution command.
+pub struct CoevolutionOptions {
+    pub is_protein: bool,
+    pub correction: Correction,
+    pub weights_identity: Option<f64>,
+    pub max_gaps: f64,
+    pub top: usize,
+    pub min_separation: usize,
+    pub reference: Option<Reference>,
+}
+
+impl Coevolution {
+    pub fn coevolution_command(
+        fs: DataSource,
+        fo: DataSink,
+        options: CoevolutionOptions,
+    ) -> io::Result<()> {
+        let msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let reference = options
+            .reference
+            .as_ref()
+            .map(|x| x.numbering(&msa))
+            .transpose()?;
+        let mut mi = MutualInformation::new(&msa)
+            .with_correction(options.correction)
+            .with_max_gaps(options.max_gaps);
+        if !options.is_protein {
+            mi = mi.as_dna();
+        }
+        if let Some(identity) = options.weights_identity {
+            let mut clusterer = Hobohm1::new(&msa)
+                .with_similarity(identity);
+            let weights = clusterer
+                .clusterize()
+                .and_then(|_| clusterer.weights())
+                .map_err(|e| e.to_string().to_io_error())?;
+            mi = mi.with_weights(weights);
+        }
+        let scores = mi
+            .compute()
+            .map_err(|e| e.to_string().to_io_error())?;
+        scores.write_tsv(
+            fo.writer()?,
+            options.top,
+            options.min_separation,
+            reference.as_ref(),
+        )
+    }
+}
+
+impl Command for Coevolution {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("coevolution") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let correction = match m.value_of("correction").unwrap() {
+                "none" => Correction::None,
+                "apc" => Correction::APC,
+                "rcw" => Correction::RCW,
+                x => return format!("Invalid correction: {}.\n", x).to_error(),
+            };
+            let weights_identity = m
+                .value_of("weights-identity")
+                .map(|x| x.parse::<f64>())
+                .transpose()
+                .map_err(
+                    |_| "Cannot parse weights identity.\n".to_io_error()
+                )?;
+            let max_gaps = m
+                .value_of("max-gaps")
+                .unwrap()
+                .parse::<f64>()
+                .map_err(|_| "Cannot parse max gaps.\n".to_io_error())?;
+            let top = m
+                .value_of("top")
+                .unwrap()
+                .parse::<usize>()
+                .map_err(|_| "Cannot parse top.\n".to_io_error())?;
+            let min_separation = m
+                .value_of("min-separation")
+                .unwrap()
+                .parse::<usize>()
+                .map_err(|_| "Cannot parse min separation.\n".to_io_error())?;
+            let options = CoevolutionOptions {
+                is_protein: !m.is_present("is_nucleic"),
+                correction,
+                weights_identity,
+                max_gaps,
+                top,
+                min_separation,
+                reference: reference(m)?,
+            };
+            Self::coevolution_command(input, output, options)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("coevolution")
+            .is_some()
+    }
+}
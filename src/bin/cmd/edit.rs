@@ -1,8 +1,18 @@
-use std::io::{self, ErrorKind};
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind};
+use std::path::Path;
+use famlib::align::PairAligner;
+use famlib::edit_script::{EditScript, Operation};
+use famlib::journal::{EditJournal, Journaled};
 use famlib::seqs::SequenceAccesors;
 use famlib::edit::EditSequence;
+use famlib::edit_msa::EditMSA;
 use crate::data::{DataSink, DataSource};
-use super::{Command, datasink, datasource};
+use super::{
+    Command, ColumnSelection, Reference, ToError, column_selection, datasink,
+    datasource, reference, reference_column, save_journal
+};
+use super::align::aligner;
 
 pub struct Edit{}
 
@@ -11,17 +21,24 @@ impl Edit {
         fs: DataSource,
         fo: DataSink,
         at: Vec<usize>,
-        content: Vec<&str>
+        content: Vec<&str>,
+        reference: Option<Reference>,
+        journal_path: Option<&str>,
     ) -> io::Result<()> {
         let mut input = fs.get_sequence_collection().unwrap();
-        let col_idx = at[1]-1;
+        let mut journal = EditJournal::new();
+        let col_idx = match &reference {
+            Some(r) => reference_column(&r.numbering(&input)?, at[1])?,
+            None => at[1]-1,
+        };
         for (x, c) in content.iter().enumerate() {
             let row_idx = at[0]-1+x;
             match input.get_mut(row_idx) {
                 Some(seq) => {
                     let new=c.chars().collect::<Vec<_>>();
                     let count = new.len();
-                    match seq.edit_replace(new, col_idx, count) {
+                    match Journaled::new(seq, &mut journal)
+                        .edit_replace(new, col_idx, count) {
                         Ok(_) => {}
                         Err(x) => {
                             return  Err(std::io::Error::new(
@@ -39,22 +56,30 @@ impl Edit {
                 }
             }
         };
-        fo.write_fasta(&input)
+        fo.write_fasta(&input)?;
+        save_journal(journal_path, &journal)
     }
     pub fn edit_insert(
         fs: DataSource,
         fo: DataSink,
         at: Vec<usize>,
-        content: Vec<&str>
+        content: Vec<&str>,
+        reference: Option<Reference>,
+        journal_path: Option<&str>,
     ) -> io::Result<()> {
         let mut input = fs.get_sequence_collection().unwrap();
-        let col_idx = at[1]-1;
+        let mut journal = EditJournal::new();
+        let col_idx = match &reference {
+            Some(r) => reference_column(&r.numbering(&input)?, at[1])?,
+            None => at[1]-1,
+        };
         for (x, c) in content.iter().enumerate() {
             let row_idx = at[0]-1+x;
             match input.get_mut(row_idx) {
                 Some(seq) => {
                     let new=c.chars().collect::<Vec<_>>();
-                    match seq.edit_insert(new, col_idx) {
+                    match Journaled::new(seq, &mut journal)
+                        .edit_insert(new, col_idx) {
                         Ok(_) => {}
                         Err(x) => {
                             return  Err(std::io::Error::new(
@@ -72,31 +97,181 @@ impl Edit {
                 }
             }
         };
-        fo.write_fasta(&input)
+        fo.write_fasta(&input)?;
+        save_journal(journal_path, &journal)
     }
     pub fn edit_delete(
             fs: DataSource,
             fo: DataSink,
             at: Vec<usize>,
             width: usize,
-            height: usize)
+            height: usize,
+            reference: Option<Reference>,
+            journal_path: Option<&str>)
             -> io::Result<()> {
         let mut input = fs.get_sequence_collection().unwrap();
-        let col_idx = at[1]-1;
-        println!("Col Index: {}", col_idx);
+        let mut journal = EditJournal::new();
+        // With a reference, the width is given in residues of the reference.
+        let (col_idx, width) = match &reference {
+            Some(r) if width > 0 => {
+                let numbering = r.numbering(&input)?;
+                let first = reference_column(&numbering, at[1])?;
+                let last = reference_column(&numbering, at[1] + width - 1)?;
+                (first, last - first + 1)
+            }
+            Some(r) => (reference_column(&r.numbering(&input)?, at[1])?, 0),
+            None => (at[1]-1, width),
+        };
         for x in 0..height {
             let row_idx = at[0]-1+x;
-            input
+            let seq = input
                 .get_mut(row_idx)
                 .ok_or_else(
                     || std::io::Error::new(
                         ErrorKind::Other,
                         format!("Row index out of bounds: {}.", row_idx)
                     )
-                )?
-                .edit_delete(col_idx, width)?;
+                )?;
+            Journaled::new(seq, &mut journal).edit_delete(col_idx, width)?;
         };
-        fo.write_fasta(&input)
+        fo.write_fasta(&input)?;
+        save_journal(journal_path, &journal)
+    }
+    /// Deletes the selected columns from all sequences.
+    pub fn edit_delete_columns(
+        fs: DataSource,
+        fo: DataSink,
+        selection: ColumnSelection,
+        journal_path: Option<&str>,
+    ) -> io::Result<()> {
+        let mut msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let columns = selection.columns(&msa, msa.length())?;
+        let mut journal = EditJournal::new();
+        Journaled::new(&mut msa, &mut journal).remove_columns(columns)?;
+        fo.write_fasta(&msa)?;
+        save_journal(journal_path, &journal)
+    }
+    /// Realigns the columns from `first` to `last`, both one based and
+    /// inclusive. With a reference, positions are its residue numbers.
+    pub fn edit_realign(
+        fs: DataSource,
+        fo: DataSink,
+        first: usize,
+        last: usize,
+        aligner: PairAligner,
+        reference: Option<Reference>,
+        journal_path: Option<&str>,
+    ) -> io::Result<()> {
+        let mut msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let (first, last) = match &reference {
+            Some(r) => {
+                let numbering = r.numbering(&msa)?;
+                (
+                    reference_column(&numbering, first)? + 1,
+                    reference_column(&numbering, last)? + 1
+                )
+            }
+            None => (first, last),
+        };
+        if first == 0 || first > last || last > msa.length() {
+            return format!(
+                "Invalid column range: {}-{}.\n", first, last
+            ).to_error();
+        }
+        let mut journal = EditJournal::new();
+        Journaled::new(&mut msa, &mut journal)
+            .realign_columns(first - 1, last, &aligner)
+            .map_err(|e| format!("{}\n", e).to_io_error())?;
+        fo.write_fasta(&msa)?;
+        save_journal(journal_path, &journal)
+    }
+    /// Applies all the edits of a TSV or TOML script, and reports the
+    /// applied edits to stderr. With a reference, positions are its residue
+    /// numbers.
+    pub fn edit_script(
+        fs: DataSource,
+        fo: DataSink,
+        script: &str,
+        reference: Option<Reference>,
+        journal_path: Option<&str>,
+    ) -> io::Result<()> {
+        let mut script = match Path::new(script).extension() {
+            Some(x) if x == "toml" => std::fs::read_to_string(script)
+                .map_err(|e| e.to_string())
+                .and_then(
+                    |x| EditScript::from_toml(&x).map_err(|e| e.to_string())
+                ),
+            _ => File::open(script)
+                .map_err(|e| e.to_string())
+                .and_then(
+                    |x| EditScript::from_tsv(BufReader::new(x))
+                        .map_err(|e| e.to_string())
+                ),
+        }
+        .map_err(|e| format!("Cannot read edit script: {}.\n", e).to_io_error())?;
+        let mut msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        // With a reference, positions and widths are given in residues of
+        // the reference.
+        if let Some(r) = &reference {
+            let numbering = r.numbering(&msa)?;
+            for instruction in script.instructions.iter_mut() {
+                let width = instruction
+                    .width()
+                    .map_err(|e| format!("{}.\n", e).to_io_error())?;
+                let first = reference_column(&numbering, instruction.position)?;
+                if width > 0 {
+                    let last = reference_column(
+                        &numbering,
+                        instruction.position + width - 1
+                    )?;
+                    match instruction.operation {
+                        Operation::Delete => {
+                            instruction.content = (last - first + 1).to_string()
+                        }
+                        Operation::Replace => {
+                            instruction.width = Some(last - first + 1)
+                        }
+                        Operation::Insert => {}
+                    }
+                }
+                instruction.position = first + 1;
+            }
+        }
+        let mut journal = EditJournal::new();
+        let applied = script
+            .apply_with_journal(&mut msa, &mut journal)
+            .map_err(|e| format!("{}.\n", e).to_io_error())?;
+        eprintln!("Applied {} edits:", applied.len());
+        for edit in applied {
+            eprintln!(
+                "{}\t{}\tposition {}\tcolumn {}\t{} residues",
+                edit.operation.name(),
+                edit.id,
+                edit.position,
+                edit.column,
+                edit.residues
+            );
+        }
+        fo.write_fasta(&msa)?;
+        save_journal(journal_path, &journal)
     }
 }
 
@@ -126,7 +301,14 @@ impl Command for Edit {
                 let content = m1.values_of("content")
                     .unwrap()
                     .collect::<Vec<&str>>();
-                Self::edit_replace(input, output, at, content)?
+                Self::edit_replace(
+                    input,
+                    output,
+                    at,
+                    content,
+                    reference(m1)?,
+                    m1.value_of("journal")
+                )?
             };
             if let Some(m1) = m.subcommand_matches("insert") {
                 let input = datasource(m1);
@@ -149,11 +331,23 @@ impl Command for Edit {
                 let content = m1.values_of("content")
                     .unwrap()
                     .collect::<Vec<&str>>();
-                Self::edit_insert(input, output, at, content)?
+                Self::edit_insert(
+                    input,
+                    output,
+                    at,
+                    content,
+                    reference(m1)?,
+                    m1.value_of("journal")
+                )?
             };
             if let Some(m1) = m.subcommand_matches("delete") {
                 let input = datasource(m1);
                 let output = datasink(m1);
+                if let Some(selection) = column_selection(m1)? {
+                    return Self::edit_delete_columns(
+                        input, output, selection, m1.value_of("journal")
+                    );
+                }
                 let at = m1.values_of("at")
                     .unwrap()
                     .map(|x| x.parse::<usize>().ok())
@@ -177,7 +371,45 @@ impl Command for Edit {
                 );
                 let width = m1.value_of("width").unwrap().parse::<usize>().or_else(err_gen)?;
                 let height = m1.value_of("height").unwrap().parse::<usize>().or_else(err_gen)?;
-                Self::edit_delete(input, output, at, width, height)?
+                Self::edit_delete(
+                    input,
+                    output,
+                    at,
+                    width,
+                    height,
+                    reference(m1)?,
+                    m1.value_of("journal")
+                )?
+            };
+            if let Some(m1) = m.subcommand_matches("script") {
+                let input = datasource(m1);
+                let output = datasink(m1);
+                let script = m1.value_of("script").unwrap();
+                Self::edit_script(
+                    input,
+                    output,
+                    script,
+                    reference(m1)?,
+                    m1.value_of("journal")
+                )?
+            };
+            if let Some(m1) = m.subcommand_matches("realign") {
+                let input = datasource(m1);
+                let output = datasink(m1);
+                let cols = m1.values_of("cols")
+                    .unwrap()
+                    .map(|x| x.parse::<usize>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| "Cannot parse columns.\n".to_io_error())?;
+                Self::edit_realign(
+                    input,
+                    output,
+                    cols[0],
+                    cols[1],
+                    aligner(m1)?,
+                    reference(m1)?,
+                    m1.value_of("journal")
+                )?
             };
         }
         Ok(())
@@ -1,9 +1,14 @@
 
 use std::io::{self, ErrorKind};
 use crate::data::{DataSink, DataSource};
-use super::{datasink, datasource, Command, ToError};
+use super::{
+    column_selection, datasink, datasource, mask, reference,
+    remove_or_mask_columns, ColumnSelection, Command, ToError
+};
 use clap::ArgMatches;
 use famlib::degap::DegapBufferedSequenceCollection;
+use famlib::masking::Mask;
+use famlib::seqs::Alignment;
 
 pub struct Gap {}
 
@@ -44,10 +49,7 @@ impl Gap {
             .map_err(Into::into)
     }
 
-    pub fn remove_all_gap_columns_command(
-        fs: DataSource,
-        fo: DataSink,
-    ) -> io::Result<()> {
+    fn read_msa(fs: DataSource) -> io::Result<Alignment> {
         fs
             .get_sequence_collection()
             .ok_or_else(
@@ -55,25 +57,58 @@ impl Gap {
             )?
             .to_msa()
             .map_err(|_| "Input is not an alignment.\n".to_io_error())
-            .map(|mut msa| {msa.remove_all_gap_columns(); msa})
-            .map(|msa| fo.write_fasta(&msa))?
+    }
+
+    pub fn remove_all_gap_columns_command(
+        fs: DataSource,
+        fo: DataSink,
+        mask: Option<Mask>,
+        journal_path: Option<&str>,
+    ) -> io::Result<()> {
+        let mut msa = Self::read_msa(fs)?;
+        let columns = msa.all_gap_columns();
+        remove_or_mask_columns(&mut msa, columns, mask, journal_path)?;
+        fo.write_fasta(&msa)
     }
 
     pub fn rm_columns(
         fs: DataSource,
         fo: DataSink,
-        min_freq: f64
+        min_freq: f64,
+        mask: Option<Mask>,
+        journal_path: Option<&str>,
     ) -> io::Result<()> {
-        fs
-            .get_sequence_collection()
-            .ok_or_else(
-                || "Cannot get sequence collection from input.\n"
-                    .to_io_error()
-            )?
-            .to_msa()
-            .map_err(|_| "Input is not an alignment.\n".to_io_error())
-            .map( |mut msa| {msa.remove_frq_gap_columns(min_freq); msa})
-            .map(|msa| fo.write_fasta(&msa))?
+        let mut msa = Self::read_msa(fs)?;
+        let columns = msa.frq_gap_columns(min_freq);
+        remove_or_mask_columns(&mut msa, columns, mask, journal_path)?;
+        fo.write_fasta(&msa)
+    }
+
+    pub fn rm_selected_columns(
+        fs: DataSource,
+        fo: DataSink,
+        selection: ColumnSelection,
+        mask: Option<Mask>,
+        journal_path: Option<&str>,
+    ) -> io::Result<()> {
+        let mut msa = Self::read_msa(fs)?;
+        let columns = selection.columns(&msa, msa.length())?;
+        remove_or_mask_columns(&mut msa, columns, mask, journal_path)?;
+        fo.write_fasta(&msa)
+    }
+
+    /// Removes or masks the columns where the reference sequence has gaps.
+    pub fn by_reference(
+        fs: DataSource,
+        fo: DataSink,
+        id: &str,
+        mask: Option<Mask>,
+        journal_path: Option<&str>,
+    ) -> io::Result<()> {
+        let mut msa = Self::read_msa(fs)?;
+        let columns = msa.reference_gap_columns(id)?;
+        remove_or_mask_columns(&mut msa, columns, mask, journal_path)?;
+        fo.write_fasta(&msa)
     }
 }
 impl Command for Gap {
@@ -91,6 +126,25 @@ impl Command for Gap {
                     let accetps_dots = m1.is_present("accept-dots");
                     Self::degap(input, output, accetps_dots)?
                 },
+                ("by-reference", Some(m1)) => {
+                    let input = datasource(m1);
+                    let output = datasink(m1);
+                    let id = match (m1.value_of("id"), reference(m1)?) {
+                        (Some(id), _) => id.to_string(),
+                        (None, Some(global)) => global.id,
+                        (None, None) => {
+                            return "A reference sequence is required.\n"
+                                .to_error()
+                        }
+                    };
+                    Self::by_reference(
+                        input,
+                        output,
+                        &id,
+                        mask(m1)?,
+                        m1.value_of("journal"),
+                    )?
+                },
                 ("remove-columns", Some(m1)) => {
                     let input = datasource(m1);
                     let output = datasink(m1);
@@ -102,11 +156,18 @@ impl Command for Gap {
                                 .max(0.0f64)
                                 .min(1.0f64)
                         );
-                    match freq {
-                        Some(freq) =>
-                            Self::rm_columns(input, output, freq)?,
-                        None =>
-                            Self::remove_all_gap_columns_command(input, output)?
+                    let mask = mask(m1)?;
+                    let journal = m1.value_of("journal");
+                    match (freq, column_selection(m1)?) {
+                        (_, Some(selection)) => Self::rm_selected_columns(
+                            input, output, selection, mask, journal
+                        )?,
+                        (Some(freq), None) => Self::rm_columns(
+                            input, output, freq, mask, journal
+                        )?,
+                        (None, None) => Self::remove_all_gap_columns_command(
+                            input, output, mask, journal
+                        )?
                     }
                 },
                 _ => eprintln!("No subcommand provided"),
@@ -0,0 +1,112 @@
+use std::io::{self, Write};
+use clap::ArgMatches;
+use famlib::kmer::{KmerDistances, KmerMethod};
+use famlib::pairwise::PairwiseValues;
+use famlib::seqs::SequenceAccesors;
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, Command, ToError};
+
+pub struct KmerCommand {}
+
+/// Options of the kmer command.
+pub struct KmerOptions {
+    pub method: KmerMethod,
+    pub kmer_size: usize,
+    pub sketch_size: usize,
+    pub out_format: String,
+    pub neighbours: usize,
+    pub threads: usize,
+}
+
+impl KmerCommand {
+    pub fn kmer_command(
+        fs: DataSource,
+        fo: DataSink,
+        options: KmerOptions,
+    ) -> io::Result<()> {
+        let seqs = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?;
+        let distances = KmerDistances::new(&seqs)
+            .with_method(options.method)
+            .with_kmer_size(options.kmer_size)
+            .with_sketch_size(options.sketch_size)
+            .with_threads(options.threads);
+        let mut writer = fo.writer()?;
+        match options.out_format.as_str() {
+            "square" => distances
+                .matrix()
+                .map_err(|e| e.to_string().to_io_error())?
+                .write_tsv(writer, PairwiseValues::Distance),
+            "phylip" => distances
+                .matrix()
+                .map_err(|e| e.to_string().to_io_error())?
+                .write_phylip(writer),
+            "nearest" => {
+                let nearest = distances
+                    .nearest(options.neighbours)
+                    .map_err(|e| e.to_string().to_io_error())?;
+                writeln!(writer, "id\tneighbour\tdistance")?;
+                for (i, neighbours) in nearest.iter().enumerate() {
+                    let id = seqs.get(i).unwrap().id();
+                    for (j, d) in neighbours {
+                        let other = seqs.get(*j).unwrap().id();
+                        writeln!(writer, "{}\t{}\t{:.4}", id, other, d)?;
+                    }
+                }
+                writer.flush()
+            }
+            x => format!("Invalid output format: {}.\n", x).to_error(),
+        }
+    }
+}
+
+impl Command for KmerCommand {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("kmer") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let method = match m.value_of("method").unwrap() {
+                "profile" => KmerMethod::Profile,
+                "minhash" => KmerMethod::MinHash,
+                x => return format!("Invalid method: {}.\n", x).to_error(),
+            };
+            let parse = |name: &str, msg: &str| {
+                m.value_of(name)
+                    .unwrap()
+                    .parse::<usize>()
+                    .map_err(|_| msg.to_io_error())
+            };
+            let threads = m
+                .value_of("threads")
+                .map(|x| x.parse::<usize>())
+                .transpose()
+                .map_err(|_| "Cannot parse threads.\n".to_io_error())?
+                .unwrap_or(0);
+            let options = KmerOptions {
+                method,
+                kmer_size: parse("kmer_size", "Cannot parse k-mer size.\n")?,
+                sketch_size: parse(
+                    "sketch_size",
+                    "Cannot parse sketch size.\n"
+                )?,
+                out_format: m.value_of("out-format").unwrap().to_string(),
+                neighbours: parse(
+                    "neighbours",
+                    "Cannot parse number of neighbours.\n"
+                )?,
+                threads,
+            };
+            Self::kmer_command(input, output, options)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("kmer")
+            .is_some()
+    }
+}
@@ -0,0 +1,102 @@
+use std::{fs::File, io::{self, BufWriter}, path::Path};
+use clap::ArgMatches;
+use famlib::logo::SequenceLogo;
+use crate::data::DataSource;
+use super::{datasource, Command, ToError};
+
+pub struct Logo {}
+
+/// Drawing options of the logo command.
+pub struct LogoOptions {
+    pub is_protein: bool,
+    pub start: usize,
+    pub end: Option<usize>,
+    pub column_width: u32,
+    pub bit_height: u32,
+    pub image_format: String,
+}
+
+impl Logo {
+    pub fn logo_command(
+        fs: DataSource,
+        outfile: &str,
+        options: LogoOptions,
+    ) -> io::Result<()> {
+        let msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let mut logo = SequenceLogo::new(&msa)
+            .with_range(options.start, options.end)
+            .with_column_width(options.column_width)
+            .with_bit_height(options.bit_height);
+        if !options.is_protein {
+            logo = logo.as_dna();
+        }
+        let result = match options.image_format.as_str() {
+            "png" => logo.save_png(Path::new(outfile)),
+            "svg" => {
+                let file = File::create(outfile)?;
+                logo.write_svg(BufWriter::new(file))
+            }
+            x => return format!("Invalid image format: {}.\n", x).to_error(),
+        };
+        result.map_err(
+            |e| format!(
+                "There was a problem generating the image file {}.\n", e
+            ).to_io_error()
+        )
+    }
+}
+
+fn parse_position(m: &ArgMatches, name: &str) -> io::Result<Option<usize>> {
+    m.value_of(name)
+        .map(|x| x.parse::<usize>())
+        .transpose()
+        .map_err(|_| format!("Cannot parse {} column.\n", name).to_io_error())
+}
+
+impl Command for Logo {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("logo") {
+            let input = datasource(m);
+            let output = m.value_of("output").unwrap();
+            let image_format = match m.value_of("image-format") {
+                Some(x) => x.to_lowercase(),
+                None => Path::new(output)
+                    .extension()
+                    .map(|x| x.to_string_lossy().to_lowercase())
+                    .unwrap_or_else(|| String::from("png")),
+            };
+            let column_width = m
+                .value_of("column-width")
+                .unwrap()
+                .parse::<u32>()
+                .map_err(|_| "Cannot parse column width.\n".to_io_error())?;
+            let bit_height = m
+                .value_of("bit-height")
+                .unwrap()
+                .parse::<u32>()
+                .map_err(|_| "Cannot parse bit height.\n".to_io_error())?;
+            let options = LogoOptions {
+                is_protein: !m.is_present("is_nucleic"),
+                start: parse_position(m, "start")?.unwrap_or(1),
+                end: parse_position(m, "end")?,
+                column_width,
+                bit_height,
+                image_format,
+            };
+            Self::logo_command(input, output, options)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("logo")
+            .is_some()
+    }
+}
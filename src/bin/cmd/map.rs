@@ -0,0 +1,160 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use clap::ArgMatches;
+use famlib::mapping::PositionMap;
+use famlib::seqs::Alignment;
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, Command, ToError};
+
+pub struct MapCommand {}
+
+/// The positions to map, given in the command line or in the first column
+/// of a TSV file.
+pub enum MapPositions {
+    Values(Vec<String>),
+    Tsv(String),
+}
+
+/// Options of the map command. If `from` or `to` are None, positions are
+/// alignment columns.
+pub struct MapOptions {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub positions: MapPositions,
+}
+
+/// Maps a one based position, from residues of a sequence or from
+/// alignment columns, to residues of a sequence or to alignment columns.
+struct Mapper {
+    from: Option<PositionMap>,
+    to: Option<PositionMap>,
+    length: usize,
+}
+
+impl Mapper {
+    fn new(msa: &Alignment, options: &MapOptions) -> io::Result<Self> {
+        let position_map = |id: &Option<String>| {
+            id.as_ref()
+                .map(|x| PositionMap::from_alignment(msa, x))
+                .transpose()
+        };
+        Ok(
+            Mapper {
+                from: position_map(&options.from)?,
+                to: position_map(&options.to)?,
+                length: msa.length(),
+            }
+        )
+    }
+    fn map(&self, position: usize) -> Option<usize> {
+        let column = match &self.from {
+            Some(x) => x.column_of(position.checked_sub(1)?)?,
+            None => position.checked_sub(1).filter(|x| *x < self.length)?,
+        };
+        match &self.to {
+            Some(x) => x.residue_at(column).map(|x| x + 1),
+            None => Some(column + 1),
+        }
+    }
+    fn format(&self, position: &str) -> io::Result<String> {
+        let position = position.trim().parse::<usize>().map_err(
+            |_| format!("Cannot parse position: {}.\n", position).to_io_error()
+        )?;
+        Ok(
+            self.map(position)
+                .map(|x| x.to_string())
+                .unwrap_or_else(|| "-".to_string())
+        )
+    }
+}
+
+impl MapCommand {
+    /// Maps positions between residues of sequences and alignment columns.
+    /// Positions that fall in a gap of the target sequence are written as
+    /// '-'.
+    pub fn map_positions(
+        fs: DataSource,
+        fo: DataSink,
+        options: MapOptions,
+    ) -> io::Result<()> {
+        let msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let mapper = Mapper::new(&msa, &options)?;
+        let name = |x: &Option<String>| {
+            x.clone().unwrap_or_else(|| "column".to_string())
+        };
+        let mut writer = fo.writer()?;
+        match &options.positions {
+            MapPositions::Values(values) => {
+                let mapped = values
+                    .iter()
+                    .map(|x| mapper.format(x))
+                    .collect::<io::Result<Vec<_>>>()?;
+                writeln!(
+                    writer,
+                    "{}\t{}",
+                    name(&options.from),
+                    name(&options.to)
+                )?;
+                for (value, mapped) in values.iter().zip(mapped) {
+                    writeln!(writer, "{}\t{}", value, mapped)?;
+                }
+            }
+            MapPositions::Tsv(path) => {
+                let file = File::open(path).map_err(
+                    |_| format!("Cannot open file: {}.\n", path).to_io_error()
+                )?;
+                for (i, line) in BufReader::new(file).lines().enumerate() {
+                    let line = line?;
+                    let first = line.split('\t').next().unwrap_or("");
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    // A first line without a position is a header.
+                    if i == 0 && first.trim().parse::<usize>().is_err() {
+                        writeln!(writer, "{}\t{}", line, name(&options.to))?;
+                        continue;
+                    }
+                    writeln!(writer, "{}\t{}", line, mapper.format(first)?)?;
+                }
+            }
+        }
+        writer.flush()
+    }
+}
+
+impl Command for MapCommand {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("map") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let positions = match m.value_of("tsv") {
+                Some(x) => MapPositions::Tsv(x.to_string()),
+                None => MapPositions::Values(
+                    m.values_of("positions")
+                        .unwrap()
+                        .map(|x| x.to_string())
+                        .collect()
+                ),
+            };
+            let options = MapOptions {
+                from: m.value_of("from").map(|x| x.to_string()),
+                to: m.value_of("to").map(|x| x.to_string()),
+                positions,
+            };
+            Self::map_positions(input, output, options)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("map")
+            .is_some()
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+use clap::ArgMatches;
+use famlib::matrices::{
+    DNAMatrix, LogOdds, Num, ProteinMatrix, SubstitutionMatrix
+};
+use famlib::seqs::Alignment;
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, Command, ToError};
+
+pub struct MatrixCommand {}
+
+/// Options of the matrix command.
+pub struct MatrixOptions {
+    pub is_protein: bool,
+    pub values: String,
+    pub out_format: String,
+    pub identity: f64,
+    pub pseudocount: f64,
+    pub threads: usize,
+}
+
+fn write_matrix<T, M, W>(
+    matrix: &M,
+    out_format: &str,
+    writer: W,
+) -> io::Result<()>
+where
+    T: Num + Display,
+    M: SubstitutionMatrix<T>,
+    W: Write,
+{
+    match out_format {
+        "tsv" => matrix.write_tsv(writer),
+        "ncbi" => matrix.write_ncbi(writer),
+        x => format!("Invalid output format: {}.\n", x).to_error(),
+    }
+}
+
+fn build_and_write<M, W>(
+    mut matrix: M,
+    msa: &Alignment,
+    options: &MatrixOptions,
+    writer: W,
+) -> io::Result<()>
+where
+    M: SubstitutionMatrix<usize>,
+    M::Output: SubstitutionMatrix<f64>,
+    W: Write,
+{
+    matrix
+        .build_from(msa)
+        .map_err(|e| e.to_string().to_io_error())?;
+    match options.values.as_str() {
+        "frequencies" => {
+            let normalized = matrix
+                .normalize()
+                .map_err(|e| e.to_string().to_io_error())?;
+            write_matrix(&normalized, &options.out_format, writer)
+        }
+        _ => write_matrix(&matrix, &options.out_format, writer),
+    }
+}
+
+impl MatrixCommand {
+    pub fn matrix_command(
+        fs: DataSource,
+        fo: DataSink,
+        options: MatrixOptions,
+    ) -> io::Result<()> {
+        let msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let writer = fo.writer()?;
+        match (options.values.as_str(), options.is_protein) {
+            ("log-odds", false) => {
+                "Log-odds matrices require protein sequences.\n".to_error()
+            }
+            ("log-odds", true) => {
+                let matrix = LogOdds::new(&msa)
+                    .with_identity(options.identity)
+                    .with_pseudocount(options.pseudocount)
+                    .with_threads(options.threads)
+                    .build()
+                    .map_err(|e| e.to_string().to_io_error())?;
+                write_matrix(&matrix, &options.out_format, writer)
+            }
+            (_, true) => build_and_write(
+                ProteinMatrix::<usize>::new(),
+                &msa,
+                &options,
+                writer
+            ),
+            (_, false) => build_and_write(
+                DNAMatrix::<usize>::new(),
+                &msa,
+                &options,
+                writer
+            ),
+        }
+    }
+}
+
+impl Command for MatrixCommand {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("matrix") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let identity = m
+                .value_of("identity")
+                .unwrap()
+                .parse::<f64>()
+                .map_err(|_| "Cannot parse identity.\n".to_io_error())?;
+            let pseudocount = m
+                .value_of("pseudocount")
+                .unwrap()
+                .parse::<f64>()
+                .map_err(|_| "Cannot parse pseudocount.\n".to_io_error())?;
+            let threads = m
+                .value_of("threads")
+                .map(|x| x.parse::<usize>())
+                .transpose()
+                .map_err(|_| "Cannot parse threads.\n".to_io_error())?
+                .unwrap_or(0);
+            let options = MatrixOptions {
+                is_protein: !m.is_present("is_nucleic"),
+                values: m.value_of("values").unwrap().to_string(),
+                out_format: m.value_of("out-format").unwrap().to_string(),
+                identity,
+                pseudocount,
+                threads,
+            };
+            Self::matrix_command(input, output, options)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("matrix")
+            .is_some()
+    }
+}
@@ -1,6 +1,13 @@
-use std::io;
+use std::fs::File;
+use std::io::{self, BufReader};
 use clap::ArgMatches;
 use famlib::fastaio::{format_from_string, InputFormats};
+use famlib::edit_msa::EditMSA;
+use famlib::journal::{EditJournal, Journaled};
+use famlib::mapping::ReferenceNumbering;
+use famlib::masking::{Mask, MaskColumns};
+use famlib::ranges::{NamedRanges, RangeExpression};
+use famlib::seqs::{Alignment, SequenceAccesors};
 use std::result::Result::Err;
 
 use crate::data::{DataSink, DataSource};
@@ -16,6 +23,20 @@ pub mod pad;
 pub mod gap;
 pub mod combine;
 pub mod trim;
+pub mod pssm;
+pub mod logo;
+pub mod stats;
+pub mod pairwise;
+pub mod coevolution;
+pub mod neff;
+pub mod cluster;
+pub mod weights;
+pub mod kmer;
+pub mod matrix;
+pub mod align;
+pub mod map;
+pub mod slice;
+pub mod patch;
 
 /// A trait to encapsulate command line execution code.
 pub trait Command {
@@ -58,6 +79,184 @@ fn inputformat(matches: &ArgMatches) -> InputFormats {
     }
 }
 
+/// A reference sequence used to number positions, given by the global
+/// ref and ref-start arguments.
+pub struct Reference {
+    pub id: String,
+    pub start: usize,
+}
+
+impl Reference {
+    pub fn numbering<T: SequenceAccesors>(
+        &self,
+        seqs: &T
+    ) -> io::Result<ReferenceNumbering> {
+        ReferenceNumbering::from_collection(seqs, &self.id)
+            .map(|x| x.with_start(self.start))
+            .map_err(
+                |_| format!("Reference {} not found.\n", self.id).to_io_error()
+            )
+    }
+}
+
+/// Creates a Reference struct from the commandline arguments
+pub fn reference(matches: &ArgMatches) -> io::Result<Option<Reference>> {
+    match matches.value_of("ref") {
+        None => Ok(None),
+        Some(id) => {
+            let start = matches
+                .value_of("ref-start")
+                .unwrap_or("1")
+                .parse::<usize>()
+                .map_err(|_| "Cannot parse reference start.\n".to_io_error())?;
+            Ok(Some(Reference { id: id.to_string(), start }))
+        }
+    }
+}
+
+/// The zero based column of a residue number of the reference.
+pub fn reference_column(
+    numbering: &ReferenceNumbering,
+    number: usize
+) -> io::Result<usize> {
+    numbering.column_of(number).ok_or_else(
+        || format!(
+            "Residue {} is not in reference {}.\n",
+            number,
+            numbering.id()
+        ).to_io_error()
+    )
+}
+
+/// A selection of columns given by a range expression in the cols
+/// argument. Names of ranges are read from the file of the ranges
+/// argument, and positions are residue numbers of the --ref sequence, if
+/// given.
+pub struct ColumnSelection {
+    pub expression: String,
+    pub ranges: Option<String>,
+    pub reference: Option<Reference>,
+}
+
+impl ColumnSelection {
+    /// Resolves the selection to sorted zero based columns. With a
+    /// reference, a range includes all the columns between its first and
+    /// last residues.
+    pub fn columns<T: SequenceAccesors>(
+        &self,
+        seqs: &T,
+        length: usize
+    ) -> io::Result<Vec<usize>> {
+        let names = match &self.ranges {
+            Some(path) => {
+                let file = File::open(path).map_err(
+                    |_| format!("Cannot open file: {}.\n", path).to_io_error()
+                )?;
+                NamedRanges::from_reader(BufReader::new(file))
+                    .map_err(|e| format!("{}.\n", e).to_io_error())?
+            }
+            None => NamedRanges::new(),
+        };
+        let invalid = |e: Box<dyn std::error::Error>| {
+            format!("{}.\n", e).to_io_error()
+        };
+        let expression = RangeExpression::parse_with_names(
+            &self.expression,
+            &names
+        ).map_err(invalid)?;
+        match &self.reference {
+            None => Ok(
+                expression
+                    .positions(1, length)
+                    .map_err(invalid)?
+                    .into_iter()
+                    .map(|x| x - 1)
+                    .collect()
+            ),
+            Some(reference) => {
+                let numbering = reference.numbering(seqs)?;
+                let residues = numbering.position_map().residues();
+                if residues == 0 {
+                    return Err(
+                        format!("Reference {} has no residues.\n", reference.id)
+                            .to_io_error()
+                    );
+                }
+                let last = reference.start + residues - 1;
+                let mut columns = vec![];
+                for (a, b) in expression
+                    .intervals(reference.start, last)
+                    .map_err(invalid)?
+                {
+                    let first = reference_column(&numbering, a)?;
+                    let last = reference_column(&numbering, b)?;
+                    columns.extend(first..=last);
+                }
+                Ok(columns)
+            }
+        }
+    }
+}
+
+/// Creates a ColumnSelection struct from the commandline arguments, if a
+/// range expression is given.
+pub fn column_selection(
+    matches: &ArgMatches
+) -> io::Result<Option<ColumnSelection>> {
+    match matches.value_of("cols") {
+        None => Ok(None),
+        Some(expression) => Ok(
+            Some(
+                ColumnSelection {
+                    expression: expression.to_string(),
+                    ranges: matches.value_of("ranges").map(String::from),
+                    reference: reference(matches)?,
+                }
+            )
+        ),
+    }
+}
+
+/// Reads the mask from the commandline arguments, if present.
+pub fn mask(matches: &ArgMatches) -> io::Result<Option<Mask>> {
+    matches
+        .value_of("mask")
+        .map(
+            |x| Mask::from_name(x).ok_or_else(
+                || format!("Invalid mask: {}.\n", x).to_io_error()
+            )
+        )
+        .transpose()
+}
+
+/// Saves the journal as a patch file, if a path is given.
+pub fn save_journal(
+    path: Option<&str>,
+    journal: &EditJournal,
+) -> io::Result<()> {
+    match path {
+        Some(path) => journal.write_patch(&mut File::create(path)?),
+        None => Ok(()),
+    }
+}
+
+/// Removes the given columns of the alignment, or masks them if a mask is
+/// given. The changes are saved as a patch file if a journal path is given.
+pub fn remove_or_mask_columns(
+    msa: &mut Alignment,
+    columns: Vec<usize>,
+    mask: Option<Mask>,
+    journal_path: Option<&str>,
+) -> io::Result<()> {
+    let mut journal = EditJournal::new();
+    let mut journaled = Journaled::new(msa, &mut journal);
+    match mask {
+        Some(mask) => journaled.mask_columns(&columns, mask)?,
+        None => journaled.remove_columns(columns)?,
+    }
+    save_journal(journal_path, &journal)
+}
+
 #[allow(dead_code)]
 pub trait ToError {
     fn to_error(&self) -> Result<(), io::Error>;
@@ -0,0 +1,105 @@
+use std::io::{self, Write};
+use clap::ArgMatches;
+use famlib::clustering::neff;
+use famlib::seqs::SequenceAccesors;
+use famlib::similarity::metric_by_name;
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, reference, Command, Reference, ToError};
+
+pub struct NeffCommand {}
+
+/// Options of the neff command.
+pub struct NeffOptions {
+    pub identity: f64,
+    pub metric: String,
+    pub exclude_gaps: bool,
+    pub per_column: bool,
+    pub threads: usize,
+}
+
+impl NeffCommand {
+    pub fn neff_command(
+        fs: DataSource,
+        fo: DataSink,
+        options: NeffOptions,
+        reference: Option<Reference>,
+    ) -> io::Result<()> {
+        let msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let numbering = reference
+            .map(|x| x.numbering(&msa))
+            .transpose()?;
+        let metric = metric_by_name(&options.metric, options.exclude_gaps)
+            .map_err(|e| format!("{}.\n", e).to_io_error())?;
+        let result = neff(&msa, metric, options.identity, options.threads)
+            .map_err(|e| e.to_string().to_io_error())?;
+        let mut writer = fo.writer()?;
+        writeln!(writer, "Number of sequences: {}", msa.size())?;
+        writeln!(writer, "Similarity threshold: {}", options.identity)?;
+        writeln!(writer, "Neff: {:.2}", result.total)?;
+        if msa.length() > 0 {
+            writeln!(
+                writer,
+                "Neff / sqrt(length): {:.2}",
+                result.total / (msa.length() as f64).sqrt()
+            )?;
+        }
+        if options.per_column {
+            match numbering {
+                Some(_) => writeln!(writer, "\ncolumn\tref\tneff")?,
+                None => writeln!(writer, "\ncolumn\tneff")?,
+            }
+            for (i, x) in result.per_column.iter().enumerate() {
+                write!(writer, "{}\t", i + 1)?;
+                if let Some(numbering) = &numbering {
+                    match numbering.number_at(i) {
+                        Some(n) => write!(writer, "{}\t", n)?,
+                        None => write!(writer, "-\t")?,
+                    }
+                }
+                writeln!(writer, "{:.2}", x)?;
+            }
+        }
+        writer.flush()
+    }
+}
+
+impl Command for NeffCommand {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("neff") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let identity = m
+                .value_of("identity")
+                .unwrap()
+                .parse::<f64>()
+                .map_err(|_| "Cannot parse identity.\n".to_io_error())?;
+            let threads = m
+                .value_of("threads")
+                .map(|x| x.parse::<usize>())
+                .transpose()
+                .map_err(|_| "Cannot parse threads.\n".to_io_error())?
+                .unwrap_or(0);
+            let options = NeffOptions {
+                identity,
+                metric: m.value_of("metric").unwrap().to_string(),
+                exclude_gaps: m.is_present("exclude_gaps"),
+                per_column: m.is_present("per_column"),
+                threads,
+            };
+            Self::neff_command(input, output, options, reference(m)?)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("neff")
+            .is_some()
+    }
+}
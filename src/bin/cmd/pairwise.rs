@@ -0,0 +1,105 @@
+use std::io;
+use clap::ArgMatches;
+use famlib::pairwise::{PairwiseIdentity, PairwiseValues};
+use famlib::similarity::metric_by_name;
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, Command, ToError};
+
+pub struct Pairwise {}
+
+/// Output options of the pairwise command.
+pub struct PairwiseOptions {
+    pub out_format: String,
+    pub values: PairwiseValues,
+    pub threshold: Option<f64>,
+    pub threads: usize,
+    pub metric: String,
+    pub exclude_gaps: bool,
+}
+
+impl Pairwise {
+    pub fn pairwise_command(
+        fs: DataSource,
+        fo: DataSink,
+        options: PairwiseOptions,
+    ) -> io::Result<()> {
+        let msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let metric = metric_by_name(&options.metric, options.exclude_gaps)
+            .map_err(|e| format!("{}.\n", e).to_io_error())?;
+        let pairwise = PairwiseIdentity::new(&msa)
+            .with_threads(options.threads)
+            .with_metric(metric);
+        let writer = fo.writer()?;
+        match options.out_format.as_str() {
+            "square" => pairwise
+                .matrix()
+                .map_err(|e| e.to_string().to_io_error())?
+                .write_tsv(writer, options.values),
+            "phylip" => pairwise
+                .matrix()
+                .map_err(|e| e.to_string().to_io_error())?
+                .write_phylip(writer),
+            "edges" => {
+                // Distance thresholds are maximum values, identity
+                // thresholds are minimum values.
+                let min_identity = match (options.values, options.threshold) {
+                    (_, None) => f64::NEG_INFINITY,
+                    (PairwiseValues::Identity, Some(t)) => t,
+                    (PairwiseValues::Distance, Some(t)) => 1.0 - t,
+                };
+                pairwise
+                    .edges(min_identity)
+                    .map_err(|e| e.to_string().to_io_error())?
+                    .write_tsv(writer, options.values)
+            }
+            x => format!("Invalid output format: {}.\n", x).to_error(),
+        }
+    }
+}
+
+impl Command for Pairwise {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("pairwise") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let values = match m.value_of("values").unwrap() {
+                "identity" => PairwiseValues::Identity,
+                "distance" => PairwiseValues::Distance,
+                x => return format!("Invalid values: {}.\n", x).to_error(),
+            };
+            let threshold = m
+                .value_of("threshold")
+                .map(|x| x.parse::<f64>())
+                .transpose()
+                .map_err(|_| "Cannot parse threshold.\n".to_io_error())?;
+            let threads = m
+                .value_of("threads")
+                .map(|x| x.parse::<usize>())
+                .transpose()
+                .map_err(|_| "Cannot parse threads.\n".to_io_error())?
+                .unwrap_or(0);
+            let options = PairwiseOptions {
+                out_format: m.value_of("out-format").unwrap().to_string(),
+                values,
+                threshold,
+                threads,
+                metric: m.value_of("metric").unwrap().to_string(),
+                exclude_gaps: m.is_present("exclude_gaps"),
+            };
+            Self::pairwise_command(input, output, options)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("pairwise")
+            .is_some()
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use clap::ArgMatches;
+use famlib::journal::EditJournal;
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, inputformat, Command, ToError};
+
+pub struct PatchCommand {}
+
+impl PatchCommand {
+    fn read_patch(path: &str) -> io::Result<EditJournal> {
+        let file = File::open(path)?;
+        EditJournal::from_patch(BufReader::new(file))
+            .map_err(|e| format!("Cannot read patch: {}.\n", e).to_io_error())
+    }
+    /// Applies the changes of a patch, or undoes them if `revert` is true.
+    pub fn apply_patch(
+        fs: DataSource,
+        fo: DataSink,
+        patch: &str,
+        revert: bool,
+    ) -> io::Result<()> {
+        let mut journal = Self::read_patch(patch)?;
+        if revert {
+            journal = journal.inverse();
+        }
+        let mut seqs = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?;
+        journal
+            .apply(&mut seqs)
+            .map_err(|e| format!("Cannot apply patch: {}.\n", e).to_io_error())?;
+        fo.write_fasta(&seqs)
+    }
+    /// Writes the patch that transforms the input into the target.
+    pub fn diff(
+        fs: DataSource,
+        target: DataSource,
+        fo: DataSink,
+    ) -> io::Result<()> {
+        let old = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?;
+        let new = target
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from target.\n".to_io_error()
+            )?;
+        EditJournal::diff(&old, &new).write_patch(&mut fo.writer()?)
+    }
+}
+
+impl Command for PatchCommand {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("patch") {
+            match m.subcommand() {
+                ("apply", Some(m1)) | ("revert", Some(m1)) => {
+                    let input = datasource(m1);
+                    let output = datasink(m1);
+                    let patch = m1.value_of("patch").unwrap();
+                    let revert = m.subcommand_name() == Some("revert");
+                    Self::apply_patch(input, output, patch, revert)?
+                }
+                ("diff", Some(m1)) => {
+                    let input = datasource(m1);
+                    let output = datasink(m1);
+                    let target = DataSource::from(
+                        m1.value_of("to").unwrap(),
+                        inputformat(m1)
+                    );
+                    Self::diff(input, target, output)?
+                }
+                _ => eprintln!("No subcommand provided"),
+            }
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("patch")
+            .is_some()
+    }
+}
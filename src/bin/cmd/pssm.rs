@@ -0,0 +1,126 @@
+use std::io;
+use clap::ArgMatches;
+use famlib::clustering::{Clusterer, Hobohm1};
+use famlib::pssm::{ProfileBuilder, ProfileValues};
+use famlib::seqs::SequenceAccesors;
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, reference, Command, Reference, ToError};
+
+pub struct Pssm {}
+
+/// Output options of the pssm command.
+pub struct PssmOptions {
+    pub is_protein: bool,
+    pub pseudocount: f64,
+    pub weights_identity: Option<f64>,
+    pub out_format: String,
+    pub values: ProfileValues,
+    pub reference: Option<String>,
+    pub numbering: Option<Reference>,
+}
+
+impl Pssm {
+    pub fn pssm_command(
+        fs: DataSource,
+        fo: DataSink,
+        options: PssmOptions,
+    ) -> io::Result<()> {
+        let msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let mut builder = ProfileBuilder::new(&msa)
+            .with_pseudocount(options.pseudocount);
+        if !options.is_protein {
+            builder = builder.as_dna();
+        }
+        if let Some(identity) = options.weights_identity {
+            let mut clusterer = Hobohm1::new(&msa)
+                .with_similarity(identity);
+            let weights = clusterer
+                .clusterize()
+                .and_then(|_| clusterer.weights())
+                .map_err(|e| e.to_string().to_io_error())?;
+            builder = builder.with_weights(weights);
+        }
+        let profile = builder
+            .build()
+            .map_err(|e| e.to_string().to_io_error())?;
+        let writer = fo.writer()?;
+        match options.out_format.to_lowercase().as_str() {
+            "tsv" => {
+                let numbering = options
+                    .numbering
+                    .as_ref()
+                    .map(|x| x.numbering(&msa))
+                    .transpose()?;
+                profile.write_tsv(writer, options.values, numbering.as_ref())
+            }
+            "json" => profile.write_json(writer),
+            "psiblast" => {
+                let id = options
+                    .reference
+                    .as_ref()
+                    .or_else(|| options.numbering.as_ref().map(|x| &x.id));
+                let reference = match id {
+                    Some(id) => msa.get_by_id(id).ok_or_else(
+                        || format!("Reference {} not found.\n", id)
+                            .to_io_error()
+                    )?,
+                    None => msa.get(0).ok_or_else(
+                        || "Input alignment is empty.\n".to_io_error()
+                    )?,
+                };
+                profile.write_psiblast(writer, reference)
+            }
+            x => format!("Invalid output format: {}.\n", x).to_error(),
+        }
+    }
+}
+
+impl Command for Pssm {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("pssm") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let values = match m.value_of("values").unwrap() {
+                "counts" => ProfileValues::Counts,
+                "freqs" => ProfileValues::Frequencies,
+                "log-odds" => ProfileValues::LogOdds,
+                x => return format!("Invalid values: {}.\n", x).to_error(),
+            };
+            let pseudocount = m
+                .value_of("pseudocount")
+                .unwrap()
+                .parse::<f64>()
+                .map_err(|_| "Cannot parse pseudocount.\n".to_io_error())?;
+            let weights_identity = m
+                .value_of("weights-identity")
+                .map(|x| x.parse::<f64>())
+                .transpose()
+                .map_err(
+                    |_| "Cannot parse weights identity.\n".to_io_error()
+                )?;
+            let options = PssmOptions {
+                is_protein: !m.is_present("is_nucleic"),
+                pseudocount,
+                weights_identity,
+                out_format: m.value_of("out-format").unwrap().to_string(),
+                values,
+                reference: m.value_of("reference").map(String::from),
+                numbering: reference(m)?,
+            };
+            Self::pssm_command(input, output, options)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("pssm")
+            .is_some()
+    }
+}
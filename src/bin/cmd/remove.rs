@@ -1,26 +1,38 @@
 
 use std::io::{self, ErrorKind};
 use crate::data::{DataSink, DataSource};
-use super::{Command, datasink, datasource};
+use super::{Command, datasink, datasource, save_journal};
 use clap::{ArgMatches, Values};
 use famlib::{edit_msa::EditMSA, seqs::SequenceAccesors};
+use famlib::journal::{EditJournal, JournalEntry, Journaled};
 
 pub struct Remove {}
 
 /// Remove Sequence command
 impl Remove {
-    /// Remove Rows and columns from a MSA
+    /// Remove Rows and columns from a MSA. The changes are saved as a patch
+    /// file if a journal path is given.
     pub fn remove_command(
         fs: DataSource,
         fo: DataSink,
         rows: Vec<usize>,
         columns: Vec<usize>,
+        journal_path: Option<&str>,
     ) -> io::Result<()> {
         let mut input = fs
             .get_sequence_collection()
             .unwrap();
+        let mut journal = EditJournal::new();
         for i in rows {
-            input.remove(i);
+            if let Some(removed) = input.remove(i) {
+                journal.record(
+                    JournalEntry::RemoveRow {
+                        id: removed.id().to_string(),
+                        index: i,
+                        content: removed.seq().cloned().unwrap_or_default(),
+                    }
+                );
+            }
         }
         if !columns.is_empty() {
             let mut msa = match input.to_msa() {
@@ -33,7 +45,8 @@ impl Remove {
                     ));
                 }
             };
-            match msa.remove_columns(columns) {
+            let mut journaled = Journaled::new(&mut msa, &mut journal);
+            match journaled.remove_columns(columns) {
                 Ok(_) => {},
                 Err(x) => {
                     return  Err(std::io::Error::new(
@@ -44,7 +57,8 @@ impl Remove {
             }
             input = msa.seq_col_owned();
         }
-        fo.write_fasta(&input)
+        fo.write_fasta(&input)?;
+        save_journal(journal_path, &journal)
     }
 }
 impl Command for Remove {
@@ -67,6 +81,7 @@ impl Command for Remove {
                 sink,
                 rows,
                 cols,
+                m.value_of("journal"),
             )?
         }
         Ok(())
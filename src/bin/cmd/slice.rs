@@ -0,0 +1,69 @@
+use std::io;
+use clap::ArgMatches;
+use famlib::edit_msa::EditMSA;
+use famlib::journal::{EditJournal, Journaled};
+use crate::data::{DataSink, DataSource};
+use super::{
+    column_selection, datasink, datasource, save_journal, ColumnSelection,
+    Command, ToError
+};
+
+pub struct SliceCommand {}
+
+impl SliceCommand {
+    /// Keeps the selected columns of the alignment and removes the rest.
+    /// The removed columns are saved as a patch file if a journal path is
+    /// given.
+    pub fn slice_command(
+        fs: DataSource,
+        fo: DataSink,
+        selection: ColumnSelection,
+        journal_path: Option<&str>,
+    ) -> io::Result<()> {
+        let mut msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let mut keep = vec![false; msa.length()];
+        for column in selection.columns(&msa, msa.length())? {
+            keep[column] = true;
+        }
+        let removed = keep
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| !**x)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let mut journal = EditJournal::new();
+        Journaled::new(&mut msa, &mut journal).remove_columns(removed)?;
+        fo.write_fasta(&msa)?;
+        save_journal(journal_path, &journal)
+    }
+}
+
+impl Command for SliceCommand {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("slice") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let selection = column_selection(m)?
+                .ok_or_else(|| "No columns selected.\n".to_io_error())?;
+            Self::slice_command(
+                input,
+                output,
+                selection,
+                m.value_of("journal"),
+            )?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("slice")
+            .is_some()
+    }
+}
@@ -0,0 +1,49 @@
+use std::io;
+use clap::ArgMatches;
+use famlib::stats::AlignmentStats;
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, Command, ToError};
+
+pub struct Stats {}
+
+impl Stats {
+    pub fn stats_command(
+        fs: DataSource,
+        fo: DataSink,
+        json: bool,
+        expanded: bool,
+    ) -> io::Result<()> {
+        let msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let stats = AlignmentStats::from_msa(&msa);
+        let writer = fo.writer()?;
+        match json {
+            true => stats.write_json(writer),
+            false => stats.write_text(writer, expanded),
+        }
+    }
+}
+
+impl Command for Stats {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("stats") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let json = m.value_of("out-format").unwrap() == "json";
+            let expanded = m.is_present("expanded");
+            Self::stats_command(input, output, json, expanded)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("stats")
+            .is_some()
+    }
+}
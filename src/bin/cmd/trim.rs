@@ -1,9 +1,15 @@
 use std::io::{Result, Error, ErrorKind::InvalidData};
-use famlib::trim::TrimBufferedSequenceCollection;
+use famlib::gapping::PadWithGaps;
+use famlib::masking::Mask;
+use famlib::seqs::{Alignment, SequenceCollection};
+use famlib::trim::{TrimBufferedSequenceCollection, TrimmedColumns};
 
 use crate::data::{DataSink, DataSource};
 
-use super::{datasink, datasource, Command};
+use super::{
+    datasink, datasource, mask, reference, remove_or_mask_columns, Command,
+    Reference, ToError
+};
 
 pub struct Trim{ }
 
@@ -13,7 +19,13 @@ impl Trim {
         fo: DataSink,
         right: usize,
         left: usize,
+        reference: Option<Reference>,
     ) -> Result<()> {
+        if let Some(reference) = reference {
+            return Self::trim_fixed_by_reference(
+                fs, fo, right, left, reference
+            );
+        }
         let input = fs
             .get_buffered_sequence_collection()
             .unwrap();
@@ -27,12 +39,76 @@ impl Trim {
             .map_err(Into::into)
 
     }
+    /// Trims a number of residues of the reference sequence from each end,
+    /// with all the columns before the first or after the last kept
+    /// residue.
+    fn trim_fixed_by_reference(
+        fs: DataSource,
+        fo: DataSink,
+        left: usize,
+        right: usize,
+        reference: Reference,
+    ) -> Result<()> {
+        let input = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?;
+        let numbering = reference.numbering(&input)?;
+        let map = numbering.position_map();
+        if left + right > map.residues() {
+            return "Cannot trim more residues than the reference has.\n"
+                .to_error();
+        }
+        let left = match left {
+            0 => 0,
+            x => map.column_of(x - 1).unwrap() + 1,
+        };
+        let right = match right {
+            0 => 0,
+            x => map.columns() - map.column_of(map.residues() - x).unwrap(),
+        };
+        let result = famlib::trim::Trim::trim_fixed(&input, left, right);
+        fo.write_fasta(&result)
+    }
+    /// Masks the columns selected from an alignment of the input sequences,
+    /// padded to the same length. The changes are saved as a patch file if a
+    /// journal path is given.
+    fn mask_trimmed_columns(
+        fs: DataSource,
+        fo: DataSink,
+        mask: Mask,
+        journal_path: Option<&str>,
+        select: impl Fn(&Alignment) -> Vec<usize>,
+    ) -> Result<()> {
+        let input = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?;
+        let padded: SequenceCollection = input.pad_with_gaps_to_max_length();
+        let mut msa = padded.to_msa()?;
+        let columns = select(&msa);
+        remove_or_mask_columns(&mut msa, columns, Some(mask), journal_path)?;
+        fo.write_fasta(&msa)
+    }
     pub fn trim_by_gaps(
         fs: DataSource,
         fo: DataSink,
         right: bool,
         left: bool,
+        mask: Option<Mask>,
+        journal_path: Option<&str>,
     ) -> Result<()> {
+        if let Some(mask) = mask {
+            return Self::mask_trimmed_columns(
+                fs,
+                fo,
+                mask,
+                journal_path,
+                |msa| msa.gapped_end_columns(right, left)
+            );
+        }
         let input = fs
             .get_sequence_collection()
             .unwrap();
@@ -45,7 +121,18 @@ impl Trim {
         fo: DataSink,
         right: bool,
         left: bool,
+        mask: Option<Mask>,
+        journal_path: Option<&str>,
     ) -> Result<()> {
+        if let Some(mask) = mask {
+            return Self::mask_trimmed_columns(
+                fs,
+                fo,
+                mask,
+                journal_path,
+                |msa| msa.terminal_gap_columns(right, left)
+            );
+        }
         let input = fs
             .get_sequence_collection()
             .unwrap();
@@ -75,7 +162,7 @@ impl Command for Trim {
                     .map(|x| x.parse::<usize>())
                     .unwrap_or(Ok(0))
                     .map_err(|e| Error::new(InvalidData, e))?;
-                Self::trim_fixed(input, sink, left, right)?;
+                Self::trim_fixed(input, sink, left, right, reference(m1)?)?;
             }
             if let Some(m1) = m.subcommand_matches("by-gaps") {
                 let input = datasource(m1);
@@ -86,7 +173,9 @@ impl Command for Trim {
                     right = true;
                     left = true
                 }
-                Self::trim_by_gaps(input, sink, left, right)?;
+                Self::trim_by_gaps(
+                    input, sink, left, right, mask(m1)?, m1.value_of("journal")
+                )?;
             }
             if let Some(m1) = m.subcommand_matches("by-terminal-gaps") {
                 let input = datasource(m1);
@@ -97,7 +186,9 @@ impl Command for Trim {
                     right = true;
                     left = true
                 }
-                Self::trim_by_terminal_gaps(input, sink, left, right)?;
+                Self::trim_by_terminal_gaps(
+                    input, sink, left, right, mask(m1)?, m1.value_of("journal")
+                )?;
             }
         }
         Ok(())
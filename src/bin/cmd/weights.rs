@@ -0,0 +1,108 @@
+use std::io::{self, Write};
+use clap::ArgMatches;
+use famlib::clustering::{Clusterer, Hobohm1};
+use famlib::seqs::SequenceAccesors;
+use famlib::similarity::metric_by_name;
+use famlib::weights::{normalize_weights, Gsc, Henikoff};
+use crate::data::{DataSink, DataSource};
+use super::{datasink, datasource, Command, ToError};
+
+pub struct WeightsCommand {}
+
+/// Options of the weights command.
+pub struct WeightsOptions {
+    pub method: String,
+    pub identity: f64,
+    pub metric: String,
+    pub exclude_gaps: bool,
+    pub gaps_as_residue: bool,
+    pub normalize: bool,
+    pub threads: usize,
+}
+
+impl WeightsCommand {
+    pub fn weights_command(
+        fs: DataSource,
+        fo: DataSink,
+        options: WeightsOptions,
+    ) -> io::Result<()> {
+        let msa = fs
+            .get_sequence_collection()
+            .ok_or_else(
+                || "Cannot get sequence collection from input.\n".to_io_error()
+            )?
+            .to_msa()
+            .map_err(|_| "Input is not an alignment.\n".to_io_error())?;
+        let metric = metric_by_name(&options.metric, options.exclude_gaps)
+            .map_err(|e| format!("{}.\n", e).to_io_error())?;
+        let weights = match options.method.as_str() {
+            "henikoff" => {
+                let henikoff = Henikoff::new(&msa);
+                match options.gaps_as_residue {
+                    true => henikoff.include_gaps().weights(),
+                    false => henikoff.weights(),
+                }
+            }
+            "gsc" => Gsc::new(&msa)
+                .with_metric(metric)
+                .with_threads(options.threads)
+                .weights(),
+            "cluster" => {
+                let mut clusterer = Hobohm1::new(&msa)
+                    .with_similarity(options.identity)
+                    .with_metric(metric);
+                clusterer
+                    .clusterize()
+                    .and_then(|_| clusterer.weights())
+            }
+            x => return format!("Invalid method: {}.\n", x).to_error(),
+        };
+        let mut weights = weights.map_err(|e| e.to_string().to_io_error())?;
+        if options.normalize {
+            normalize_weights(&mut weights);
+        }
+        let mut writer = fo.writer()?;
+        writeln!(writer, "id\tweight")?;
+        for (seq, w) in msa.iter().zip(&weights) {
+            writeln!(writer, "{}\t{:.6}", seq.id(), w)?;
+        }
+        writer.flush()
+    }
+}
+
+impl Command for WeightsCommand {
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        if let Some(m) = matches.subcommand_matches("weights") {
+            let input = datasource(m);
+            let output = datasink(m);
+            let identity = m
+                .value_of("identity")
+                .unwrap()
+                .parse::<f64>()
+                .map_err(|_| "Cannot parse identity.\n".to_io_error())?;
+            let threads = m
+                .value_of("threads")
+                .map(|x| x.parse::<usize>())
+                .transpose()
+                .map_err(|_| "Cannot parse threads.\n".to_io_error())?
+                .unwrap_or(0);
+            let options = WeightsOptions {
+                method: m.value_of("method").unwrap().to_string(),
+                identity,
+                metric: m.value_of("metric").unwrap().to_string(),
+                exclude_gaps: m.is_present("exclude_gaps"),
+                gaps_as_residue: m.is_present("gaps_as_residue"),
+                normalize: m.is_present("normalize"),
+                threads,
+            };
+            Self::weights_command(input, output, options)?
+        };
+        Ok(())
+    }
+
+    fn works_with(&self, matches: &ArgMatches) -> bool {
+        matches
+            .subcommand_matches("weights")
+            .is_some()
+    }
+}
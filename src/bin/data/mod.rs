@@ -18,7 +18,7 @@ use famlib::{
 use std::fs::File;
 use std::path::Path;
 use std::io;
-use std::io::stdout;
+use std::io::{stdout, BufWriter, Write};
 
 #[derive(Debug)]
 /// Representation of the reading input of a MSA or sequence collection.
@@ -91,6 +91,15 @@ impl DataSink {
             }
         }
     }
+    /// Creates a writer for non fasta outputs, like tables or reports.
+    pub fn writer(&self) -> io::Result<Box<dyn Write>> {
+        match self {
+            DataSink::StdOut => Ok(Box::new(BufWriter::new(stdout()))),
+            DataSink::FilePath(x) => {
+                Ok(Box::new(BufWriter::new(File::create(x)?)))
+            }
+        }
+    }
     /// Writes a Buffered Sequence collection to fasta file.
     pub fn write_buffered_to_fasta<T: BufferedSeqCollection>(
         &self,
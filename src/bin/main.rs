@@ -19,6 +19,20 @@ use cmd::{
     combine::Combine,
     remove::Remove,
     trim::Trim,
+    pssm::Pssm,
+    logo::Logo,
+    stats::Stats,
+    pairwise::Pairwise,
+    coevolution::Coevolution,
+    neff::NeffCommand,
+    cluster::ClusterCommand,
+    weights::WeightsCommand,
+    kmer::KmerCommand,
+    matrix::MatrixCommand,
+    align::AlignCommand,
+    map::MapCommand,
+    slice::SliceCommand,
+    patch::PatchCommand,
     Command,
     ToError
 };
@@ -167,6 +181,7 @@ fn add_remove_subcommand<'a>(
                     .help("Specify the input format: [Fasta, Plain]")
                     .default_value("fasta")
             )
+            .arg(journal_arg())
     );
     return app;
 }
@@ -272,7 +287,111 @@ fn add_delete_subcommand<'a>(
     _map: &Messages
 ) -> App<'a, 'a> {
     let edit = edit.subcommand(
-        SubCommand::with_name("delete")
+        add_column_range_args(
+            SubCommand::with_name("delete")
+                .arg(
+                    Arg::with_name("input")
+                        .short("i")
+                        .long("in")
+                        .takes_value(true)
+                        .help("The input file")
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("out")
+                        .takes_value(true)
+                        .help("The output file")
+                )
+                .arg(
+                    Arg::with_name("at")
+                        .long("at")
+                        .takes_value(true)
+                        .required_unless("cols")
+                        .conflicts_with("cols")
+                        .min_values(2)
+                        .help("X,Y positions of the edit point")
+                )
+                .arg(
+                    Arg::with_name("width")
+                        .short("w")
+                        .long("width")
+                        .required_unless("cols")
+                        .takes_value(true)
+                        .help(".")
+                )
+                .arg(
+                    Arg::with_name("height")
+                        .short("h")
+                        .long("height")
+                        .required_unless("cols")
+                        .takes_value(true)
+                        .help(".")
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .help("Specify the input format: [Fasta, Plain]")
+                        .default_value("fasta")
+                ),
+            false
+        )
+    );
+    return edit;
+}
+
+fn add_realign_subcommand<'a>(
+    edit: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    edit.subcommand(
+        add_aligner_args(
+            SubCommand::with_name("realign")
+                .about("Degap and realign a block of columns in place")
+                .arg(
+                    Arg::with_name("input")
+                        .short("i")
+                        .long("in")
+                        .takes_value(true)
+                        .help("The input file")
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("out")
+                        .takes_value(true)
+                        .help("The output file")
+                )
+                .arg(
+                    Arg::with_name("cols")
+                        .short("c")
+                        .long("cols")
+                        .takes_value(true)
+                        .required(true)
+                        .number_of_values(2)
+                        .help(
+                            "First and last columns of the block (first column has index 1)"
+                        )
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .help("Specify the input format: [Fasta, Plain]")
+                        .default_value("fasta")
+                )
+        )
+    )
+}
+
+fn add_script_subcommand<'a>(
+    edit: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    edit.subcommand(
+        SubCommand::with_name("script")
+            .about("Apply all the edits of a script file")
             .arg(
                 Arg::with_name("input")
                     .short("i")
@@ -288,28 +407,14 @@ fn add_delete_subcommand<'a>(
                     .help("The output file")
             )
             .arg(
-                Arg::with_name("at")
-                    .long("at")
-                    .takes_value(true)
-                    .required(true)
-                    .min_values(2)
-                    .help("X,Y positions of the edit point")
-            )
-            .arg(
-                Arg::with_name("width")
-                    .short("w")
-                    .long("width")
-                    .required(true)
+                Arg::with_name("script")
+                    .short("s")
+                    .long("script")
                     .takes_value(true)
-                    .help(".")
-            )
-            .arg(
-                Arg::with_name("height")
-                    .short("h")
-                    .long("height")
                     .required(true)
-                    .takes_value(true)
-                    .help(".")
+                    .help(
+                        "A TSV file with operation (insert, replace or delete), sequence ID or '*', position and content on each line, or a TOML file with an [[edit]] table for each edit"
+                    )
             )
             .arg(
                 Arg::with_name("format")
@@ -318,8 +423,7 @@ fn add_delete_subcommand<'a>(
                     .help("Specify the input format: [Fasta, Plain]")
                     .default_value("fasta")
             )
-    );
-    return edit;
+    )
 }
 
 fn add_edit_subcommand<'a>(
@@ -327,10 +431,15 @@ fn add_edit_subcommand<'a>(
     _map: &'a Messages
 ) -> App<'a, 'a> {
     let edit = SubCommand::with_name("edit")
-        .about("Edit MSA content");
+        .about("Edit MSA content")
+        .arg(journal_arg().global(true))
+        .arg(reference_arg().global(true))
+        .arg(reference_start_arg().global(true));
     let edit = add_replace_subcommand(edit, _map);
     let edit = add_insert_subcommand(edit, _map);
     let edit = add_delete_subcommand(edit, _map);
+    let edit = add_realign_subcommand(edit, _map);
+    let edit = add_script_subcommand(edit, _map);
     let app = app.subcommand(edit);
     return app;
 }
@@ -668,14 +777,38 @@ fn add_gap_subcommand<'a>(
                     .about("Remove columns with gaps in all positions")
             )
             .subcommand(
-                SubCommand::with_name("remove-columns")
-                    .about("Remove columns with gaps")
-                    .arg(
-                        Arg::with_name("by-freq")
-                            .long("by-freq")
-                            .takes_value(true)
-                            .help("The threshold to remove columns")
-                    )
+                add_reference_args(add_mask_arg(
+                    SubCommand::with_name("by-reference")
+                        .about(
+                            "Remove or mask the columns where a reference sequence has gaps"
+                        )
+                        .arg(
+                            Arg::with_name("id")
+                                .long("id")
+                                .takes_value(true)
+                                .help(
+                                    "The ID of the reference sequence [default: the --ref sequence]"
+                                )
+                        )
+                        .arg(journal_arg())
+                ))
+            )
+            .subcommand(
+                add_reference_args(add_column_range_args(
+                    add_mask_arg(
+                        SubCommand::with_name("remove-columns")
+                            .about("Remove or mask columns with gaps, or the selected columns")
+                            .arg(
+                                Arg::with_name("by-freq")
+                                    .long("by-freq")
+                                    .takes_value(true)
+                                    .conflicts_with("cols")
+                                    .help("The threshold to remove columns")
+                            )
+                            .arg(journal_arg())
+                    ),
+                    false
+                ))
             )
     );
     return app;
@@ -713,26 +846,29 @@ fn add_trim_command<'a>(
                     .global(true)
             )
             .subcommand(
-                SubCommand::with_name("by-gaps")
-                    .arg(
-                        Arg::with_name("right")
-                            .long("right")
-                            .takes_value(false)
-                            .help(
-                                "Trim from the right until no gaps are present"
-                            )
-                    )
-                    .arg(
-                        Arg::with_name("left")
-                            .long("left")
-                            .takes_value(false)
-                            .help(
-                                "Trim from the left until no gaps are present"
-                            )
-                    )
+                add_mask_arg(
+                    SubCommand::with_name("by-gaps")
+                        .arg(
+                            Arg::with_name("right")
+                                .long("right")
+                                .takes_value(false)
+                                .help(
+                                    "Trim from the right until no gaps are present"
+                                )
+                        )
+                        .arg(
+                            Arg::with_name("left")
+                                .long("left")
+                                .takes_value(false)
+                                .help(
+                                    "Trim from the left until no gaps are present"
+                                )
+                        )
+                        .arg(journal_arg().requires("mask"))
+                )
             )
             .subcommand(
-                SubCommand::with_name("fixed")
+                add_reference_args(SubCommand::with_name("fixed"))
                     .arg(
                         Arg::with_name("right")
                             .long("right")
@@ -751,25 +887,1178 @@ fn add_trim_command<'a>(
                     )
             )
             .subcommand(
-                SubCommand::with_name("by-terminal-gaps")
-                    .arg(
-                        Arg::with_name("right")
-                            .long("right")
-                            .takes_value(true)
-                            .help(
-                                "Trim from the right by terminal gaps"
-                            )
+                add_mask_arg(
+                    SubCommand::with_name("by-terminal-gaps")
+                        .arg(
+                            Arg::with_name("right")
+                                .long("right")
+                                .takes_value(true)
+                                .help(
+                                    "Trim from the right by terminal gaps"
+                                )
+                        )
+                        .arg(
+                            Arg::with_name("left")
+                                .long("left")
+                                .takes_value(true)
+                                .help(
+                                    "Trim from the left by terminal gaps"
+                                )
+                        )
+                        .arg(journal_arg().requires("mask"))
+                )
+            )
+
+    )
+}
+
+fn add_pssm_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        add_reference_args(SubCommand::with_name("pssm"))
+            .about("Compute a position specific scoring matrix of the MSA")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("is_nucleic")
+                    .long("is_nucleic")
+                    .takes_value(false)
+                    .help("Input sequences are nucleic acids")
+            )
+            .arg(
+                Arg::with_name("out-format")
+                    .long("out-format")
+                    .takes_value(true)
+                    .possible_values(&["tsv", "json", "psiblast"])
+                    .default_value("tsv")
+                    .help("The output format")
+            )
+            .arg(
+                Arg::with_name("values")
+                    .long("values")
+                    .takes_value(true)
+                    .possible_values(&["counts", "freqs", "log-odds"])
+                    .default_value("log-odds")
+                    .help("The values written in tsv format")
+            )
+            .arg(
+                Arg::with_name("pseudocount")
+                    .long("pseudocount")
+                    .takes_value(true)
+                    .default_value("1")
+                    .help("Total pseudocount weight added to each column")
+            )
+            .arg(
+                Arg::with_name("weights-identity")
+                    .long("weights-identity")
+                    .takes_value(true)
+                    .help(
+                        "Weight sequences by Hobohm1 clusters at this identity"
                     )
-                    .arg(
-                        Arg::with_name("left")
-                            .long("left")
-                            .takes_value(true)
-                            .help(
-                                "Trim from the left by terminal gaps"
-                            )
+            )
+            .arg(
+                Arg::with_name("reference")
+                    .long("reference")
+                    .takes_value(true)
+                    .help(
+                        "The ID of the reference sequence of psiblast format \
+                        [default: first sequence]"
+                    )
+            )
+    )
+}
+
+fn add_logo_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        SubCommand::with_name("logo")
+            .about("Draw a sequence logo of the MSA")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .required(true)
+                    .takes_value(true)
+                    .help("The output PNG or SVG file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("is_nucleic")
+                    .long("is_nucleic")
+                    .takes_value(false)
+                    .help("Use a nucleic acid alphabet and color scheme")
+            )
+            .arg(
+                Arg::with_name("image-format")
+                    .long("image-format")
+                    .takes_value(true)
+                    .possible_values(&["png", "svg"])
+                    .help(
+                        "The image format [default: from the output file \
+                        extension]"
                     )
             )
+            .arg(
+                Arg::with_name("start")
+                    .long("start")
+                    .takes_value(true)
+                    .help("First column of the logo, starting at 1")
+            )
+            .arg(
+                Arg::with_name("end")
+                    .long("end")
+                    .takes_value(true)
+                    .help("Last column of the logo, inclusive")
+            )
+            .arg(
+                Arg::with_name("column-width")
+                    .long("column-width")
+                    .takes_value(true)
+                    .default_value("20")
+                    .help("Width in pixels of each column")
+            )
+            .arg(
+                Arg::with_name("bit-height")
+                    .long("bit-height")
+                    .takes_value(true)
+                    .default_value("40")
+                    .help("Height in pixels of one bit of information")
+            )
+    )
+}
+
+fn add_stats_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        SubCommand::with_name("stats")
+            .about("Report summary statistics of the MSA")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("out-format")
+                    .long("out-format")
+                    .takes_value(true)
+                    .possible_values(&["text", "json"])
+                    .default_value("text")
+                    .help("The output format")
+            )
+            .arg(
+                Arg::with_name("expanded")
+                    .short("e")
+                    .long("expanded")
+                    .help("Show the statistics of every sequence in text format")
+            )
+    )
+}
 
+fn add_pairwise_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        SubCommand::with_name("pairwise")
+            .about("Compute the identity between all pairs of sequences")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("out-format")
+                    .long("out-format")
+                    .takes_value(true)
+                    .possible_values(&["square", "phylip", "edges"])
+                    .default_value("square")
+                    .help(
+                        "The output format. Phylip output always contains \
+                        distances"
+                    )
+            )
+            .arg(
+                Arg::with_name("values")
+                    .long("values")
+                    .takes_value(true)
+                    .possible_values(&["identity", "distance"])
+                    .default_value("identity")
+                    .help("Write identities or distances (1 - identity)")
+            )
+            .arg(
+                Arg::with_name("threshold")
+                    .long("threshold")
+                    .takes_value(true)
+                    .help(
+                        "Keep only edges with identity equal or greater, or \
+                        distance equal or lower, than this value"
+                    )
+            )
+            .arg(
+                Arg::with_name("threads")
+                    .short("t")
+                    .long("threads")
+                    .takes_value(true)
+                    .help("Number of threads [default: all available cores]")
+            )
+            .arg(
+                Arg::with_name("metric")
+                    .long("metric")
+                    .takes_value(true)
+                    .possible_values(&[
+                        "identity",
+                        "identity-shorter",
+                        "identity-aligned",
+                        "positives",
+                        "score"
+                    ])
+                    .default_value("identity")
+                    .help(
+                        "Similarity metric: identity over alignment length, \
+                        over the shorter sequence, over aligned residues, \
+                        BLOSUM62 positives or normalized BLOSUM62 score"
+                    )
+            )
+            .arg(
+                Arg::with_name("exclude_gaps")
+                    .long("exclude-gaps")
+                    .help("Ignore positions in which both sequences have gaps")
+            )
+    )
+}
+
+fn add_coevolution_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        add_reference_args(SubCommand::with_name("coevolution"))
+            .about("Compute mutual information between pairs of columns")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("is_nucleic")
+                    .long("is_nucleic")
+                    .takes_value(false)
+                    .help("Input sequences are nucleic acids")
+            )
+            .arg(
+                Arg::with_name("correction")
+                    .long("correction")
+                    .takes_value(true)
+                    .possible_values(&["none", "apc", "rcw"])
+                    .default_value("apc")
+                    .help("Correction applied to mutual information")
+            )
+            .arg(
+                Arg::with_name("weights-identity")
+                    .long("weights-identity")
+                    .takes_value(true)
+                    .help(
+                        "Weight sequences by Hobohm1 clusters at this identity"
+                    )
+            )
+            .arg(
+                Arg::with_name("max-gaps")
+                    .long("max-gaps")
+                    .takes_value(true)
+                    .default_value("1")
+                    .help("Ignore columns with a greater fraction of gaps")
+            )
+            .arg(
+                Arg::with_name("top")
+                    .long("top")
+                    .takes_value(true)
+                    .default_value("100")
+                    .help("Number of column pairs reported")
+            )
+            .arg(
+                Arg::with_name("min-separation")
+                    .long("min-separation")
+                    .takes_value(true)
+                    .default_value("1")
+                    .help("Minimum distance between columns of a pair")
+            )
+    )
+}
+
+fn add_neff_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        add_reference_args(SubCommand::with_name("neff"))
+            .about("Compute the effective number of sequences of the MSA")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("identity")
+                    .long("identity")
+                    .takes_value(true)
+                    .default_value("0.8")
+                    .help("Similarity threshold to count similar sequences")
+            )
+            .arg(
+                Arg::with_name("metric")
+                    .long("metric")
+                    .takes_value(true)
+                    .possible_values(&[
+                        "identity",
+                        "identity-shorter",
+                        "identity-aligned",
+                        "positives",
+                        "score"
+                    ])
+                    .default_value("identity")
+                    .help("Similarity metric used to compare sequences")
+            )
+            .arg(
+                Arg::with_name("exclude_gaps")
+                    .long("exclude-gaps")
+                    .help("Ignore positions in which both sequences have gaps")
+            )
+            .arg(
+                Arg::with_name("per_column")
+                    .long("per-column")
+                    .help("Report the effective number of sequences by column")
+            )
+            .arg(
+                Arg::with_name("threads")
+                    .short("t")
+                    .long("threads")
+                    .takes_value(true)
+                    .help("Number of threads [default: all available cores]")
+            )
+    )
+}
+
+fn add_cluster_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        SubCommand::with_name("cluster")
+            .about("Cluster sequences and keep one representative of each")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("method")
+                    .long("method")
+                    .takes_value(true)
+                    .possible_values(&["hobohm1", "hobohm2", "cdhit"])
+                    .default_value("hobohm1")
+                    .help(
+                        "The clustering algorithm. Hobohm methods require \
+                        aligned sequences"
+                    )
+            )
+            .arg(
+                Arg::with_name("identity")
+                    .long("identity")
+                    .takes_value(true)
+                    .default_value("0.62")
+                    .help("Minimum similarity to the cluster representative")
+            )
+            .arg(
+                Arg::with_name("representative")
+                    .long("representative")
+                    .takes_value(true)
+                    .possible_values(&["first", "longest", "fewest-gaps"])
+                    .help(
+                        "Preferred sequences for cluster representatives, \
+                        not supported by hobohm2 [default: longest for \
+                        cdhit, first for hobohm1]"
+                    )
+            )
+            .arg(
+                Arg::with_name("metric")
+                    .long("metric")
+                    .takes_value(true)
+                    .possible_values(&[
+                        "identity",
+                        "identity-shorter",
+                        "identity-aligned",
+                        "positives",
+                        "score"
+                    ])
+                    .help(
+                        "Similarity metric of hobohm1 and hobohm2: identity \
+                        over alignment length, over the shorter sequence, \
+                        over aligned residues, BLOSUM62 positives or \
+                        normalized BLOSUM62 score [default: identity]"
+                    )
+            )
+            .arg(
+                Arg::with_name("exclude_gaps")
+                    .long("exclude-gaps")
+                    .help(
+                        "Ignore positions in which both sequences have gaps, \
+                        with hobohm1 and hobohm2"
+                    )
+            )
+            .arg(
+                Arg::with_name("members")
+                    .long("members")
+                    .takes_value(true)
+                    .help("Write the members of each cluster to a TSV file")
+            )
+    )
+}
+
+fn add_weights_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        SubCommand::with_name("weights")
+            .about("Compute sequence weights of the MSA")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("method")
+                    .long("method")
+                    .takes_value(true)
+                    .possible_values(&["henikoff", "gsc", "cluster"])
+                    .default_value("henikoff")
+                    .help(
+                        "Weighting method: position-based (Henikoff), \
+                        tree-based (Gerstein, Sonnhammer & Chothia) or \
+                        the inverse of Hobohm1 cluster sizes"
+                    )
+            )
+            .arg(
+                Arg::with_name("identity")
+                    .long("identity")
+                    .takes_value(true)
+                    .default_value("0.62")
+                    .help("Similarity threshold of the cluster method")
+            )
+            .arg(
+                Arg::with_name("metric")
+                    .long("metric")
+                    .takes_value(true)
+                    .possible_values(&[
+                        "identity",
+                        "identity-shorter",
+                        "identity-aligned",
+                        "positives",
+                        "score"
+                    ])
+                    .default_value("identity")
+                    .help("Similarity metric of the gsc and cluster methods")
+            )
+            .arg(
+                Arg::with_name("exclude_gaps")
+                    .long("exclude-gaps")
+                    .help("Ignore positions in which both sequences have gaps")
+            )
+            .arg(
+                Arg::with_name("gaps_as_residue")
+                    .long("gaps-as-residue")
+                    .help("Count gaps as another residue in henikoff weights")
+            )
+            .arg(
+                Arg::with_name("normalize")
+                    .long("normalize")
+                    .help(
+                        "Scale weights to sum one. Henikoff and gsc weights \
+                        are always normalized"
+                    )
+            )
+            .arg(
+                Arg::with_name("threads")
+                    .short("t")
+                    .long("threads")
+                    .takes_value(true)
+                    .help("Number of threads [default: all available cores]")
+            )
+    )
+}
+
+fn add_kmer_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        SubCommand::with_name("kmer")
+            .about("Compute alignment-free k-mer distances between sequences")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("method")
+                    .long("method")
+                    .takes_value(true)
+                    .possible_values(&["profile", "minhash"])
+                    .default_value("profile")
+                    .help(
+                        "Fractional common k-mer distance or MinHash \
+                        (Mash) distance"
+                    )
+            )
+            .arg(
+                Arg::with_name("kmer_size")
+                    .short("k")
+                    .long("kmer-size")
+                    .takes_value(true)
+                    .default_value("3")
+                    .help("Length of the words")
+            )
+            .arg(
+                Arg::with_name("sketch_size")
+                    .long("sketch-size")
+                    .takes_value(true)
+                    .default_value("128")
+                    .help("Number of hashes of MinHash sketches")
+            )
+            .arg(
+                Arg::with_name("out-format")
+                    .long("out-format")
+                    .takes_value(true)
+                    .possible_values(&["square", "phylip", "nearest"])
+                    .default_value("square")
+                    .help(
+                        "Write a distance matrix, or the nearest neighbours \
+                        of each sequence"
+                    )
+            )
+            .arg(
+                Arg::with_name("neighbours")
+                    .long("neighbours")
+                    .takes_value(true)
+                    .default_value("5")
+                    .help("Number of nearest neighbours of each sequence")
+            )
+            .arg(
+                Arg::with_name("threads")
+                    .short("t")
+                    .long("threads")
+                    .takes_value(true)
+                    .help("Number of threads [default: all available cores]")
+            )
+    )
+}
+
+fn add_matrix_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        SubCommand::with_name("matrix")
+            .about("Compute a substitution matrix from the MSA")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("values")
+                    .long("values")
+                    .takes_value(true)
+                    .possible_values(&["counts", "frequencies", "log-odds"])
+                    .default_value("counts")
+                    .help(
+                        "Write pair counts, pair frequencies or BLOSUM like log-odds scores"
+                    )
+            )
+            .arg(
+                Arg::with_name("identity")
+                    .long("identity")
+                    .takes_value(true)
+                    .default_value("0.62")
+                    .help(
+                        "Minimum identity to cluster sequences for log-odds scores"
+                    )
+            )
+            .arg(
+                Arg::with_name("pseudocount")
+                    .long("pseudocount")
+                    .takes_value(true)
+                    .default_value("1")
+                    .help("Pseudocount added to each pair for log-odds scores")
+            )
+            .arg(
+                Arg::with_name("threads")
+                    .short("t")
+                    .long("threads")
+                    .takes_value(true)
+                    .help("Number of threads [default: all available cores]")
+            )
+            .arg(
+                Arg::with_name("out-format")
+                    .long("out-format")
+                    .takes_value(true)
+                    .possible_values(&["tsv", "ncbi"])
+                    .default_value("tsv")
+                    .help("The output format")
+            )
+            .arg(
+                Arg::with_name("is_nucleic")
+                    .long("is_nucleic")
+                    .takes_value(false)
+                    .help("Input sequences are nucleic acids")
+            )
+    )
+}
+
+// Adds the arguments of the scoring matrix, gap penalties and alignment
+// mode.
+fn add_aligner_args<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app
+        .arg(
+            Arg::with_name("mode")
+                .long("mode")
+                .takes_value(true)
+                .possible_values(&["global", "local", "semiglobal"])
+                .default_value("global")
+                .help("The kind of alignment")
+        )
+        .arg(
+            Arg::with_name("matrix")
+                .long("matrix")
+                .takes_value(true)
+                .help(
+                    "A built-in scoring matrix (BLOSUM45, BLOSUM62, BLOSUM80, PAM30, PAM70, PAM250) or a file in NCBI format [default: BLOSUM62]"
+                )
+        )
+        .arg(
+            Arg::with_name("gap-open")
+                .long("gap-open")
+                .takes_value(true)
+                .default_value("10")
+                .help("Penalty of the first position of a gap")
+        )
+        .arg(
+            Arg::with_name("gap-extend")
+                .long("gap-extend")
+                .takes_value(true)
+                .default_value("0.5")
+                .help("Penalty of each additional position of a gap")
+        )
+        .arg(
+            Arg::with_name("is_nucleic")
+                .long("is_nucleic")
+                .takes_value(false)
+                .help(
+                    "Score nucleic acids with +5 for matches and -4 for mismatches"
+                )
+        )
+}
+
+fn add_align_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        SubCommand::with_name("align")
+            .about("Align sequences")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+                    .global(true)
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+                    .global(true)
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+                    .global(true)
+            )
+            .subcommand(
+                add_aligner_args(
+                    SubCommand::with_name("pair")
+                        .about(
+                            "Align each input sequence to each target sequence, or all pairs of input sequences"
+                        )
+                        .arg(
+                            Arg::with_name("target")
+                                .long("target")
+                                .takes_value(true)
+                                .help("A file with target sequences")
+                        )
+                        .arg(
+                            Arg::with_name("out-format")
+                                .long("out-format")
+                                .takes_value(true)
+                                .possible_values(&["table", "fasta"])
+                                .default_value("table")
+                                .help("The output format")
+                        )
+                )
+            )
+            .subcommand(
+                add_aligner_args(
+                    SubCommand::with_name("add")
+                        .about(
+                            "Add unaligned sequences to the input alignment, keeping its columns"
+                        )
+                        .arg(
+                            Arg::with_name("add")
+                                .long("add")
+                                .takes_value(true)
+                                .required(true)
+                                .help("A file with the sequences to add")
+                        )
+                        .arg(
+                            Arg::with_name("keeplength")
+                                .long("keeplength")
+                                .takes_value(false)
+                                .help(
+                                    "Drop residues that are not aligned to a column, instead of adding new columns"
+                                )
+                        )
+                )
+            )
+            .subcommand(
+                add_aligner_args(
+                    SubCommand::with_name("msa")
+                        .about(
+                            "Build a multiple sequence alignment of the input sequences by progressive alignment"
+                        )
+                        .arg(
+                            Arg::with_name("kmer_size")
+                                .short("k")
+                                .long("kmer-size")
+                                .takes_value(true)
+                                .default_value("3")
+                                .help("Length of the words used to build the guide tree")
+                        )
+                        .arg(
+                            Arg::with_name("refine")
+                                .long("refine")
+                                .takes_value(true)
+                                .default_value("0")
+                                .help(
+                                    "Number of rounds realigning each sequence to the rest"
+                                )
+                        )
+                        .arg(
+                            Arg::with_name("threads")
+                                .short("t")
+                                .long("threads")
+                                .takes_value(true)
+                                .help("Number of threads [default: all available cores]")
+                        )
+                )
+            )
+    )
+}
+
+fn add_map_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        SubCommand::with_name("map")
+            .about(
+                "Map positions between residues of aligned sequences and alignment columns"
+            )
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+            )
+            .arg(
+                Arg::with_name("from")
+                    .long("from")
+                    .takes_value(true)
+                    .help(
+                        "Id of the sequence whose residue numbers are given [default: alignment columns]"
+                    )
+            )
+            .arg(
+                Arg::with_name("to")
+                    .long("to")
+                    .takes_value(true)
+                    .help(
+                        "Id of the sequence whose residue numbers are reported [default: alignment columns]"
+                    )
+            )
+            .arg(
+                Arg::with_name("positions")
+                    .short("p")
+                    .long("positions")
+                    .takes_value(true)
+                    .min_values(1)
+                    .required_unless("tsv")
+                    .conflicts_with("tsv")
+                    .help("Positions to map (first position has index 1)")
+            )
+            .arg(
+                Arg::with_name("tsv")
+                    .long("tsv")
+                    .takes_value(true)
+                    .help(
+                        "A tab separated file with positions in the first column, the mapped positions are added as a new column"
+                    )
+            )
+    )
+}
+
+/// Adds the cols and ranges arguments, to select columns with a range
+/// expression.
+fn add_column_range_args<'a>(app: App<'a, 'a>, required: bool) -> App<'a, 'a> {
+    app
+        .arg(
+            Arg::with_name("cols")
+                .long("cols")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .required(required)
+                .help(
+                    "Columns as a comma separated list of positions, ranges and range names, like '1-50,75,100-'. Negative positions count from the end"
+                )
+        )
+        .arg(
+            Arg::with_name("ranges")
+                .long("ranges")
+                .takes_value(true)
+                .help("A file with a range name and a range expression on each line")
+        )
+}
+
+fn add_mask_arg<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app.arg(
+        Arg::with_name("mask")
+            .long("mask")
+            .takes_value(true)
+            .help(
+                "Mask the columns instead of removing them: 'lower' converts residues to lowercase, a symbol like 'X' or 'N' replaces them"
+            )
+    )
+}
+
+fn reference_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ref")
+        .long("ref")
+        .takes_value(true)
+        .help(
+            "Give and report positions as residue numbers of the sequence with this ID, instead of alignment columns"
+        )
+}
+
+fn reference_start_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ref-start")
+        .long("ref-start")
+        .takes_value(true)
+        .requires("ref")
+        .help("Number of the first residue of the reference [default: 1]")
+}
+
+/// Adds the ref and ref-start arguments, to number positions by the residues
+/// of a reference sequence.
+fn add_reference_args<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app.arg(reference_arg()).arg(reference_start_arg())
+}
+
+fn journal_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("journal")
+        .long("journal")
+        .takes_value(true)
+        .help("Save the changes as a patch file")
+}
+
+fn add_slice_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    app.subcommand(
+        add_reference_args(add_column_range_args(
+            SubCommand::with_name("slice")
+                .about("Extract a subalignment with the selected columns")
+                .arg(
+                    Arg::with_name("input")
+                        .short("i")
+                        .long("in")
+                        .takes_value(true)
+                        .help("The input file")
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("out")
+                        .takes_value(true)
+                        .help("The output file")
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .help("Specify the input format: [Fasta, Plain]")
+                        .default_value("fasta")
+                )
+                .arg(journal_arg()),
+            true
+        ))
+    )
+}
+
+fn add_patch_subcommand<'a>(
+    app: App<'a, 'a>,
+    _map: &Messages
+) -> App<'a, 'a> {
+    let patch_arg = Arg::with_name("patch")
+        .short("p")
+        .long("patch")
+        .takes_value(true)
+        .required(true)
+        .help("The patch file");
+    app.subcommand(
+        SubCommand::with_name("patch")
+            .about("Apply, revert or create patches of changes between alignments")
+            .arg(
+                Arg::with_name("input")
+                    .short("i")
+                    .long("in")
+                    .takes_value(true)
+                    .help("The input file")
+                    .global(true)
+            )
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("out")
+                    .takes_value(true)
+                    .help("The output file")
+                    .global(true)
+            )
+            .arg(
+                Arg::with_name("format")
+                    .short("f")
+                    .long("format")
+                    .help("Specify the input format: [Fasta, Plain]")
+                    .default_value("fasta")
+                    .global(true)
+            )
+            .subcommand(
+                SubCommand::with_name("apply")
+                    .about("Apply the changes of a patch")
+                    .arg(patch_arg.clone())
+            )
+            .subcommand(
+                SubCommand::with_name("revert")
+                    .about("Undo the changes of a patch")
+                    .arg(patch_arg)
+            )
+            .subcommand(
+                SubCommand::with_name("diff")
+                    .about("Create a patch with the changes from the input to another file")
+                    .arg(
+                        Arg::with_name("to")
+                            .long("to")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The changed file")
+                    )
+            )
     )
 }
 
@@ -790,6 +2079,20 @@ fn create_app<'a>(map: &'a Messages)-> App<'a, 'a> {
     app = add_gap_subcommand(app, &map);
     app = add_combine_subcommand(app, &map);
     app = add_trim_command(app, &map);
+    app = add_pssm_subcommand(app, &map);
+    app = add_logo_subcommand(app, &map);
+    app = add_stats_subcommand(app, &map);
+    app = add_pairwise_subcommand(app, &map);
+    app = add_coevolution_subcommand(app, &map);
+    app = add_neff_subcommand(app, &map);
+    app = add_cluster_subcommand(app, &map);
+    app = add_weights_subcommand(app, &map);
+    app = add_kmer_subcommand(app, &map);
+    app = add_matrix_subcommand(app, &map);
+    app = add_align_subcommand(app, &map);
+    app = add_map_subcommand(app, &map);
+    app = add_slice_subcommand(app, &map);
+    app = add_patch_subcommand(app, &map);
     return app;
 }
 
@@ -840,6 +2143,20 @@ pub fn main() -> io::Result<()> {
         Box::new(Gap{}),
         Box::new(Combine{}),
         Box::new(Trim{}),
+        Box::new(Pssm{}),
+        Box::new(Logo{}),
+        Box::new(Stats{}),
+        Box::new(Pairwise{}),
+        Box::new(Coevolution{}),
+        Box::new(NeffCommand{}),
+        Box::new(ClusterCommand{}),
+        Box::new(WeightsCommand{}),
+        Box::new(KmerCommand{}),
+        Box::new(MatrixCommand{}),
+        Box::new(AlignCommand{}),
+        Box::new(MapCommand{}),
+        Box::new(SliceCommand{}),
+        Box::new(PatchCommand{}),
     ];
     let is_there_any_command = commands
         .iter()
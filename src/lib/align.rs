@@ -0,0 +1,741 @@
+use std::error::Error;
+
+use itertools::Itertools;
+
+use crate::matrices::{Num, ScoringMatrix, SubstitutionMatrix};
+use crate::edit_msa::EditMSA;
+use crate::seqs::{
+    Alignment, AnnotatedSequence, SeqError, SequenceAccesors,
+    SequenceCollection,
+};
+
+/// The kind of pairwise alignment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlignMode {
+    /// Needleman-Wunsch, both sequences are aligned end to end.
+    Global,
+    /// Smith-Waterman, only the best scoring region is aligned.
+    Local,
+    /// Both sequences are aligned end to end, but gaps at the ends are not
+    /// penalized.
+    SemiGlobal,
+}
+
+impl AlignMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "global" => Some(AlignMode::Global),
+            "local" => Some(AlignMode::Local),
+            "semiglobal" => Some(AlignMode::SemiGlobal),
+            _ => None,
+        }
+    }
+}
+
+/// The result of aligning two sequences.
+pub struct PairAlignment {
+    /// The aligned region of both sequences, as a two row alignment.
+    pub alignment: Alignment,
+    pub score: f64,
+    /// Identical positions over the alignment length.
+    pub identity: f64,
+    /// The alignment operations, M for aligned residues, I for residues of
+    /// the first sequence aligned to a gap and D for residues of the
+    /// second sequence aligned to a gap.
+    pub cigar: String,
+    /// Zero-based, half-open range of the first sequence that is aligned.
+    pub range1: (usize, usize),
+    /// Zero-based, half-open range of the second sequence that is aligned.
+    pub range2: (usize, usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum State {
+    Match,
+    GapIn2,
+    GapIn1,
+    Start,
+}
+
+/// Aligns pairs of sequences with affine gap penalties (Gotoh 1982).
+///
+/// A gap of length n costs `gap_open + (n - 1) * gap_extend`. Residues not
+/// in the matrix alphabet are scored as `X`, or `N` if the alphabet has no
+/// `X`. Gaps in input sequences are removed before aligning.
+#[derive(Clone)]
+pub struct PairAligner {
+    pub(crate) alphabet: Vec<char>,
+    pub(crate) scores: Vec<f64>,
+    pub(crate) mode: AlignMode,
+    pub(crate) gap_open: f64,
+    pub(crate) gap_extend: f64,
+}
+
+impl Default for PairAligner {
+    /// Global alignment with BLOSUM62, and gap penalties 10 and 0.5.
+    fn default() -> Self {
+        PairAligner::new(&ScoringMatrix::blosum62())
+    }
+}
+
+impl PairAligner {
+    pub fn new<T: Num, M: SubstitutionMatrix<T>>(matrix: &M) -> Self {
+        let alphabet = matrix
+            .alphabet()
+            .iter()
+            .map(|x| x.to_ascii_uppercase())
+            .collect::<Vec<_>>();
+        let size = alphabet.len();
+        let scores = (0..size * size)
+            .map(|x| {
+                matrix
+                    .get(x / size, x % size)
+                    .map(|v| v.as_float())
+                    .unwrap_or_default()
+            })
+            .collect();
+        PairAligner {
+            alphabet,
+            scores,
+            mode: AlignMode::Global,
+            gap_open: 10f64,
+            gap_extend: 0.5f64,
+        }
+    }
+    pub fn with_mode(mut self, mode: AlignMode) -> Self {
+        self.mode = mode;
+        self
+    }
+    /// Sets the penalty of the first position of a gap, as a positive value.
+    pub fn with_gap_open(mut self, gap_open: f64) -> Self {
+        self.gap_open = gap_open;
+        self
+    }
+    /// Sets the penalty of each additional position of a gap, as a positive
+    /// value.
+    pub fn with_gap_extend(mut self, gap_extend: f64) -> Self {
+        self.gap_extend = gap_extend;
+        self
+    }
+
+    /// The indexes of the residues of a sequence in the matrix alphabet.
+    pub(crate) fn indexes(
+        &self,
+        seq: &[char]
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let position = |c: char| self.alphabet.iter().position(|x| *x == c);
+        let wildcard = position('X').or_else(|| position('N'));
+        seq.iter()
+            .map(|c| {
+                position(c.to_ascii_uppercase())
+                    .or(wildcard)
+                    .ok_or_else(|| format!("Residue not in matrix: '{}'", c))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.into())
+    }
+
+    /// Aligns two sequences.
+    /// ```
+    /// use famlib::align::{AlignMode, PairAligner};
+    /// use famlib::seqs::{AnnotatedSequence, SequenceAccesors};
+    /// let seq1 = AnnotatedSequence::from_string("s1", "HEAGAWGHEE");
+    /// let seq2 = AnnotatedSequence::from_string("s2", "PAWHEAE");
+    /// let result = PairAligner::default()
+    ///     .with_mode(AlignMode::Local)
+    ///     .align(&seq1, &seq2)
+    ///     .unwrap();
+    /// assert_eq!(result.alignment.get(0).unwrap().seq_as_string(), "AWGHE");
+    /// assert_eq!(result.alignment.get(1).unwrap().seq_as_string(), "AW-HE");
+    /// assert_eq!(result.cigar, "2M1I2M");
+    /// assert_eq!(result.range1, (4, 9));
+    /// ```
+    pub fn align(
+        &self,
+        seq1: &AnnotatedSequence,
+        seq2: &AnnotatedSequence,
+    ) -> Result<PairAlignment, Box<dyn Error>> {
+        if seq1.id() == seq2.id() {
+            return Err(
+                format!("Sequences have the same id: {}", seq1.id()).into()
+            );
+        }
+        let (chars1, chars2) = (ungapped(seq1)?, ungapped(seq2)?);
+        let (idx1, idx2) = (self.indexes(&chars1)?, self.indexes(&chars2)?);
+        let size = self.alphabet.len();
+        let path = self.path(
+            idx1.len(),
+            idx2.len(),
+            |i, j| self.scores[idx1[i] * size + idx2[j]],
+            &vec![1f64; idx1.len()],
+            &vec![1f64; idx2.len()],
+        );
+        let (range1, range2) = (path.range1, path.range2);
+        build_result(
+            (seq1.id(), &chars1[range1.0..range1.1]),
+            (seq2.id(), &chars2[range2.0..range2.1]),
+            &path.ops,
+            path.score,
+            (range1, range2),
+        )
+    }
+
+    /// Finds the best alignment between two sequences of length n and m.
+    /// `score(i, j)` scores the i-th position of the first sequence with the
+    /// j-th position of the second. Gaps against the i-th position of the
+    /// first sequence have their penalties multiplied by `weights1[i]`, and
+    /// gaps against the j-th position of the second by `weights2[j]`.
+    pub(crate) fn path(
+        &self,
+        n: usize,
+        m: usize,
+        score: impl Fn(usize, usize) -> f64,
+        weights1: &[f64],
+        weights2: &[f64],
+    ) -> Path {
+        let width = m + 1;
+        let neg = f64::NEG_INFINITY;
+        let local = self.mode == AlignMode::Local;
+        let free_ends = self.mode == AlignMode::SemiGlobal;
+        // Best scores of alignments ending in a pair of residues, or in a
+        // gap in the second or the first sequence, and the state of the
+        // previous position.
+        let cells = (n + 1) * width;
+        let mut best = [vec![neg; cells], vec![neg; cells], vec![neg; cells]];
+        let mut trace = [
+            vec![State::Start; cells],
+            vec![State::Start; cells],
+            vec![State::Start; cells],
+        ];
+        best[0][0] = 0f64;
+        let pick = |options: [(f64, State); 3]| {
+            options
+                .iter()
+                .copied()
+                .fold((neg, State::Start), |a, b| if b.0 > a.0 { b } else { a })
+        };
+        for i in 0..=n {
+            for j in 0..=m {
+                let cell = i * width + j;
+                if i > 0 && j > 0 {
+                    let prev = cell - width - 1;
+                    let (mut value, mut state) = pick([
+                        (best[0][prev], State::Match),
+                        (best[1][prev], State::GapIn2),
+                        (best[2][prev], State::GapIn1),
+                    ]);
+                    if local && value <= 0f64 {
+                        (value, state) = (0f64, State::Start);
+                    }
+                    best[0][cell] = value + score(i - 1, j - 1);
+                    trace[0][cell] = state;
+                }
+                if i > 0 {
+                    let prev = cell - width;
+                    let free = free_ends && (j == 0 || j == m);
+                    let (open, extend) = match free {
+                        true => (0f64, 0f64),
+                        false => (
+                            self.gap_open * weights1[i - 1],
+                            self.gap_extend * weights1[i - 1],
+                        ),
+                    };
+                    let (value, state) = pick([
+                        (best[0][prev] - open, State::Match),
+                        (best[1][prev] - extend, State::GapIn2),
+                        (best[2][prev] - open, State::GapIn1),
+                    ]);
+                    best[1][cell] = value;
+                    trace[1][cell] = state;
+                }
+                if j > 0 {
+                    let prev = cell - 1;
+                    let free = free_ends && (i == 0 || i == n);
+                    let (open, extend) = match free {
+                        true => (0f64, 0f64),
+                        false => (
+                            self.gap_open * weights2[j - 1],
+                            self.gap_extend * weights2[j - 1],
+                        ),
+                    };
+                    let (value, state) = pick([
+                        (best[0][prev] - open, State::Match),
+                        (best[1][prev] - open, State::GapIn2),
+                        (best[2][prev] - extend, State::GapIn1),
+                    ]);
+                    best[2][cell] = value;
+                    trace[2][cell] = state;
+                }
+            }
+        }
+        let (mut i, mut j, mut state, score) = match local {
+            true => (0..=n)
+                .flat_map(|i| (0..=m).map(move |j| (i, j)))
+                .map(|(i, j)| (i, j, State::Match, best[0][i * width + j]))
+                .fold((0, 0, State::Start, 0f64), |a, b| {
+                    if b.3 > a.3 { b } else { a }
+                }),
+            false => {
+                let (score, state) = pick([
+                    (best[0][n * width + m], State::Match),
+                    (best[1][n * width + m], State::GapIn2),
+                    (best[2][n * width + m], State::GapIn1),
+                ]);
+                (n, m, state, score)
+            }
+        };
+        let (end1, end2) = (i, j);
+        let mut ops = vec![];
+        while state != State::Start && (i > 0 || j > 0) {
+            let cell = i * width + j;
+            let previous = match state {
+                State::Match => trace[0][cell],
+                State::GapIn2 => trace[1][cell],
+                State::GapIn1 => trace[2][cell],
+                State::Start => unreachable!(),
+            };
+            ops.push(state);
+            match state {
+                State::Match => (i, j) = (i - 1, j - 1),
+                State::GapIn2 => i -= 1,
+                State::GapIn1 => j -= 1,
+                State::Start => {}
+            }
+            state = previous;
+        }
+        ops.reverse();
+        Path {
+            ops,
+            score,
+            range1: (i, end1),
+            range2: (j, end2),
+        }
+    }
+}
+
+/// The residues of a sequence, without gaps.
+pub(crate) fn ungapped(seq: &AnnotatedSequence) -> Result<Vec<char>, Box<dyn Error>> {
+    seq.seq()
+        .map(|x| {
+            x.iter()
+                .filter(|c| **c != '-' && **c != '.')
+                .copied()
+                .collect::<Vec<_>>()
+        })
+        .ok_or_else(|| "Sequence without residues".into())
+}
+
+/// New rows for an alignment, computed by `ProfileAligner`.
+pub struct ProfileAddition {
+    /// Gap columns that must be added to the alignment before adding the
+    /// rows, as pairs of position in the original alignment and number of
+    /// columns. Sorted by position.
+    pub columns: Vec<(usize, usize)>,
+    pub ids: Vec<String>,
+    pub rows: Vec<Vec<char>>,
+}
+
+impl ProfileAddition {
+    /// Inserts the new columns and appends the new rows to the alignment.
+    pub fn apply(&self, msa: &mut Alignment) -> Result<(), SeqError> {
+        for (at, count) in self.columns.iter().rev() {
+            msa.insert_empty_columns(*at, *count, '-')?;
+        }
+        for (id, row) in self.ids.iter().zip(&self.rows) {
+            if row.len() != msa.length() {
+                return Err(SeqError::EditError);
+            }
+            msa.insert_rows(msa.size(), vec![id], vec![row.clone()])?;
+        }
+        Ok(())
+    }
+}
+
+/// The score of each residue against each column of an alignment, and the
+/// fraction of residues in each column.
+struct Profile {
+    scores: Vec<Vec<f64>>,
+    occupancy: Vec<f64>,
+}
+
+/// Adds unaligned sequences to an existing alignment, keeping its columns.
+///
+/// Each sequence is aligned independently to the profile of the alignment,
+/// where a residue is scored against a column by the average score with the
+/// residues of the column, counting gaps as zero. Gap penalties against
+/// columns are multiplied by the fraction of residues in the column.
+///
+/// Residues that are not aligned to any column are added in new columns,
+/// or dropped if the length of the alignment is kept.
+pub struct ProfileAligner<'a> {
+    msa: &'a Alignment,
+    aligner: PairAligner,
+    keep_length: bool,
+}
+
+impl<'a> ProfileAligner<'a> {
+    pub fn new(msa: &'a Alignment) -> Self {
+        ProfileAligner {
+            msa,
+            aligner: PairAligner::default(),
+            keep_length: false,
+        }
+    }
+    /// Sets the matrix, gap penalties and mode used to align sequences.
+    pub fn with_aligner(mut self, aligner: PairAligner) -> Self {
+        self.aligner = aligner;
+        self
+    }
+    /// Drops residues that are not aligned to a column, instead of adding
+    /// new columns.
+    pub fn keep_length(mut self) -> Self {
+        self.keep_length = true;
+        self
+    }
+
+    /// Scores of each residue of the alphabet against each column.
+    fn profile(&self) -> Result<Profile, Box<dyn Error>> {
+        let size = self.aligner.alphabet.len();
+        let nseqs = self.msa.size().max(1) as f64;
+        let mut scores = vec![];
+        let mut occupancy = vec![];
+        for column in self.msa.columns() {
+            let residues = column
+                .iter()
+                .filter(|c| ***c != '-' && ***c != '.')
+                .map(|c| **c)
+                .collect::<Vec<_>>();
+            let mut counts = vec![0f64; size];
+            for x in self.aligner.indexes(&residues)? {
+                counts[x] += 1f64;
+            }
+            scores.push(
+                (0..size)
+                    .map(|a| {
+                        counts
+                            .iter()
+                            .enumerate()
+                            .map(|(b, n)| n * self.aligner.scores[a * size + b])
+                            .sum::<f64>()
+                            / nseqs
+                    })
+                    .collect::<Vec<_>>()
+            );
+            occupancy.push(residues.len() as f64 / nseqs);
+        }
+        Ok(Profile { scores, occupancy })
+    }
+
+    /// Aligns all sequences to the alignment.
+    /// ```
+    /// use famlib::align::ProfileAligner;
+    /// use famlib::seqs::{
+    ///     AnnotatedSequence, SequenceAccesors, SequenceCollection
+    /// };
+    /// let mut seqs = SequenceCollection::new();
+    /// for (id, seq) in [("s1", "MKV-LW"), ("s2", "MRVGLW")] {
+    ///     seqs.add(AnnotatedSequence::from_string(id, seq)).unwrap();
+    /// }
+    /// let mut msa = seqs.to_msa().unwrap();
+    /// let mut new = SequenceCollection::new();
+    /// new.add(AnnotatedSequence::from_string("s3", "MKVLLW")).unwrap();
+    /// let addition = ProfileAligner::new(&msa).add(&new).unwrap();
+    /// addition.apply(&mut msa).unwrap();
+    /// assert_eq!(msa.get(2).unwrap().seq_as_string(), "MKVLLW");
+    /// ```
+    pub fn add(
+        &self,
+        seqs: &SequenceCollection,
+    ) -> Result<ProfileAddition, Box<dyn Error>> {
+        let Profile { scores, occupancy } = self.profile()?;
+        let m = scores.len();
+        let mut aligned = vec![];
+        let mut ids = vec![];
+        for seq in seqs.iter() {
+            if self.msa.contains(seq.id()) {
+                return Err(
+                    format!("Sequence already in alignment: {}", seq.id())
+                        .into()
+                );
+            }
+            let chars = ungapped(seq)?;
+            let idx = self.aligner.indexes(&chars)?;
+            let path = self.aligner.path(
+                idx.len(),
+                m,
+                |i, j| scores[j][idx[i]],
+                &vec![1f64; idx.len()],
+                &occupancy,
+            );
+            let ops = path.full_ops(idx.len(), m);
+            // Residues aligned to each column, and residues inserted before
+            // each column or at the end.
+            let mut columns = vec!['-'; m];
+            let mut inserts = vec![vec![]; m + 1];
+            let (mut i, mut j) = (0, 0);
+            for op in ops {
+                match op {
+                    State::GapIn1 => j += 1,
+                    State::GapIn2 => {
+                        inserts[j].push(chars[i]);
+                        i += 1;
+                    }
+                    _ => {
+                        columns[j] = chars[i];
+                        (i, j) = (i + 1, j + 1);
+                    }
+                }
+            }
+            aligned.push((columns, inserts));
+            ids.push(seq.id().to_string());
+        }
+        let widths = (0..=m)
+            .map(|j| match self.keep_length {
+                true => 0,
+                false => {
+                    aligned.iter().map(|x| x.1[j].len()).max().unwrap_or(0)
+                }
+            })
+            .collect::<Vec<_>>();
+        let rows = aligned
+            .into_iter()
+            .map(|(columns, inserts)| {
+                let mut row = vec![];
+                for (j, insert) in inserts.into_iter().enumerate() {
+                    let mut insert = insert;
+                    insert.truncate(widths[j]);
+                    insert.resize(widths[j], '-');
+                    row.extend(insert);
+                    if j < m {
+                        row.push(columns[j]);
+                    }
+                }
+                row
+            })
+            .collect();
+        Ok(ProfileAddition {
+            columns: widths
+                .into_iter()
+                .enumerate()
+                .filter(|(_, w)| *w > 0)
+                .collect(),
+            ids,
+            rows,
+        })
+    }
+}
+
+/// The operations of an alignment, and the aligned ranges of both
+/// sequences.
+pub(crate) struct Path {
+    pub(crate) ops: Vec<State>,
+    pub(crate) score: f64,
+    pub(crate) range1: (usize, usize),
+    pub(crate) range2: (usize, usize),
+}
+
+impl Path {
+    /// The operations to align all positions of two sequences of length n
+    /// and m. Unaligned ends of local alignments are kept next to the
+    /// aligned region.
+    pub(crate) fn full_ops(self, n: usize, m: usize) -> Vec<State> {
+        [
+            vec![State::GapIn1; self.range2.0],
+            vec![State::GapIn2; self.range1.0],
+            self.ops,
+            vec![State::GapIn2; n - self.range1.1],
+            vec![State::GapIn1; m - self.range2.1],
+        ].concat()
+    }
+}
+
+fn build_result(
+    (id1, chars1): (&str, &[char]),
+    (id2, chars2): (&str, &[char]),
+    ops: &[State],
+    score: f64,
+    (range1, range2): ((usize, usize), (usize, usize)),
+) -> Result<PairAlignment, Box<dyn Error>> {
+    let (mut row1, mut row2) = (vec![], vec![]);
+    let (mut it1, mut it2) = (chars1.iter(), chars2.iter());
+    for op in ops {
+        let (a, b) = match op {
+            State::GapIn2 => (it1.next(), Some(&'-')),
+            State::GapIn1 => (Some(&'-'), it2.next()),
+            _ => (it1.next(), it2.next()),
+        };
+        row1.push(*a.ok_or("Invalid alignment path")?);
+        row2.push(*b.ok_or("Invalid alignment path")?);
+    }
+    let identical = row1
+        .iter()
+        .zip(&row2)
+        .filter(|(a, b)| **a != '-' && a.eq_ignore_ascii_case(b))
+        .count();
+    let identity = match ops.is_empty() {
+        true => 0f64,
+        false => identical as f64 / ops.len() as f64,
+    };
+    let mut cigar = String::new();
+    for (state, group) in &ops.iter().group_by(|x| **x) {
+        let op = match state {
+            State::GapIn2 => 'I',
+            State::GapIn1 => 'D',
+            _ => 'M',
+        };
+        cigar.push_str(&format!("{}{}", group.count(), op));
+    }
+    let mut alignment = Alignment::new();
+    alignment
+        .add(AnnotatedSequence::new(id1.to_string(), row1))
+        .map_err(|e| e.to_string())?;
+    alignment
+        .add(AnnotatedSequence::new(id2.to_string(), row2))
+        .map_err(|e| e.to_string())?;
+    Ok(PairAlignment {
+        alignment,
+        score,
+        identity,
+        cigar,
+        range1,
+        range2,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::{build_msa, rows};
+    use super::*;
+
+    fn dna_matrix() -> ScoringMatrix {
+        ScoringMatrix::from_ncbi_str(
+            "   A  C  G  T\n\
+            A  1 -1 -1 -1\n\
+            C -1  1 -1 -1\n\
+            G -1 -1  1 -1\n\
+            T -1 -1 -1  1\n"
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_global() {
+        let seq1 = AnnotatedSequence::from_string("s1", "ACGTTTAC");
+        let seq2 = AnnotatedSequence::from_string("s2", "AC-GTAC");
+        let result = PairAligner::new(&dna_matrix())
+            .with_gap_open(2.0)
+            .with_gap_extend(1.0)
+            .align(&seq1, &seq2)
+            .unwrap();
+        assert_eq!(rows(&result.alignment), vec!["ACGTTTAC", "ACG--TAC"]);
+        assert_eq!(result.score, 6.0 - 3.0);
+        assert_eq!(result.cigar, "3M2I3M");
+        assert_eq!(result.identity, 6.0 / 8.0);
+        assert_eq!(result.range2, (0, 6));
+    }
+
+    #[test]
+    fn test_affine_gaps_are_joined() {
+        // With a high open penalty, a single long gap is preferred.
+        let seq1 = AnnotatedSequence::from_string("s1", "AAAGGGTTT");
+        let seq2 = AnnotatedSequence::from_string("s2", "AAATTT");
+        let result = PairAligner::new(&dna_matrix())
+            .with_gap_open(5.0)
+            .with_gap_extend(0.1)
+            .align(&seq1, &seq2)
+            .unwrap();
+        assert_eq!(result.cigar, "3M3I3M");
+        assert!((result.score - (6.0 - 5.2)).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_semiglobal() {
+        let seq1 = AnnotatedSequence::from_string("s1", "GGGGACGTACGT");
+        let seq2 = AnnotatedSequence::from_string("s2", "ACGTAC");
+        let aligner = PairAligner::new(&dna_matrix())
+            .with_gap_open(3.0)
+            .with_gap_extend(1.0);
+        let result = aligner
+            .with_mode(AlignMode::SemiGlobal)
+            .align(&seq1, &seq2)
+            .unwrap();
+        assert_eq!(
+            rows(&result.alignment),
+            vec!["GGGGACGTACGT", "----ACGTAC--"]
+        );
+        assert_eq!(result.score, 6.0);
+        assert_eq!(result.cigar, "4I6M2I");
+        let result = PairAligner::new(&dna_matrix())
+            .with_mode(AlignMode::SemiGlobal)
+            .align(&seq2, &seq1)
+            .unwrap();
+        assert_eq!(result.cigar, "4D6M2D");
+    }
+
+    #[test]
+    fn test_local() {
+        let seq1 = AnnotatedSequence::from_string("s1", "TTTTACGTAGG");
+        let seq2 = AnnotatedSequence::from_string("s2", "CCACGTACC");
+        let result = PairAligner::new(&dna_matrix())
+            .with_mode(AlignMode::Local)
+            .align(&seq1, &seq2)
+            .unwrap();
+        assert_eq!(rows(&result.alignment), vec!["ACGTA", "ACGTA"]);
+        assert_eq!(result.score, 5.0);
+        assert_eq!(result.range1, (4, 9));
+        assert_eq!(result.range2, (2, 7));
+        let seq3 = AnnotatedSequence::from_string("s3", "GGGG");
+        let seq4 = AnnotatedSequence::from_string("s4", "TTTT");
+        let result = PairAligner::new(&dna_matrix())
+            .with_mode(AlignMode::Local)
+            .align(&seq3, &seq4)
+            .unwrap();
+        assert_eq!(result.score, 0.0);
+        assert_eq!(result.cigar, "");
+    }
+
+    #[test]
+    fn test_add_to_profile() {
+        let msa = build_msa(&["ACGTAC", "AC-TAC"]);
+        let mut seqs = SequenceCollection::new();
+        for (id, seq) in [("n1", "ACGTTTTAC"), ("n2", "ACTTAC"), ("n3", "TA")] {
+            seqs.add(AnnotatedSequence::from_string(id, seq)).unwrap();
+        }
+        let aligner = PairAligner::new(&dna_matrix())
+            .with_gap_open(3.0)
+            .with_gap_extend(1.0);
+        let addition = ProfileAligner::new(&msa)
+            .with_aligner(aligner.clone())
+            .add(&seqs)
+            .unwrap();
+        assert_eq!(addition.columns, vec![(3, 3)]);
+        let mut result = msa.clone();
+        addition.apply(&mut result).unwrap();
+        assert_eq!(
+            rows(&result),
+            vec![
+                "ACG---TAC", "AC----TAC", "ACGTTTTAC", "ACT---TAC", "------TA-"
+            ]
+        );
+        let addition = ProfileAligner::new(&msa)
+            .with_aligner(aligner)
+            .keep_length()
+            .add(&seqs)
+            .unwrap();
+        assert!(addition.columns.is_empty());
+        assert_eq!(addition.rows[0].iter().collect::<String>(), "ACGTAC");
+        let mut result = msa.clone();
+        addition.apply(&mut result).unwrap();
+        assert_eq!(result.length(), 6);
+        let existing = msa.iter().cloned().collect::<SequenceCollection>();
+        assert!(ProfileAligner::new(&msa).add(&existing).is_err());
+    }
+
+    #[test]
+    fn test_unknown_residues() {
+        let seq1 = AnnotatedSequence::from_string("s1", "ACJ");
+        let seq2 = AnnotatedSequence::from_string("s2", "ACD");
+        let result = PairAligner::default().align(&seq1, &seq2).unwrap();
+        assert_eq!(result.score, 4.0 + 9.0 - 1.0);
+        assert!(PairAligner::new(&dna_matrix()).align(&seq1, &seq2).is_err());
+    }
+}
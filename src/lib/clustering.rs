@@ -1,7 +1,9 @@
-use std::{cmp::max, error::Error};
+use std::{cmp::max, collections::HashMap, error::Error};
 use std::io::ErrorKind;
 
-use crate::seqs::{Alignment, SequenceAccesors};
+use crate::pairwise::PairwiseIdentity;
+use crate::seqs::{Alignment, SequenceAccesors, SequenceCollection};
+use crate::similarity::{IdentityOverLength, SimilarityMetric};
 
 #[derive(Clone)]
 pub struct Cluster {
@@ -33,6 +35,36 @@ pub trait Clusterer {
 
 
 }
+
+/// Weights sequences by the inverse of the size of their clusters.
+fn cluster_weights(
+        clusters: &Option<Vec<Cluster>>)
+        -> Result<Vec<f64>, Box<dyn Error>> {
+    match clusters {
+        Some(clusters) => {
+            let size = clusters
+                .iter()
+                .flat_map(|x| x.members.iter())
+                .max()
+                .map_or(0, |x| x + 1);
+            let mut ws = vec![0f64; size];
+            clusters
+                .iter()
+                .for_each(|x| {
+                    x.members.iter().for_each(
+                        |y| ws[*y] = 1f64/x.members.len() as f64
+                    )
+                });
+            Ok(ws)
+        }
+        None => {
+            Err(Box::new(std::io::Error::other(
+                "There are no clusters to compute weights",
+            )))
+        }
+    }
+}
+
 /// Computes the similarity of two sequences as the fraction of
 /// identical characters in both sequences.
 ///
@@ -52,8 +84,8 @@ pub trait Clusterer {
 /// assert_eq!(id, 0.75);
 /// ```
 pub fn identity_fraction(
-        seq1: &Vec<char>,
-        seq2: &Vec<char>,
+        seq1: &[char],
+        seq2: &[char],
         exclude_gaps: bool)
         -> Result<f64, Box<dyn Error>> {
     if seq1.len() == seq2.len() {
@@ -90,7 +122,7 @@ pub fn identity_fraction(
 pub struct Hobohm1<'a> {
     msa: &'a Alignment,
     similarity: f64,
-    exclude_gaps: bool,
+    metric: Box<dyn SimilarityMetric + 'a>,
     clusters: Option<Vec<Cluster>>
 }
 
@@ -99,7 +131,7 @@ impl <'a> Hobohm1<'a> {
         Hobohm1{
             msa,
             similarity: 0.62,
-            exclude_gaps: false,
+            metric: Box::new(IdentityOverLength::new()),
             clusters: None
         }
     }
@@ -107,12 +139,22 @@ impl <'a> Hobohm1<'a> {
         self.similarity = similarity;
         self
     }
+    /// Sets the metric used to compare sequences, identity over the
+    /// alignment length is used by default.
+    pub fn with_metric(mut self, metric: impl SimilarityMetric + 'a)
+            -> Self {
+        self.metric = Box::new(metric);
+        self
+    }
+    /// Uses identity over the alignment length, excluding positions in
+    /// which both sequences are gaps.
     pub fn exclude_gaps(mut self) -> Self {
-        self.exclude_gaps = true;
+        self.metric = Box::new(IdentityOverLength::new().exclude_gaps());
         self
     }
+    /// Uses identity over the alignment length.
     pub fn include_gaps(mut self) -> Self {
-        self.exclude_gaps = false;
+        self.metric = Box::new(IdentityOverLength::new());
         self
     }
 }
@@ -126,10 +168,9 @@ impl <'a> Clusterer for Hobohm1<'a> {
             let cseq = self.msa.get(index).unwrap().seq().unwrap();
             let mut add_cluster = true;
             for (i, repr) in repr_indexes.iter().enumerate() {
-                match identity_fraction(
+                match self.metric.similarity(
                         cseq,
-                        self.msa.get(*repr).unwrap().seq().unwrap(),
-                        false) {
+                        self.msa.get(*repr).unwrap().seq().unwrap()) {
                     Ok(sim) => {
                         if sim >= self.similarity {
                             // Add index to current cluster
@@ -153,30 +194,134 @@ impl <'a> Clusterer for Hobohm1<'a> {
     }
 
     fn weights(&self) -> Result<Vec<f64>, Box<dyn Error>> {
-        match &self.clusters {
-            Some(clusters) => {
-                let max_index = clusters
-                    .iter()
-                    .flat_map(|x| x.members.iter())
-                    .max()
-                    .unwrap();
-                let mut ws = vec![0f64; *max_index+1];
-                clusters
-                    .iter()
-                    .for_each(|x| {
-                        x.members.iter().for_each(
-                            |y| ws[*y] = 1f64/x.members.len() as f64
-                        )
-                    });
-                Ok(ws)
+        cluster_weights(&self.clusters)
+    }
+    fn clear(&mut self) {
+        self.clusters = None;
+    }
+    fn take_clusters(&mut self) -> Option<Vec<Cluster>> {
+        self.clusters.take()
+    }
+    fn clusters(&self) -> Option<Vec<Cluster>> {
+        self.clusters.clone()
+    }
+
+}
+/// Hobohm2 clustering.
+///
+/// Sequences with an identity equal or greater than the similarity
+/// threshold are neighbours. The sequence with most neighbours is removed
+/// iteratively until there are no neighbours left, on ties the last
+/// sequence of the alignment is removed. The remaining sequences are the
+/// cluster representatives, every removed sequence is assigned to the
+/// representative with the highest identity.
+pub struct Hobohm2<'a> {
+    msa: &'a Alignment,
+    similarity: f64,
+    metric: Box<dyn SimilarityMetric + 'a>,
+    clusters: Option<Vec<Cluster>>
+}
+
+impl <'a> Hobohm2<'a> {
+    pub fn new(msa: &'a Alignment) -> Self {
+        Hobohm2{
+            msa,
+            similarity: 0.62,
+            metric: Box::new(IdentityOverLength::new()),
+            clusters: None
+        }
+    }
+    pub fn with_similarity(mut self, similarity: f64) -> Self {
+        self.similarity = similarity;
+        self
+    }
+    /// Sets the metric used to compare sequences, identity over the
+    /// alignment length is used by default.
+    pub fn with_metric(mut self, metric: impl SimilarityMetric + 'a)
+            -> Self {
+        self.metric = Box::new(metric);
+        self
+    }
+    /// Uses identity over the alignment length, excluding positions in
+    /// which both sequences are gaps.
+    pub fn exclude_gaps(mut self) -> Self {
+        self.metric = Box::new(IdentityOverLength::new().exclude_gaps());
+        self
+    }
+    /// Uses identity over the alignment length.
+    pub fn include_gaps(mut self) -> Self {
+        self.metric = Box::new(IdentityOverLength::new());
+        self
+    }
+}
+
+impl <'a> Clusterer for Hobohm2<'a> {
+    fn clusterize(&mut self) -> Result<(), Box<dyn Error>> {
+        let size = self.msa.size();
+        let edges = PairwiseIdentity::new(self.msa)
+            .with_metric(&self.metric)
+            .edges(self.similarity)?;
+        let mut neighbours: Vec<Vec<usize>> = vec![vec![]; size];
+        for (i, j, _) in edges.edges() {
+            neighbours[*i].push(*j);
+            neighbours[*j].push(*i);
+        }
+        let mut removed = vec![false; size];
+        let mut counts = neighbours.iter().map(|x| x.len()).collect::<Vec<_>>();
+        loop {
+            let candidate = (0..size)
+                .filter(|x| !removed[*x] && counts[*x] > 0)
+                .max_by_key(|x| (counts[*x], *x));
+            match candidate {
+                Some(index) => {
+                    removed[index] = true;
+                    for n in &neighbours[index] {
+                        if !removed[*n] {
+                            counts[*n] -= 1;
+                        }
+                    }
+                }
+                None => break
+            }
+        }
+        let repr_indexes = (0..size)
+            .filter(|x| !removed[*x])
+            .collect::<Vec<_>>();
+        let mut members: Vec<Vec<usize>> = repr_indexes
+            .iter()
+            .map(|x| vec![*x])
+            .collect();
+        for index in (0..size).filter(|x| removed[*x]) {
+            let cseq = self.msa.get(index).unwrap().seq().unwrap();
+            let mut best: Option<(usize, f64)> = None;
+            for (i, repr) in repr_indexes.iter().enumerate() {
+                let sim = self.metric.similarity(
+                    cseq,
+                    self.msa.get(*repr).unwrap().seq().unwrap()
+                )?;
+                let better = match best {
+                    None => true,
+                    Some((_, b)) => sim > b,
+                };
+                if better {
+                    best = Some((i, sim));
+                }
             }
-            None => {
-                Err(Box::new(std::io::Error::new(
-                    ErrorKind::Other,
-                    format!("There are no clusters to compute weights"),
-                )))
+            if let Some((i, _)) = best {
+                members[i].push(index);
             }
         }
+        self.clusters = Some(repr_indexes.iter().zip(members).map(
+            |(r, mut m)| {
+                m.sort();
+                Cluster::new(*r).with_members(m)
+            }
+        ).collect());
+        Ok(())
+    }
+
+    fn weights(&self) -> Result<Vec<f64>, Box<dyn Error>> {
+        cluster_weights(&self.clusters)
     }
     fn clear(&mut self) {
         self.clusters = None;
@@ -187,12 +332,281 @@ impl <'a> Clusterer for Hobohm1<'a> {
     fn clusters(&self) -> Option<Vec<Cluster>> {
         self.clusters.clone()
     }
+}
 
+/// Word size used by CD-HIT for protein sequences at a given identity
+/// threshold.
+fn default_word_size(similarity: f64) -> usize {
+    match similarity {
+        x if x >= 0.7 => 5,
+        x if x >= 0.6 => 4,
+        x if x >= 0.5 => 3,
+        _ => 2
+    }
 }
+
+/// Counts the words of length k of a sequence.
+fn word_counts(seq: &[char], k: usize) -> HashMap<&[char], usize> {
+    let mut counts = HashMap::new();
+    if k > 0 {
+        for word in seq.windows(k) {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Length of the longest common subsequence of two sequences.
+fn lcs_length(seq1: &[char], seq2: &[char]) -> usize {
+    let mut previous = vec![0usize; seq2.len() + 1];
+    let mut current = vec![0usize; seq2.len() + 1];
+    for a in seq1 {
+        for (j, b) in seq2.iter().enumerate() {
+            current[j + 1] = match a == b {
+                true => previous[j] + 1,
+                false => max(previous[j + 1], current[j])
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[seq2.len()]
+}
+
+fn ungapped_upper(seq: &[char]) -> Vec<char> {
+    seq.iter()
+        .filter(|c| **c != '-' && **c != '.')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Computes the identity of two unaligned sequences as the length of their
+/// longest common subsequence divided by the length of the shorter one.
+/// Gaps are removed and case is ignored.
+///
+/// Example:
+/// ```
+/// use famlib::clustering::lcs_identity;
+/// let seq1 = "MKVLA-AGIVA".chars().collect::<Vec<_>>();
+/// let seq2 = "mkvlsag".chars().collect::<Vec<_>>();
+/// assert_eq!(lcs_identity(&seq1, &seq2), 6.0 / 7.0);
+/// ```
+pub fn lcs_identity(seq1: &[char], seq2: &[char]) -> f64 {
+    let seq1 = ungapped_upper(seq1);
+    let seq2 = ungapped_upper(seq2);
+    match seq1.len().min(seq2.len()) {
+        0 => 0f64,
+        x => lcs_length(&seq1, &seq2) as f64 / x as f64
+    }
+}
+
+/// Greedy incremental clustering of unaligned sequences, similar to
+/// CD-HIT.
+///
+/// Gaps are removed and sequences are processed from the longest to the
+/// shortest, or in the input order with `keep_order`. Each sequence is
+/// added to the first representative with an identity equal or greater
+/// than the similarity threshold, or it becomes a new representative.
+/// Identity is the number of identical residues in the longest common
+/// subsequence, divided by the length of the shorter sequence.
+///
+/// Before computing the identity, pairs are filtered by the number of
+/// shared words of length k, as an identity over the threshold requires a
+/// minimum number of them.
+///
+/// Unlike the other clusterers, CdHit does not take a `SimilarityMetric`,
+/// as metrics compare aligned sequences, and the word filter is only valid
+/// for this identity.
+pub struct CdHit<'a> {
+    seqs: &'a SequenceCollection,
+    similarity: f64,
+    word_size: Option<usize>,
+    sort_by_length: bool,
+    clusters: Option<Vec<Cluster>>
+}
+
+impl <'a> CdHit<'a> {
+    pub fn new(seqs: &'a SequenceCollection) -> Self {
+        CdHit{
+            seqs,
+            similarity: 0.9,
+            word_size: None,
+            sort_by_length: true,
+            clusters: None
+        }
+    }
+    pub fn with_similarity(mut self, similarity: f64) -> Self {
+        self.similarity = similarity;
+        self
+    }
+    /// Sets the length of the words used to filter pairs. By default it
+    /// depends on the similarity threshold, as in CD-HIT.
+    pub fn with_word_size(mut self, word_size: usize) -> Self {
+        self.word_size = Some(word_size);
+        self
+    }
+    /// Processes sequences in the input order instead of sorting them by
+    /// length, so that the first sequences are preferred as
+    /// representatives.
+    pub fn keep_order(mut self) -> Self {
+        self.sort_by_length = false;
+        self
+    }
+}
+
+impl <'a> Clusterer for CdHit<'a> {
+    fn clusterize(&mut self) -> Result<(), Box<dyn Error>> {
+        let k = self.word_size
+            .unwrap_or_else(|| default_word_size(self.similarity));
+        let ungapped = self.seqs
+            .iter()
+            .map(|x| x.seq().map(|s| ungapped_upper(s)).unwrap_or_default())
+            .collect::<Vec<_>>();
+        let mut order = (0..ungapped.len()).collect::<Vec<_>>();
+        if self.sort_by_length {
+            order.sort_by_key(|x| std::cmp::Reverse(ungapped[*x].len()));
+        }
+        let words = ungapped
+            .iter()
+            .map(|x| word_counts(x, k))
+            .collect::<Vec<_>>();
+        let mut repr_indexes: Vec<usize> = vec![];
+        let mut members: Vec<Vec<usize>> = vec![];
+        for index in order {
+            let cseq = &ungapped[index];
+            let mut cluster = None;
+            for (i, repr) in repr_indexes.iter().enumerate() {
+                let length = cseq.len().min(ungapped[*repr].len());
+                // Each mismatch can remove up to k shared words.
+                let required = length as f64 - k as f64 + 1f64 -
+                    (1f64 - self.similarity) * length as f64 * k as f64;
+                if k > 0 && length >= k {
+                    let shared: usize = words[index]
+                        .iter()
+                        .map(|(w, c)| {
+                            *c.min(words[*repr].get(w).unwrap_or(&0))
+                        })
+                        .sum();
+                    if (shared as f64) < required {
+                        continue;
+                    }
+                }
+                let identity = match length {
+                    0 => 0f64,
+                    _ => lcs_length(cseq, &ungapped[*repr]) as f64 /
+                        length as f64
+                };
+                if identity >= self.similarity {
+                    cluster = Some(i);
+                    break;
+                }
+            }
+            match cluster {
+                Some(i) => members[i].push(index),
+                None => {
+                    repr_indexes.push(index);
+                    members.push(vec![index]);
+                }
+            }
+        }
+        self.clusters = Some(repr_indexes.iter().zip(members).map(
+            |(r, m)| Cluster::new(*r).with_members(m)
+        ).collect());
+        Ok(())
+    }
+
+    fn weights(&self) -> Result<Vec<f64>, Box<dyn Error>> {
+        cluster_weights(&self.clusters)
+    }
+    fn clear(&mut self) {
+        self.clusters = None;
+    }
+    fn take_clusters(&mut self) -> Option<Vec<Cluster>> {
+        self.clusters.take()
+    }
+    fn clusters(&self) -> Option<Vec<Cluster>> {
+        self.clusters.clone()
+    }
+}
+
+/// Effective number of sequences of an alignment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Neff {
+    /// Sum of the weights of all sequences.
+    pub total: f64,
+    /// Sum of the weights of the sequences without gaps in each column.
+    pub per_column: Vec<f64>,
+    /// The weight of each sequence, that is the inverse of the number of
+    /// sequences (itself included) with an identity equal or greater than
+    /// the threshold.
+    pub weights: Vec<f64>,
+}
+
+/// Computes the effective number of sequences (Neff or Meff) of an
+/// alignment, at a given similarity threshold. Usual identity thresholds
+/// are 0.8 and 0.62.
+///
+/// Similarity between sequences is computed with `metric`, pairs are
+/// compared using `threads` threads, or all available cores if zero.
+///
+/// Example:
+/// ```
+/// use famlib::clustering::neff;
+/// use famlib::similarity::IdentityOverLength;
+/// use famlib::seqs::{
+///     AnnotatedSequence, SequenceAccesors, SequenceCollection
+/// };
+/// let mut seqs = SequenceCollection::new();
+/// for (id, seq) in [("s1", "AAAAA"), ("s2", "AAAAC"), ("s3", "CC-CC")] {
+///     seqs.add(
+///         AnnotatedSequence::from_string(id.to_string(), seq.to_string())
+///     ).unwrap();
+/// }
+/// let msa = seqs.to_msa().unwrap();
+/// let result = neff(&msa, IdentityOverLength::new(), 0.8, 1).unwrap();
+/// assert_eq!(result.weights, vec![0.5, 0.5, 1.0]);
+/// assert_eq!(result.total, 2.0);
+/// assert_eq!(result.per_column, vec![2.0, 2.0, 1.0, 2.0, 2.0]);
+/// ```
+pub fn neff<'a>(
+        msa: &'a Alignment,
+        metric: impl SimilarityMetric + 'a,
+        similarity: f64,
+        threads: usize)
+        -> Result<Neff, Box<dyn Error>> {
+    let pairwise = PairwiseIdentity::new(msa)
+        .with_metric(metric)
+        .with_threads(threads);
+    let mut neighbours = vec![1usize; msa.size()];
+    for (i, j, _) in pairwise.edges(similarity)?.edges() {
+        neighbours[*i] += 1;
+        neighbours[*j] += 1;
+    }
+    let weights = neighbours
+        .iter()
+        .map(|x| 1f64 / *x as f64)
+        .collect::<Vec<_>>();
+    let mut per_column = vec![0f64; msa.length()];
+    for (seq, w) in msa.iter().zip(&weights) {
+        if let Some(chars) = seq.seq() {
+            chars
+                .iter()
+                .zip(per_column.iter_mut())
+                .filter(|(c, _)| **c != '-')
+                .for_each(|(_, col)| *col += w);
+        }
+    }
+    Ok(Neff {
+        total: weights.iter().sum(),
+        per_column,
+        weights,
+    })
+}
+
 #[cfg(test)]
 mod test {
     use crate::seqs::AnnotatedSequence;
     use crate::seqs::SequenceCollection;
+    use crate::similarity::IdentityOverShorter;
 
     use super::*;
     #[test]
@@ -282,4 +696,175 @@ mod test {
             .zip(expected_weight.iter())
             .for_each(|(a, b)| assert_eq!(a, b))
     }
+    #[test]
+    fn test_clustering_excluding_gaps() {
+        let mut sequences = SequenceCollection::new();
+        sequences.add(AnnotatedSequence::from_string("s1", "AAAA----"))
+            .unwrap();
+        sequences.add(AnnotatedSequence::from_string("s2", "AAAAC---"))
+            .unwrap();
+        let sequences = sequences.to_msa().unwrap();
+        let mut cl = Hobohm1::new(&sequences);
+        cl.clusterize().unwrap();
+        assert_eq!(cl.clusters().unwrap().len(), 2);
+        let mut cl = Hobohm1::new(&sequences).exclude_gaps();
+        cl.clusterize().unwrap();
+        assert_eq!(cl.clusters().unwrap().len(), 1);
+        let mut cl = Hobohm2::new(&sequences).exclude_gaps();
+        cl.clusterize().unwrap();
+        assert_eq!(cl.clusters().unwrap().len(), 1);
+        let mut cl = Hobohm1::new(&sequences)
+            .with_metric(IdentityOverShorter)
+            .with_similarity(1.0);
+        cl.clusterize().unwrap();
+        assert_eq!(cl.clusters().unwrap().len(), 1);
+    }
+    #[test]
+    fn test_hobohm2() {
+        let mut sequences = SequenceCollection::new();
+        [
+            "AAAAA",
+            "AAAAB",
+            "AAABB",
+            "AAABC",
+            "AACDE",
+            "AACFG"
+        ].iter()
+        .enumerate()
+        .map(|(i, x)|
+            AnnotatedSequence::from_string(
+                format!("Seq_{}", i),
+                String::from(*x)
+            )
+        ).for_each(
+            |ann| sequences.add(ann).unwrap()
+        );
+        let sequences = sequences.to_msa().ok().unwrap();
+        let mut cl = Hobohm2::new(&sequences);
+        cl.clusterize().unwrap();
+
+        let clusters = cl.clusters().unwrap();
+        let representatives = clusters
+            .iter()
+            .map(|x| x.representative)
+            .collect::<Vec<_>>();
+        assert_eq!(representatives, vec![0, 3, 4, 5]);
+        assert_eq!(clusters[0].members, vec![0, 1]);
+        assert_eq!(clusters[1].members, vec![2, 3]);
+        assert_eq!(clusters[2].members, vec![4]);
+        assert_eq!(clusters[3].members, vec![5]);
+        assert_eq!(
+            cl.weights().unwrap(),
+            vec![0.5, 0.5, 0.5, 0.5, 1.0, 1.0]
+        );
+        cl.clear();
+        assert!(cl.clusters().is_none());
+        assert!(cl.weights().is_err());
+    }
+    #[test]
+    fn test_lcs_length() {
+        let seq1 = "ABCBDAB".chars().collect::<Vec<_>>();
+        let seq2 = "BDCABA".chars().collect::<Vec<_>>();
+        assert_eq!(lcs_length(&seq1, &seq2), 4);
+        assert_eq!(lcs_length(&seq1, &[]), 0);
+        assert_eq!(lcs_length(&seq1, &seq1), 7);
+    }
+    #[test]
+    fn test_cdhit() {
+        let mut sequences = SequenceCollection::new();
+        [
+            "MKVLAAGIVA",
+            "MKVLAAGIV",
+            "MKV-LSAGIVA",
+            "WWWWWWWW",
+            "WWWWYWW",
+        ].iter()
+        .enumerate()
+        .map(|(i, x)|
+            AnnotatedSequence::from_string(
+                format!("Seq_{}", i),
+                String::from(*x)
+            )
+        ).for_each(
+            |ann| sequences.add(ann).unwrap()
+        );
+        let mut cl = CdHit::new(&sequences).with_similarity(0.85);
+        cl.clusterize().unwrap();
+        let clusters = cl.take_clusters().unwrap();
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].representative, 0);
+        assert_eq!(clusters[0].members, vec![0, 2, 1]);
+        assert_eq!(clusters[1].representative, 3);
+        assert_eq!(clusters[1].members, vec![3, 4]);
+        assert!(cl.clusters().is_none());
+
+        let mut cl = CdHit::new(&sequences)
+            .with_similarity(0.95)
+            .with_word_size(3);
+        cl.clusterize().unwrap();
+        let clusters = cl.clusters().unwrap();
+        assert_eq!(clusters.len(), 4);
+        assert_eq!(clusters[0].members, vec![0, 1]);
+        assert_eq!(
+            cl.weights().unwrap(),
+            vec![0.5, 0.5, 1.0, 1.0, 1.0]
+        );
+
+        let mut cl = CdHit::new(&sequences)
+            .with_similarity(0.85)
+            .keep_order();
+        cl.clusterize().unwrap();
+        let clusters = cl.take_clusters().unwrap();
+        assert_eq!(clusters[0].members, vec![0, 1, 2]);
+        assert_eq!(clusters[1].representative, 3);
+        let shorter_first = sequences
+            .iter()
+            .skip(1)
+            .cloned()
+            .collect::<SequenceCollection>();
+        let mut cl = CdHit::new(&shorter_first)
+            .with_similarity(0.85)
+            .keep_order();
+        cl.clusterize().unwrap();
+        let clusters = cl.take_clusters().unwrap();
+        assert_eq!(clusters[0].representative, 0);
+        assert_eq!(clusters[0].members, vec![0, 1]);
+    }
+    #[test]
+    fn test_neff() {
+        let mut sequences = SequenceCollection::new();
+        [
+            "AAAAA",
+            "AAAAB",
+            "AAABB",
+            "AAABC",
+            "AACDE",
+            "-ACFG"
+        ].iter()
+        .enumerate()
+        .map(|(i, x)|
+            AnnotatedSequence::from_string(
+                format!("Seq_{}", i),
+                String::from(*x)
+            )
+        ).for_each(
+            |ann| sequences.add(ann).unwrap()
+        );
+        let sequences = sequences.to_msa().ok().unwrap();
+        // Unlike Hobohm1, every sequence counts all its neighbours.
+        let result = neff(&sequences, IdentityOverLength::new(), 0.62, 2).unwrap();
+        let expected_weight = vec![
+            1.0 / 2.0, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 2.0, 1.0, 1.0
+        ];
+        assert_eq!(result.weights, expected_weight);
+        let total: f64 = expected_weight.iter().sum();
+        assert!((result.total - total).abs() < 1e-9);
+        assert!((result.per_column[0] - (total - 1.0)).abs() < 1e-9);
+        assert!((result.per_column[1] - total).abs() < 1e-9);
+        // Threshold of 1.0 counts identical sequences only.
+        let result = neff(&sequences, IdentityOverLength::new(), 1.0, 2).unwrap();
+        assert_eq!(result.total, 6.0);
+        let result = neff(&sequences, IdentityOverLength::new(), 0.0, 2).unwrap();
+        assert!((result.total - 1.0).abs() < 1e-9);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,436 @@
+use std::error::Error;
+use std::io::{self, Write};
+
+use crate::conservation::Alphabet;
+use crate::mapping::ReferenceNumbering;
+use crate::seqs::{Alignment, SequenceAccesors};
+
+/// Corrections applied to raw mutual information scores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Correction {
+    /// Raw mutual information.
+    None,
+    /// Average product correction (Dunn et al. 2008).
+    APC,
+    /// Row and column weighting (Gouveia-Oliveira & Pedersen 2007).
+    RCW,
+}
+
+/// A pair of alignment columns and their scores.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnPair {
+    /// Zero based index of the first column.
+    pub first: usize,
+    /// Zero based index of the second column.
+    pub second: usize,
+    /// Raw mutual information, in bits.
+    pub mi: f64,
+    /// Corrected score.
+    pub score: f64,
+}
+
+/// Computes the mutual information between all pairs of columns of an
+/// alignment.
+///
+/// Only the sequences with residues of the alphabet in both columns are
+/// considered for each pair, gaps and unknown characters are ignored.
+pub struct MutualInformation<'a> {
+    msa: &'a Alignment,
+    alphabet: Alphabet,
+    weights: Option<Vec<f64>>,
+    max_gaps: f64,
+    correction: Correction,
+}
+
+impl<'a> MutualInformation<'a> {
+    pub fn new(msa: &'a Alignment) -> Self {
+        MutualInformation {
+            msa,
+            alphabet: Alphabet::Protein,
+            weights: None,
+            max_gaps: 1.0,
+            correction: Correction::APC,
+        }
+    }
+    pub fn as_dna(mut self) -> Self {
+        self.alphabet = Alphabet::DNA;
+        self
+    }
+    pub fn as_protein(mut self) -> Self {
+        self.alphabet = Alphabet::Protein;
+        self
+    }
+    /// Sets a weight for each sequence, in the same order than the
+    /// alignment.
+    pub fn with_weights(mut self, weights: Vec<f64>) -> Self {
+        self.weights = Some(weights);
+        self
+    }
+    /// Columns with a gap fraction greater than `max_gaps` are not scored.
+    pub fn with_max_gaps(mut self, max_gaps: f64) -> Self {
+        self.max_gaps = max_gaps;
+        self
+    }
+    pub fn with_correction(mut self, correction: Correction) -> Self {
+        self.correction = correction;
+        self
+    }
+
+    /// Computes the scores of all pairs of columns that pass the gap filter.
+    /// ```
+    /// use famlib::coevolution::{Correction, MutualInformation};
+    /// use famlib::seqs::{
+    ///     AnnotatedSequence, SequenceAccesors, SequenceCollection
+    /// };
+    /// let mut seqs = SequenceCollection::new();
+    /// for (id, seq) in [("s1", "AAC"), ("s2", "CCC"), ("s3", "AAT"),
+    ///                   ("s4", "CCT")] {
+    ///     seqs.add(
+    ///         AnnotatedSequence::from_string(id.to_string(), seq.to_string())
+    ///     ).unwrap();
+    /// }
+    /// let msa = seqs.to_msa().unwrap();
+    /// let scores = MutualInformation::new(&msa)
+    ///     .as_dna()
+    ///     .with_correction(Correction::None)
+    ///     .compute()
+    ///     .unwrap();
+    /// assert_eq!(scores.mi(0, 1), Some(1.0));
+    /// assert_eq!(scores.mi(0, 2), Some(0.0));
+    /// ```
+    pub fn compute(&self) -> Result<CoevolutionScores, Box<dyn Error>> {
+        let nseqs = self.msa.size();
+        let weights = match &self.weights {
+            Some(w) => {
+                if w.len() != nseqs {
+                    return Err(Box::new(io::Error::other(
+                        "The number of weights and sequences is different",
+                    )));
+                }
+                w.clone()
+            }
+            None => vec![1.0; nseqs],
+        };
+        let size = self.alphabet.size();
+        let encoded = self
+            .msa
+            .columns()
+            .map(|col| {
+                col.iter()
+                    .map(|c| self.alphabet.index(**c))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let columns = encoded
+            .iter()
+            .enumerate()
+            .filter(|(_, col)| {
+                let gaps = col.iter().filter(|x| **x >= size).count();
+                nseqs > 0 && gaps as f64 / nseqs as f64 <= self.max_gaps
+            })
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let n = columns.len();
+        let mut mi = vec![vec![0.0; n]; n];
+        let mut joint = vec![0.0; size * size];
+        for a in 0..n {
+            for b in a + 1..n {
+                let value = mutual_information(
+                    &encoded[columns[a]],
+                    &encoded[columns[b]],
+                    &weights,
+                    size,
+                    &mut joint,
+                );
+                mi[a][b] = value;
+                mi[b][a] = value;
+            }
+        }
+        let corrected = correct(&mi, self.correction);
+        Ok(CoevolutionScores {
+            columns,
+            mi,
+            corrected,
+        })
+    }
+}
+
+/// Mutual information in bits between two encoded columns.
+fn mutual_information(
+    col1: &[usize],
+    col2: &[usize],
+    weights: &[f64],
+    size: usize,
+    joint: &mut [f64],
+) -> f64 {
+    joint.iter_mut().for_each(|x| *x = 0.0);
+    let mut total = 0.0;
+    for ((x, y), w) in col1.iter().zip(col2).zip(weights) {
+        if *x < size && *y < size {
+            joint[x * size + y] += w;
+            total += w;
+        }
+    }
+    if total <= 0.0 {
+        return 0.0;
+    }
+    let mut p1 = vec![0.0; size];
+    let mut p2 = vec![0.0; size];
+    for x in 0..size {
+        for y in 0..size {
+            let p = joint[x * size + y] / total;
+            joint[x * size + y] = p;
+            p1[x] += p;
+            p2[y] += p;
+        }
+    }
+    let mut result = 0.0;
+    for x in 0..size {
+        for y in 0..size {
+            let p = joint[x * size + y];
+            if p > 0.0 {
+                result += p * (p / (p1[x] * p2[y])).log2();
+            }
+        }
+    }
+    result.max(0.0)
+}
+
+fn correct(mi: &[Vec<f64>], correction: Correction) -> Vec<Vec<f64>> {
+    let n = mi.len();
+    if n < 3 || correction == Correction::None {
+        return mi.to_vec();
+    }
+    let sums = mi
+        .iter()
+        .map(|row| row.iter().sum::<f64>())
+        .collect::<Vec<_>>();
+    let mut corrected = vec![vec![0.0; n]; n];
+    match correction {
+        Correction::APC => {
+            let means = sums
+                .iter()
+                .map(|x| x / (n - 1) as f64)
+                .collect::<Vec<_>>();
+            let total_mean = sums.iter().sum::<f64>() / (n * (n - 1)) as f64;
+            for a in 0..n {
+                for b in (0..n).filter(|b| *b != a) {
+                    let apc = match total_mean > 0.0 {
+                        true => means[a] * means[b] / total_mean,
+                        false => 0.0,
+                    };
+                    corrected[a][b] = mi[a][b] - apc;
+                }
+            }
+        }
+        Correction::RCW => {
+            for a in 0..n {
+                for b in (0..n).filter(|b| *b != a) {
+                    // Mean MI of both columns with all other columns.
+                    let others = (sums[a] + sums[b] - 2.0 * mi[a][b]) /
+                        (2 * (n - 2)) as f64;
+                    corrected[a][b] = match others > 0.0 {
+                        true => mi[a][b] / others,
+                        false => 0.0,
+                    };
+                }
+            }
+        }
+        Correction::None => {}
+    }
+    corrected
+}
+
+/// Mutual information scores of the columns of an alignment.
+pub struct CoevolutionScores {
+    columns: Vec<usize>,
+    mi: Vec<Vec<f64>>,
+    corrected: Vec<Vec<f64>>,
+}
+
+impl CoevolutionScores {
+    /// Zero based indexes of the scored columns.
+    pub fn columns(&self) -> &Vec<usize> {
+        &self.columns
+    }
+    fn position(&self, column: usize) -> Option<usize> {
+        self.columns.binary_search(&column).ok()
+    }
+    /// Raw mutual information between two columns. Returns None if a column
+    /// was not scored.
+    pub fn mi(&self, first: usize, second: usize) -> Option<f64> {
+        Some(self.mi[self.position(first)?][self.position(second)?])
+    }
+    /// Corrected score between two columns. Returns None if a column was not
+    /// scored.
+    pub fn score(&self, first: usize, second: usize) -> Option<f64> {
+        Some(self.corrected[self.position(first)?][self.position(second)?])
+    }
+    /// All the pairs of scored columns separated by at least
+    /// `min_separation` columns, sorted by decreasing score.
+    pub fn pairs(&self, min_separation: usize) -> Vec<ColumnPair> {
+        let n = self.columns.len();
+        let mut pairs = (0..n)
+            .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+            .filter(|(a, b)| {
+                self.columns[*b] - self.columns[*a] >= min_separation
+            })
+            .map(|(a, b)| ColumnPair {
+                first: self.columns[a],
+                second: self.columns[b],
+                mi: self.mi[a][b],
+                score: self.corrected[a][b],
+            })
+            .collect::<Vec<_>>();
+        pairs.sort_by(|x, y| y.score.total_cmp(&x.score));
+        pairs
+    }
+    /// Writes the best `top` pairs as a tab separated table.
+    ///
+    /// Columns are numbered from one. If a reference numbering is given,
+    /// the residue numbers of the reference and its residues are also
+    /// written, with '-' for gaps in the reference.
+    pub fn write_tsv<T: Write>(
+        &self,
+        mut writer: T,
+        top: usize,
+        min_separation: usize,
+        reference: Option<&ReferenceNumbering>,
+    ) -> io::Result<()> {
+        write!(writer, "column1\tcolumn2")?;
+        if reference.is_some() {
+            write!(writer, "\tref1\tref2\tresidue1\tresidue2")?;
+        }
+        writeln!(writer, "\tmi\tscore")?;
+        for pair in self.pairs(min_separation).into_iter().take(top) {
+            write!(writer, "{}\t{}", pair.first + 1, pair.second + 1)?;
+            if let Some(numbering) = reference {
+                let show = |c: usize| {
+                    numbering
+                        .number_at(c)
+                        .map_or(String::from("-"), |x| x.to_string())
+                };
+                write!(
+                    writer,
+                    "\t{}\t{}\t{}\t{}",
+                    show(pair.first),
+                    show(pair.second),
+                    numbering.symbol_at(pair.first).unwrap_or('-'),
+                    numbering.symbol_at(pair.second).unwrap_or('-')
+                )?;
+            }
+            writeln!(writer, "\t{:.4}\t{:.4}", pair.mi, pair.score)?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::build_msa;
+    use super::*;
+
+    #[test]
+    fn test_raw_mi() {
+        let msa = build_msa(
+            &["AKCDE", "AKCDE", "GRCDE", "GRCEE", "A-CEE", "GRC-E"]
+        );
+        let scores = MutualInformation::new(&msa)
+            .with_correction(Correction::None)
+            .compute()
+            .unwrap();
+        assert_eq!(scores.columns(), &vec![0, 1, 2, 3, 4]);
+        // Columns 0 and 1 are fully coupled in the five sequences without
+        // gaps, with 2 'A' and 3 'G'.
+        let expected = -(0.4f64 * 0.4f64.log2() + 0.6 * 0.6f64.log2());
+        assert!((scores.mi(0, 1).unwrap() - expected).abs() < 1e-9);
+        assert_eq!(scores.mi(1, 0), scores.mi(0, 1));
+        // Conserved columns have no information.
+        assert_eq!(scores.mi(0, 2), Some(0.0));
+        assert_eq!(scores.mi(2, 4), Some(0.0));
+        assert_eq!(scores.score(0, 1), scores.mi(0, 1));
+    }
+
+    #[test]
+    fn test_gap_filter() {
+        let msa = build_msa(
+            &["AKCDE", "AKCDE", "GRCDE", "GRCEE", "A-CEE", "GRC-E"]
+        );
+        let scores = MutualInformation::new(&msa)
+            .with_max_gaps(0.1)
+            .compute()
+            .unwrap();
+        assert_eq!(scores.columns(), &vec![0, 2, 4]);
+        assert_eq!(scores.mi(0, 1), None);
+        assert!(scores.score(0, 2).is_some());
+    }
+
+    #[test]
+    fn test_weights() {
+        let msa = build_msa(
+            &["AKCDE", "AKCDE", "GRCDE", "GRCEE", "A-CEE", "GRC-E"]
+        );
+        let weights = vec![0.5, 0.5, 1.0, 1.0, 1.0, 1.0];
+        let scores = MutualInformation::new(&msa)
+            .with_weights(weights)
+            .with_correction(Correction::None)
+            .compute()
+            .unwrap();
+        // One 'A' and three 'G' in weighted counts.
+        let expected = -(0.25f64 * 0.25f64.log2() + 0.75 * 0.75f64.log2());
+        assert!((scores.mi(0, 1).unwrap() - expected).abs() < 1e-9);
+        let error = MutualInformation::new(&msa)
+            .with_weights(vec![1.0])
+            .compute();
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn test_corrections() {
+        let mi = vec![
+            vec![0.0, 1.0, 0.5],
+            vec![1.0, 0.0, 0.25],
+            vec![0.5, 0.25, 0.0],
+        ];
+        let apc = correct(&mi, Correction::APC);
+        // Means: 0.75, 0.625, 0.375; total mean: 0.58333.
+        let total = 1.75 / 3.0;
+        assert!((apc[0][1] - (1.0 - 0.75 * 0.625 / total)).abs() < 1e-9);
+        assert!((apc[1][2] - (0.25 - 0.625 * 0.375 / total)).abs() < 1e-9);
+        assert_eq!(apc[0][1], apc[1][0]);
+        let rcw = correct(&mi, Correction::RCW);
+        assert!((rcw[0][1] - 1.0 / ((0.5 + 0.25) / 2.0)).abs() < 1e-9);
+        assert!((rcw[0][2] - 0.5 / ((1.0 + 0.25) / 2.0)).abs() < 1e-9);
+        assert_eq!(correct(&mi, Correction::None), mi);
+    }
+
+    #[test]
+    fn test_write_tsv() {
+        let msa = build_msa(
+            &["AKCDE", "AKCDE", "GRCDE", "GRCEE", "A-CEE", "GRC-E"]
+        );
+        let scores = MutualInformation::new(&msa)
+            .with_correction(Correction::None)
+            .compute()
+            .unwrap();
+        let mut out = vec![];
+        scores
+            .write_tsv(
+                &mut out,
+                1,
+                1,
+                Some(&ReferenceNumbering::new(msa.get(4).unwrap()))
+            )
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text,
+            "column1\tcolumn2\tref1\tref2\tresidue1\tresidue2\tmi\tscore\n\
+            1\t2\t1\t-\tA\t-\t0.9710\t0.9710\n"
+        );
+        let mut out = vec![];
+        scores.write_tsv(&mut out, 10, 4, None).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, "column1\tcolumn2\tmi\tscore\n1\t5\t0.0000\t0.0000\n");
+    }
+}
@@ -43,6 +43,65 @@ pub(crate) fn amino_index(c: char) -> usize {
     }
 }
 
+/// Residue alphabets supported by the per-column statistics.
+///
+/// The order of the symbols is the one given by `amino_index` and
+/// `dna_index`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alphabet {
+    Protein,
+    DNA
+}
+
+impl Alphabet {
+    /// Number of residue symbols, gaps and unknown characters excluded.
+    pub fn size(&self) -> usize {
+        match self {
+            Alphabet::Protein => 20,
+            Alphabet::DNA => 4
+        }
+    }
+
+    /// Index of a character in the alphabet. Gaps are mapped to `size()`
+    /// and any other character to `size() + 1`. Case is ignored.
+    /// ```
+    /// use famlib::conservation::Alphabet;
+    /// assert_eq!(Alphabet::Protein.index('c'), 1);
+    /// assert_eq!(Alphabet::DNA.index('-'), 4);
+    /// assert_eq!(Alphabet::DNA.index('N'), 5);
+    /// ```
+    pub fn index(&self, c: char) -> usize {
+        match self {
+            Alphabet::Protein => amino_index(c.to_ascii_uppercase()),
+            Alphabet::DNA => dna_index(c.to_ascii_uppercase())
+        }
+    }
+
+    /// The residue symbols, sorted by their index.
+    pub fn symbols(&self) -> Vec<char> {
+        match self {
+            Alphabet::Protein => "ACDEFGHIKLMNPQRSTVWY".chars().collect(),
+            Alphabet::DNA => "ACTG".chars().collect()
+        }
+    }
+
+    /// Default background frequencies, sorted by symbol index.
+    ///
+    /// Proteins use the Robinson & Robinson frequencies (the ones used by
+    /// BLAST), nucleic acids use a uniform distribution.
+    pub fn background(&self) -> Vec<f64> {
+        match self {
+            Alphabet::Protein => vec![
+                0.07805, 0.01925, 0.05364, 0.06295, 0.03856,
+                0.07377, 0.02199, 0.05142, 0.05744, 0.09019,
+                0.02243, 0.04487, 0.05203, 0.04264, 0.05129,
+                0.07120, 0.05841, 0.06441, 0.01330, 0.03216
+            ],
+            Alphabet::DNA => vec![0.25; 4]
+        }
+    }
+}
+
 fn _entropy(
         msa: &Alignment,
         alphabet_size: usize,
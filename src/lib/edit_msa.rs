@@ -1,9 +1,13 @@
 use std::cmp::max;
+use crate::align::PairAligner;
+use crate::degap::Degap;
 use crate::edit::EditSequence;
+use crate::progressive::ProgressiveAligner;
 use crate::seqs::Alignment;
 use crate::seqs::SeqError;
 use crate::seqs::SequenceAccesors;
 use crate::seqs::AnnotatedSequence;
+use crate::seqs::SequenceCollection;
 
 pub trait EditMSA {
     fn insert_empty_columns(
@@ -39,6 +43,16 @@ pub trait EditMSA {
         at_y: usize,
         content: Vec<Vec<char>>,
     ) -> Result<(), SeqError>;
+
+    /// Realigns the columns from `start` to `end` (exclusive). The block is
+    /// degapped and aligned again with a progressive aligner, and then
+    /// replaces the original columns. The rest of the alignment is kept.
+    fn realign_columns(
+        &mut self,
+        start: usize,
+        end: usize,
+        aligner: &PairAligner,
+    ) -> Result<(), SeqError>;
 }
 
 // Transpose a vector of vectors.
@@ -142,7 +156,6 @@ impl EditMSA for Alignment {
         &mut self,
         positions: Vec<usize>
     ) -> std::result::Result<(), SeqError> {
-        println!("Positions: {:?}", positions);
         let mut sorted = positions.clone();
         sorted.sort_unstable();
         sorted.reverse();
@@ -191,6 +204,53 @@ impl EditMSA for Alignment {
             Err(SeqError::EditError)
         }
     }
+    fn realign_columns(
+        &mut self,
+        start: usize,
+        end: usize,
+        aligner: &PairAligner,
+    ) -> Result<(), SeqError> {
+        if start > end || end > self.length() {
+            return Err(SeqError::EditError);
+        }
+        let mut block = SequenceCollection::new();
+        for seq in self.iter() {
+            let content = seq.seq().ok_or(SeqError::EditError)?;
+            block.add(
+                AnnotatedSequence::new(
+                    seq.id().to_string(),
+                    content[start..end].to_vec()
+                )
+            )?;
+        }
+        // Rows with only gaps in the block are not aligned.
+        let block = block.degap(false);
+        let mut rows = vec![];
+        let mut residues = SequenceCollection::new();
+        for (i, seq) in block.iter().enumerate() {
+            if !seq.is_empty() {
+                rows.push(i);
+                residues.add(seq.clone())?;
+            }
+        }
+        let mut content = vec![];
+        if residues.size() > 0 {
+            let realigned = ProgressiveAligner::new(&residues)
+                .with_aligner(aligner.clone())
+                .align()
+                .map_err(|_| SeqError::EditError)?;
+            let mut columns = vec![vec!['-'; self.size()]; realigned.length()];
+            for (row, seq) in rows.iter().zip(realigned.iter()) {
+                let seq = seq.seq().ok_or(SeqError::EditError)?;
+                for (column, c) in columns.iter_mut().zip(seq) {
+                    column[*row] = *c;
+                }
+            }
+            content = columns;
+        }
+        self.remove_columns((start..end).collect())?;
+        self.insert_columns(start, content)
+    }
 }
 
 #[cfg(test)]
@@ -717,4 +777,26 @@ mod test {
         compare_sequence(&msa, "s2", "YYYY");
         compare_sequence(&msa, "s3", "ZZZZ");
     }
-}
\ No newline at end of file
+    #[test]
+    fn realign_columns_in_place() {
+        let mut msa = Alignment::new();
+        for (id, seq) in [
+            ("s1", "MK-WAAG--HEK"),
+            ("s2", "MKW-A-AG-HEK"),
+            ("s3", "MR---WAG-HEK"),
+            ("s4", "MK-------HEK"),
+        ] {
+            msa.add(AnnotatedSequence::from_string(id, seq)).unwrap();
+        }
+        let aligner = PairAligner::default();
+        msa.realign_columns(2, 9, &aligner).unwrap();
+        check_internal_length_consistency(&msa);
+        compare_sequence(&msa, "s1", "MKWAAGHEK");
+        compare_sequence(&msa, "s2", "MKWAAGHEK");
+        compare_sequence(&msa, "s4", "MK----HEK");
+        let s3 = msa.get_by_id("s3").unwrap().seq_as_string();
+        assert_eq!(s3.replace('-', ""), "MRWAGHEK");
+        assert!(s3.starts_with("MRW") && s3.ends_with("GHEK"));
+        assert!(msa.realign_columns(5, 20, &aligner).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::BufRead;
+use serde::Deserialize;
+use crate::edit::EditSequence;
+use crate::journal::{EditJournal, Journaled};
+use crate::seqs::{Alignment, SequenceAccesors};
+
+/// The edit operations of an edit script.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Insert,
+    Replace,
+    Delete,
+}
+
+impl Operation {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "insert" => Some(Operation::Insert),
+            "replace" => Some(Operation::Replace),
+            "delete" => Some(Operation::Delete),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &str {
+        match self {
+            Operation::Insert => "insert",
+            Operation::Replace => "replace",
+            Operation::Delete => "delete",
+        }
+    }
+}
+
+/// A single edit of an edit script.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct EditInstruction {
+    pub operation: Operation,
+    /// The ID of the edited sequence, or '*' to edit all sequences.
+    pub id: String,
+    /// One based column of the alignment before any edit of the script is
+    /// applied. Insertions are made before this column.
+    pub position: usize,
+    /// The new residues of insertions and replacements, or the number of
+    /// positions to delete.
+    #[serde(default)]
+    pub content: String,
+    /// The number of positions replaced, the length of the content by
+    /// default.
+    #[serde(default)]
+    pub width: Option<usize>,
+}
+
+impl EditInstruction {
+    fn applies_to(&self, id: &str) -> bool {
+        self.id == "*" || self.id == id
+    }
+    /// The number of original positions covered by the edit.
+    pub fn width(&self) -> Result<usize, Box<dyn Error>> {
+        match self.operation {
+            Operation::Insert => Ok(0),
+            Operation::Replace => Ok(
+                self.width.unwrap_or_else(|| self.content.chars().count())
+            ),
+            Operation::Delete => match self.content.trim() {
+                "" => Ok(1),
+                x => x
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|x| *x > 0)
+                    .ok_or_else(
+                        || format!("Invalid number of positions: '{}'", x).into()
+                    ),
+            },
+        }
+    }
+}
+
+impl Display for EditInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.operation.name(),
+            self.id,
+            self.position,
+            self.content
+        )
+    }
+}
+
+/// An edit that was applied to an alignment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppliedEdit {
+    pub operation: Operation,
+    pub id: String,
+    /// One based column given in the script.
+    pub position: usize,
+    /// One based column where the edit was applied, after the shifts of the
+    /// previous edits.
+    pub column: usize,
+    /// The number of residues inserted, replaced or deleted.
+    pub residues: usize,
+}
+
+/// The changes of previous edits to the positions of a sequence. Each
+/// change is a position of the original sequence, with the number of
+/// inserted and deleted positions.
+#[derive(Clone, Debug, Default)]
+struct Offsets {
+    changes: Vec<(usize, usize, usize)>,
+}
+
+impl Offsets {
+    /// The current position of an original zero based position, or None if
+    /// the position was deleted.
+    fn current(&self, original: usize) -> Option<usize> {
+        let mut position = original;
+        for (at, inserted, deleted) in self.changes.iter() {
+            if original >= *at {
+                position += inserted;
+            }
+            match (original >= *at, original >= at + deleted) {
+                (true, true) => position -= deleted,
+                (true, false) => return None,
+                _ => {}
+            }
+        }
+        Some(position)
+    }
+    /// The current position of a block of original positions. Fails if any
+    /// of them was deleted or there are insertions inside the block.
+    fn current_block(&self, original: usize, width: usize) -> Option<usize> {
+        let start = self.current(original)?;
+        match width {
+            0 => Some(start),
+            w => self
+                .current(original + w - 1)
+                .filter(|end| end - start == w - 1)
+                .map(|_| start),
+        }
+    }
+}
+
+/// A list of edits that are applied together to an alignment.
+///
+/// Scripts can be written as TSV, with the operation, the sequence ID, the
+/// position and the content on each line. Lines starting with '#' and a
+/// header line starting with 'operation' are skipped.
+/// ```
+/// use famlib::edit_script::EditScript;
+/// use famlib::seqs::{AnnotatedSequence, SequenceAccesors, SequenceCollection};
+/// let mut sq = SequenceCollection::new();
+/// sq.add(AnnotatedSequence::from_string("s1", "AC-GT")).unwrap();
+/// sq.add(AnnotatedSequence::from_string("s2", "ACTGT")).unwrap();
+/// let mut msa = sq.to_msa().unwrap();
+/// let script = "insert\t*\t1\t-\ndelete\ts1\t3\t1\ninsert\ts1\t5\t-\n";
+/// let script = EditScript::from_tsv(script.as_bytes()).unwrap();
+/// let applied = script.apply(&mut msa).unwrap();
+/// assert_eq!(applied[2].column, 5);
+/// assert_eq!(msa.get(0).unwrap().seq_as_string(), "-ACG-T");
+/// assert_eq!(msa.get(1).unwrap().seq_as_string(), "-ACTGT");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct EditScript {
+    #[serde(rename = "edit", default)]
+    pub instructions: Vec<EditInstruction>,
+}
+
+impl EditScript {
+    pub fn new() -> Self {
+        EditScript::default()
+    }
+    pub fn from_tsv<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut script = EditScript::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let first = line.split('\t').next().unwrap_or("").trim();
+            let skip = first.is_empty() ||
+                first.starts_with('#') ||
+                (i == 0 && first.eq_ignore_ascii_case("operation"));
+            if skip {
+                continue;
+            }
+            let fields = line
+                .trim_end_matches('\r')
+                .split('\t')
+                .map(|x| x.trim())
+                .collect::<Vec<_>>();
+            if fields.len() < 3 || fields.len() > 4 {
+                return Err(
+                    format!("Line {}: expected three or four fields", i + 1)
+                        .into()
+                );
+            }
+            let operation = Operation::from_name(fields[0]).ok_or_else(
+                || format!("Line {}: unknown operation '{}'", i + 1, fields[0])
+            )?;
+            let position = fields[2].parse::<usize>().map_err(
+                |_| format!("Line {}: cannot parse position '{}'", i + 1, fields[2])
+            )?;
+            script.instructions.push(
+                EditInstruction {
+                    operation,
+                    id: fields[1].to_string(),
+                    position,
+                    content: fields.get(3).unwrap_or(&"").to_string(),
+                    width: None,
+                }
+            );
+        }
+        Ok(script)
+    }
+    /// Reads a TOML script, with an `[[edit]]` table for each edit.
+    pub fn from_toml(text: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str::<EditScript>(text)?)
+    }
+    /// Applies all the edits to the alignment, in order. Positions refer to
+    /// the alignment before the script, the shifts introduced by previous
+    /// insertions and deletions are tracked for each sequence. Edits can not
+    /// touch positions deleted by a previous edit or span previous
+    /// insertions. The alignment is only modified if all edits succeed and
+    /// the sequences keep the same length.
+    pub fn apply(
+        &self,
+        msa: &mut Alignment,
+    ) -> Result<Vec<AppliedEdit>, Box<dyn Error>> {
+        self.apply_with_journal(msa, &mut EditJournal::new())
+    }
+    /// Applies all the edits like `apply`, and records the changes into the
+    /// journal if all of them succeed.
+    pub fn apply_with_journal(
+        &self,
+        msa: &mut Alignment,
+        journal: &mut EditJournal,
+    ) -> Result<Vec<AppliedEdit>, Box<dyn Error>> {
+        let mut edited = msa.clone();
+        let mut recorded = EditJournal::new();
+        let ids = msa.iter().map(|x| x.id().to_string()).collect::<Vec<_>>();
+        let mut offsets = vec![Offsets::default(); ids.len()];
+        let mut applied = vec![];
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            let fail = |message: String| -> Box<dyn Error> {
+                format!("Edit {} ({}): {}", i + 1, instruction, message).into()
+            };
+            let rows = ids
+                .iter()
+                .enumerate()
+                .filter(|(_, id)| instruction.applies_to(id))
+                .map(|(j, _)| j)
+                .collect::<Vec<_>>();
+            if rows.is_empty() {
+                return Err(fail("sequence not found".to_string()));
+            }
+            if instruction.position == 0 {
+                return Err(fail("positions start at one".to_string()));
+            }
+            let original = instruction.position - 1;
+            let width = instruction.width().map_err(|e| fail(e.to_string()))?;
+            let content = instruction.content.chars().collect::<Vec<_>>();
+            let mut column = 0;
+            for row in rows {
+                column = offsets[row]
+                    .current_block(original, width)
+                    .ok_or_else(
+                        || fail("overlaps a previous edit".to_string())
+                    )?;
+                let mut seq = Journaled::new(
+                    edited.get_mut(row).unwrap(),
+                    &mut recorded
+                );
+                let result = match instruction.operation {
+                    Operation::Insert => seq.edit_insert(content.clone(), column),
+                    Operation::Replace => {
+                        seq.edit_replace(content.clone(), column, width)
+                    }
+                    Operation::Delete => seq.edit_delete(column, width),
+                };
+                result.map_err(|e| fail(e.to_string()))?;
+                let change = match instruction.operation {
+                    Operation::Insert => Some((original, content.len(), 0)),
+                    Operation::Replace if content.len() == width => None,
+                    Operation::Replace => {
+                        Some((original, content.len(), width))
+                    }
+                    Operation::Delete => Some((original, 0, width)),
+                };
+                offsets[row].changes.extend(change);
+            }
+            applied.push(
+                AppliedEdit {
+                    operation: instruction.operation,
+                    id: instruction.id.clone(),
+                    position: instruction.position,
+                    column: column + 1,
+                    residues: match instruction.operation {
+                        Operation::Insert => content.len(),
+                        _ => width,
+                    },
+                }
+            );
+        }
+        *msa = edited
+            .seq_col_owned()
+            .to_msa()
+            .map_err(
+                |_| "Edited sequences do not have the same length"
+            )?;
+        journal.extend(recorded);
+        Ok(applied)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::{build_msa, rows};
+    use super::*;
+
+    #[test]
+    fn test_apply_tracks_offsets() {
+        let text = "operation\tid\tposition\tcontent\n\
+            # curated\n\
+            insert\ts1\t1\tMM\n\
+            replace\ts1\t3\tX\n\
+            delete\ts1\t5\t1\n\
+            insert\ts2\t3\tW\n\
+            delete\ts2\t5\t\n\
+            delete\ts1\t6\t1\n";
+        let script = EditScript::from_tsv(text.as_bytes()).unwrap();
+        let mut msa = build_msa(&["ACTG-A", "AC-GTA"]);
+        let mut journal = EditJournal::new();
+        let applied = script.apply_with_journal(&mut msa, &mut journal).unwrap();
+        assert_eq!(rows(&msa), vec!["MMACXG", "ACW-GA"]);
+        assert_eq!(journal.entries().len(), 6);
+        let mut undone = msa.seq_col().clone();
+        journal.inverse().apply(&mut undone).unwrap();
+        assert_eq!(rows(&undone), vec!["ACTG-A", "AC-GTA"]);
+        assert_eq!(msa.length(), 6);
+        let columns = applied.iter().map(|x| x.column).collect::<Vec<_>>();
+        assert_eq!(columns, vec![1, 5, 7, 3, 6, 7]);
+    }
+
+    #[test]
+    fn test_apply_is_atomic() {
+        let mut msa = build_msa(&["ACTG-A", "AC-GTA"]);
+        let failing = [
+            "replace\ts1\t1\tX\nreplace\ts3\t1\tX\n",
+            "insert\ts1\t1\tX\n",
+            "delete\t*\t2\t2\nreplace\t*\t3\tX\n",
+            "insert\t*\t2\tXX\ndelete\t*\t1\t2\n",
+            "delete\t*\t6\t2\n",
+            "delete\t*\t0\t1\n",
+        ];
+        for text in failing.iter() {
+            let script = EditScript::from_tsv(text.as_bytes()).unwrap();
+            assert!(script.apply(&mut msa).is_err(), "{}", text);
+            assert_eq!(rows(&msa), vec!["ACTG-A", "AC-GTA"]);
+        }
+        for text in ["move\ts1\t1\tX\n", "insert\ts1\tX\tA\n", "insert\n"] {
+            assert!(EditScript::from_tsv(text.as_bytes()).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_from_toml() {
+        let text = "[[edit]]\noperation = \"replace\"\nid = \"*\"\n\
+            position = 5\ncontent = \"NN\"\n\n\
+            [[edit]]\noperation = \"delete\"\nid = \"s2\"\nposition = 1\n\
+            content = \"1\"\n\n\
+            [[edit]]\noperation = \"insert\"\nid = \"s2\"\nposition = 7\n\
+            content = \"-\"\n";
+        let script = EditScript::from_toml(text).unwrap();
+        assert_eq!(script.instructions.len(), 3);
+        let mut msa = build_msa(&["ACTG-A", "AC-GTA"]);
+        script.apply(&mut msa).unwrap();
+        assert_eq!(rows(&msa), vec!["ACTGNN", "C-GNN-"]);
+    }
+    #[test]
+    fn test_replace_with_width() {
+        let text = "[[edit]]\noperation = \"replace\"\nid = \"*\"\n\
+            position = 2\ncontent = \"NN\"\nwidth = 3\n\n\
+            [[edit]]\noperation = \"delete\"\nid = \"*\"\nposition = 5\n";
+        let script = EditScript::from_toml(text).unwrap();
+        let mut msa = build_msa(&["ACTG-A", "AC-GTA"]);
+        let applied = script.apply(&mut msa).unwrap();
+        assert_eq!(rows(&msa), vec!["ANNA", "ANNA"]);
+        assert_eq!(applied[0].residues, 3);
+        assert_eq!(applied[1].column, 4);
+        // Replaced positions can not be edited again.
+        let mut script = script;
+        script.instructions[1].position = 4;
+        assert!(script.apply(&mut msa).is_err());
+    }
+}
@@ -7,6 +7,11 @@ use crate::seqs::{
   SequenceCollection
 };
 
+/// Returns true if the character is a gap, '-' or '.'.
+pub fn is_gap(c: char) -> bool {
+  c == '-' || c == '.'
+}
+
 pub trait PadWithGaps<T> {
   fn pad_with_gaps(&self, max_length: usize) -> T;
   fn pad_with_gaps_to_max_length(&self) -> T;
@@ -0,0 +1,706 @@
+use std::error::Error;
+use std::io::{BufRead, Write};
+use crate::align::PairAligner;
+use crate::edit::EditSequence;
+use crate::edit_msa::EditMSA;
+use crate::masking::{Mask, MaskColumns};
+use crate::seqs::{
+    Alignment, AnnotatedSequence, SeqError, SequenceAccesors,
+    SequenceCollection
+};
+
+/// A single recorded change of a sequence collection. Positions and row
+/// indexes are zero based, and refer to the collection at the moment of
+/// the change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JournalEntry {
+    /// The residues `old` at position `at` of a sequence were replaced by
+    /// `new`. Insertions have no old residues and deletions have no new
+    /// residues.
+    Change {
+        id: String,
+        at: usize,
+        old: Vec<char>,
+        new: Vec<char>,
+    },
+    /// A sequence was added at a row index.
+    AddRow {
+        id: String,
+        index: usize,
+        content: Vec<char>,
+    },
+    /// A sequence was removed from a row index.
+    RemoveRow {
+        id: String,
+        index: usize,
+        content: Vec<char>,
+    },
+}
+
+impl JournalEntry {
+    /// The entry that undoes this entry.
+    pub fn inverse(&self) -> Self {
+        match self.clone() {
+            JournalEntry::Change { id, at, old, new } => {
+                JournalEntry::Change { id, at, old: new, new: old }
+            }
+            JournalEntry::AddRow { id, index, content } => {
+                JournalEntry::RemoveRow { id, index, content }
+            }
+            JournalEntry::RemoveRow { id, index, content } => {
+                JournalEntry::AddRow { id, index, content }
+            }
+        }
+    }
+    fn apply(&self, seqs: &mut SequenceCollection) -> Result<(), String> {
+        match self {
+            JournalEntry::Change { id, at, old, new } => {
+                let seq = seqs
+                    .iter()
+                    .position(|x| x.id() == id)
+                    .and_then(|x| seqs.get_mut(x))
+                    .and_then(|x| x.seq_mut())
+                    .ok_or_else(|| format!("Sequence {} not found", id))?;
+                if seq.get(*at..at + old.len()) != Some(old.as_slice()) {
+                    return Err(
+                        format!(
+                            "Sequence {} does not match at position {}",
+                            id,
+                            at + 1
+                        )
+                    );
+                }
+                seq.splice(*at..at + old.len(), new.iter().cloned());
+                Ok(())
+            }
+            JournalEntry::AddRow { id, index, content } => {
+                let index = (*index).min(seqs.size());
+                seqs.insert(
+                    index,
+                    AnnotatedSequence::new(id.to_string(), content.clone())
+                ).map_err(|e| e.to_string())
+            }
+            JournalEntry::RemoveRow { id, content, .. } => {
+                let matches = seqs
+                    .get_by_id(id)
+                    .and_then(|x| x.seq())
+                    .map(|x| x == content);
+                match matches {
+                    Some(true) => {
+                        seqs.remove_by_id(id);
+                        Ok(())
+                    }
+                    Some(false) => {
+                        Err(format!("Sequence {} does not match", id))
+                    }
+                    None => Err(format!("Sequence {} not found", id)),
+                }
+            }
+        }
+    }
+    fn fields(&self) -> (&str, &str, usize, String, String) {
+        let text = |x: &Vec<char>| x.iter().collect::<String>();
+        match self {
+            JournalEntry::Change { id, at, old, new } => {
+                let operation = match (old.is_empty(), new.is_empty()) {
+                    (true, _) => "insert",
+                    (_, true) => "delete",
+                    _ => "replace",
+                };
+                (operation, id, *at, text(old), text(new))
+            }
+            JournalEntry::AddRow { id, index, content } => {
+                ("add-row", id, *index, String::new(), text(content))
+            }
+            JournalEntry::RemoveRow { id, index, content } => {
+                ("remove-row", id, *index, text(content), String::new())
+            }
+        }
+    }
+}
+
+/// The changes made to a sequence collection, that can be saved as a
+/// patch, inverted and applied to other collections.
+///
+/// Patches are TSV files with the operation (insert, delete, replace,
+/// add-row or remove-row), the sequence ID, the one based position or row,
+/// the old content and the new content on each line.
+/// ```
+/// use famlib::journal::EditJournal;
+/// use famlib::seqs::{AnnotatedSequence, SequenceAccesors, SequenceCollection};
+/// let mut old = SequenceCollection::new();
+/// old.add(AnnotatedSequence::from_string("s1", "ACTG")).unwrap();
+/// let mut new = SequenceCollection::new();
+/// new.add(AnnotatedSequence::from_string("s1", "ACCCG")).unwrap();
+/// let journal = EditJournal::diff(&old, &new);
+/// let mut patch = vec![];
+/// journal.write_patch(&mut patch).unwrap();
+/// assert_eq!(String::from_utf8(patch).unwrap(), "replace\ts1\t3\tT\tCC\n");
+/// let mut edited = old.clone();
+/// journal.apply(&mut edited).unwrap();
+/// assert_eq!(edited.get(0).unwrap().seq_as_string(), "ACCCG");
+/// journal.inverse().apply(&mut edited).unwrap();
+/// assert_eq!(edited, old);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EditJournal {
+    entries: Vec<JournalEntry>,
+}
+
+impl EditJournal {
+    pub fn new() -> Self {
+        EditJournal::default()
+    }
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn record(&mut self, entry: JournalEntry) {
+        self.entries.push(entry);
+    }
+    pub fn extend(&mut self, other: EditJournal) {
+        self.entries.extend(other.entries);
+    }
+    /// The journal that undoes all the changes of this journal.
+    pub fn inverse(&self) -> Self {
+        EditJournal {
+            entries: self.entries.iter().rev().map(|x| x.inverse()).collect(),
+        }
+    }
+    /// Applies all changes in order. Replaced, deleted and removed content
+    /// must match the collection. The collection is only modified if all
+    /// the changes succeed.
+    pub fn apply(
+        &self,
+        seqs: &mut SequenceCollection,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut edited = seqs.clone();
+        for (i, entry) in self.entries.iter().enumerate() {
+            entry
+                .apply(&mut edited)
+                .map_err(|e| format!("Change {}: {}", i + 1, e))?;
+        }
+        *seqs = edited;
+        Ok(())
+    }
+    /// Computes the changes that transform `old` into `new`, matching
+    /// sequences by ID. Each shared sequence gets a single change spanning
+    /// from its first to its last differing position. Changes in the order
+    /// of shared sequences are not recorded.
+    pub fn diff<T: SequenceAccesors, U: SequenceAccesors>(
+        old: &T,
+        new: &U,
+    ) -> Self {
+        let mut journal = EditJournal::new();
+        let content = |x: &AnnotatedSequence| {
+            x.seq().cloned().unwrap_or_default()
+        };
+        let removed = old
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| !new.contains(x.id()))
+            .collect::<Vec<_>>();
+        for (index, seq) in removed.into_iter().rev() {
+            journal.record(
+                JournalEntry::RemoveRow {
+                    id: seq.id().to_string(),
+                    index,
+                    content: content(seq),
+                }
+            );
+        }
+        for seq in old.iter() {
+            if let Some(other) = new.get_by_id(seq.id()) {
+                let (a, b) = (content(seq), content(other));
+                let prefix = a
+                    .iter()
+                    .zip(b.iter())
+                    .take_while(|(x, y)| x == y)
+                    .count();
+                let suffix = a[prefix..]
+                    .iter()
+                    .rev()
+                    .zip(b[prefix..].iter().rev())
+                    .take_while(|(x, y)| x == y)
+                    .count();
+                if prefix + suffix < a.len().max(b.len()) {
+                    journal.record(
+                        JournalEntry::Change {
+                            id: seq.id().to_string(),
+                            at: prefix,
+                            old: a[prefix..a.len() - suffix].to_vec(),
+                            new: b[prefix..b.len() - suffix].to_vec(),
+                        }
+                    );
+                }
+            }
+        }
+        for (index, seq) in new.iter().enumerate() {
+            if !old.contains(seq.id()) {
+                journal.record(
+                    JournalEntry::AddRow {
+                        id: seq.id().to_string(),
+                        index,
+                        content: content(seq),
+                    }
+                );
+            }
+        }
+        journal
+    }
+    pub fn write_patch<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for entry in self.entries.iter() {
+            let (operation, id, position, old, new) = entry.fields();
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}",
+                operation,
+                id,
+                position + 1,
+                old,
+                new
+            )?;
+        }
+        Ok(())
+    }
+    /// Reads a patch. Empty lines and lines starting with '#' are skipped.
+    pub fn from_patch<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut journal = EditJournal::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() != 5 {
+                return Err(format!("Line {}: expected five fields", i + 1).into());
+            }
+            let position = fields[2]
+                .parse::<usize>()
+                .ok()
+                .filter(|x| *x > 0)
+                .ok_or_else(
+                    || format!("Line {}: invalid position '{}'", i + 1, fields[2])
+                )? - 1;
+            let id = fields[1].to_string();
+            let old = fields[3].chars().collect::<Vec<_>>();
+            let new = fields[4].chars().collect::<Vec<_>>();
+            let entry = match fields[0] {
+                "insert" | "delete" | "replace" => {
+                    JournalEntry::Change { id, at: position, old, new }
+                }
+                "add-row" => {
+                    JournalEntry::AddRow { id, index: position, content: new }
+                }
+                "remove-row" => {
+                    JournalEntry::RemoveRow { id, index: position, content: old }
+                }
+                x => {
+                    return Err(
+                        format!("Line {}: unknown operation '{}'", i + 1, x)
+                            .into()
+                    )
+                }
+            };
+            journal.record(entry);
+        }
+        Ok(journal)
+    }
+}
+
+/// Wraps a sequence or an alignment, and records all the changes made
+/// through `EditSequence` or `EditMSA` into a journal.
+/// ```
+/// use famlib::edit::EditSequence;
+/// use famlib::journal::{EditJournal, Journaled};
+/// use famlib::seqs::AnnotatedSequence;
+/// let mut seq = AnnotatedSequence::from_string("s1", "ACTG");
+/// let mut journal = EditJournal::new();
+/// let mut journaled = Journaled::new(&mut seq, &mut journal);
+/// journaled.edit_insert(vec!['-'], 2).unwrap();
+/// journaled.edit_delete(0, 1).unwrap();
+/// assert_eq!(seq.seq_as_string(), "C-TG");
+/// assert_eq!(journal.entries().len(), 2);
+/// ```
+pub struct Journaled<'a, T> {
+    target: &'a mut T,
+    journal: &'a mut EditJournal,
+}
+
+impl<'a, T> Journaled<'a, T> {
+    pub fn new(target: &'a mut T, journal: &'a mut EditJournal) -> Self {
+        Journaled { target, journal }
+    }
+}
+
+impl Journaled<'_, AnnotatedSequence> {
+    fn record_edit<F>(
+        &mut self,
+        at: usize,
+        count: usize,
+        new: &[char],
+        edit: F,
+    ) -> Result<(), SeqError>
+    where
+        F: FnOnce(&mut AnnotatedSequence) -> Result<(), SeqError>
+    {
+        let old = self
+            .target
+            .seq()
+            .and_then(|x| x.get(at..at + count))
+            .map(|x| x.to_vec())
+            .unwrap_or_default();
+        edit(self.target)?;
+        if !(old.is_empty() && new.is_empty()) {
+            self.journal.record(
+                JournalEntry::Change {
+                    id: self.target.id().to_string(),
+                    at,
+                    old,
+                    new: new.to_vec(),
+                }
+            );
+        }
+        Ok(())
+    }
+}
+
+impl EditSequence for Journaled<'_, AnnotatedSequence> {
+    fn edit_insert(
+        &mut self,
+        new: Vec<char>,
+        at: usize,
+    ) -> Result<(), SeqError> {
+        let content = new.clone();
+        self.record_edit(at, 0, &content, |x| x.edit_insert(new, at))
+    }
+    fn edit_replace(
+        &mut self,
+        new: Vec<char>,
+        at: usize,
+        count: usize,
+    ) -> Result<(), SeqError> {
+        let content = new.clone();
+        self.record_edit(at, count, &content, |x| x.edit_replace(new, at, count))
+    }
+    fn edit_delete(&mut self, at: usize, count: usize) -> Result<(), SeqError> {
+        self.record_edit(at, count, &[], |x| x.edit_delete(at, count))
+    }
+}
+
+/// Groups sorted and distinct positions into runs of consecutive
+/// positions, given as their first and last position. Runs are sorted from
+/// the last to the first, so that removing them in order does not shift
+/// the positions of the remaining runs.
+fn runs(positions: &[usize]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = vec![];
+    for position in positions {
+        match runs.last_mut() {
+            Some((_, last)) if *last + 1 == *position => *last = *position,
+            _ => runs.push((*position, *position)),
+        }
+    }
+    runs.reverse();
+    runs
+}
+
+impl Journaled<'_, Alignment> {
+    /// The content of a row, empty if the row does not exist.
+    fn row(&self, index: usize) -> &[char] {
+        self.target
+            .get(index)
+            .and_then(|x| x.seq())
+            .map_or(&[], |x| x.as_slice())
+    }
+    fn id(&self, index: usize) -> String {
+        self.target
+            .get(index)
+            .map_or_else(String::new, |x| x.id().to_string())
+    }
+    /// Records a change of a row if the old and new content differ.
+    fn record_change(
+        &mut self,
+        index: usize,
+        at: usize,
+        old: &[char],
+        new: &[char],
+    ) {
+        if old != new {
+            self.journal.record(
+                JournalEntry::Change {
+                    id: self.id(index),
+                    at,
+                    old: old.to_vec(),
+                    new: new.to_vec(),
+                }
+            );
+        }
+    }
+}
+
+impl EditMSA for Journaled<'_, Alignment> {
+    fn insert_empty_columns(
+        &mut self,
+        at: usize,
+        ncols: usize,
+        ch: char,
+    ) -> Result<(), SeqError> {
+        self.target.insert_empty_columns(at, ncols, ch)?;
+        for i in 0..self.target.size() {
+            self.record_change(i, at, &[], &vec![ch; ncols]);
+        }
+        Ok(())
+    }
+    fn insert_empty_rows(
+        &mut self,
+        at: usize,
+        rownames: Vec<&str>,
+        ch: char,
+    ) -> Result<(), SeqError> {
+        let names = rownames.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        self.target.insert_empty_rows(at, rownames, ch)?;
+        let content = vec![ch; self.target.length()];
+        for id in names {
+            self.journal.record(
+                JournalEntry::AddRow { id, index: at, content: content.clone() }
+            );
+        }
+        Ok(())
+    }
+    fn insert_columns(
+        &mut self,
+        at: usize,
+        content: Vec<Vec<char>>
+    ) -> Result<(), SeqError> {
+        let rows = (0..self.target.size())
+            .map(|i| content.iter().map(|x| x.get(i).copied()).collect())
+            .collect::<Vec<Option<Vec<char>>>>();
+        self.target.insert_columns(at, content)?;
+        for (i, new) in rows.into_iter().enumerate() {
+            self.record_change(i, at, &[], &new.unwrap_or_default());
+        }
+        Ok(())
+    }
+    fn insert_rows(
+        &mut self,
+        at: usize,
+        rownames: Vec<&str>,
+        content: Vec<Vec<char>>
+    ) -> Result<(), SeqError> {
+        let added = rownames
+            .iter()
+            .map(|x| x.to_string())
+            .zip(content.clone())
+            .collect::<Vec<_>>();
+        self.target.insert_rows(at, rownames, content)?;
+        for (id, content) in added {
+            self.journal.record(
+                JournalEntry::AddRow { id, index: at, content }
+            );
+        }
+        Ok(())
+    }
+    fn remove_columns(
+        &mut self,
+        positions: Vec<usize>,
+    ) -> Result<(), SeqError> {
+        let mut sorted = positions;
+        sorted.sort_unstable();
+        sorted.dedup();
+        let runs = runs(&sorted);
+        let removed = (0..self.target.size())
+            .map(|i| {
+                let row = self.row(i);
+                runs.iter()
+                    .map(|(first, last)| {
+                        let old = row.get(*first..=*last).unwrap_or(&[]);
+                        (*first, old.to_vec())
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        self.target.remove_columns(sorted)?;
+        for (i, row) in removed.into_iter().enumerate() {
+            for (at, old) in row {
+                self.record_change(i, at, &old, &[]);
+            }
+        }
+        Ok(())
+    }
+    fn remove_rows(&mut self, positions: Vec<usize>) -> Result<(), SeqError> {
+        let mut sorted = positions;
+        sorted.sort_unstable();
+        sorted.dedup();
+        let removed = sorted
+            .iter()
+            .rev()
+            .map(|i| (self.id(*i), *i, self.row(*i).to_vec()))
+            .collect::<Vec<_>>();
+        self.target.remove_rows(sorted)?;
+        for (id, index, content) in removed {
+            self.journal.record(JournalEntry::RemoveRow { id, index, content });
+        }
+        Ok(())
+    }
+    fn replace_content(
+        &mut self,
+        at_x: usize,
+        at_y: usize,
+        content: Vec<Vec<char>>,
+    ) -> Result<(), SeqError> {
+        let old = content
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                self.row(at_y + i)
+                    .get(at_x..at_x + x.len())
+                    .unwrap_or(&[])
+                    .to_vec()
+            })
+            .collect::<Vec<_>>();
+        self.target.replace_content(at_x, at_y, content.clone())?;
+        for (i, (old, new)) in old.iter().zip(&content).enumerate() {
+            self.record_change(at_y + i, at_x, old, new);
+        }
+        Ok(())
+    }
+    fn realign_columns(
+        &mut self,
+        start: usize,
+        end: usize,
+        aligner: &PairAligner,
+    ) -> Result<(), SeqError> {
+        let length = self.target.length();
+        let old = (0..self.target.size())
+            .map(|i| self.row(i).get(start..end).unwrap_or(&[]).to_vec())
+            .collect::<Vec<_>>();
+        self.target.realign_columns(start, end, aligner)?;
+        let new_end = end + self.target.length() - length;
+        for (i, old) in old.iter().enumerate() {
+            let new = self.row(i)[start..new_end].to_vec();
+            self.record_change(i, start, old, &new);
+        }
+        Ok(())
+    }
+}
+
+impl MaskColumns for Journaled<'_, Alignment> {
+    fn mask_columns(
+        &mut self,
+        positions: &[usize],
+        mask: Mask,
+    ) -> Result<(), SeqError> {
+        let mut sorted = positions.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        let runs = runs(&sorted);
+        let old = (0..self.target.size())
+            .map(|i| {
+                let row = self.row(i);
+                runs.iter()
+                    .map(|(first, last)| {
+                        row.get(*first..=*last).unwrap_or(&[]).to_vec()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        self.target.mask_columns(&sorted, mask)?;
+        for (i, old) in old.iter().enumerate() {
+            for ((first, last), old) in runs.iter().zip(old) {
+                let new = self.row(i)[*first..=*last].to_vec();
+                self.record_change(i, *first, old, &new);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::{build_msa, rows};
+    use super::*;
+
+    #[test]
+    fn test_journaled_alignment_undo() {
+        let original = build_msa(&["ACTG-A", "AC-GTA", "ACCGTA"]);
+        let mut msa = original.clone();
+        let mut journal = EditJournal::new();
+        let mut journaled = Journaled::new(&mut msa, &mut journal);
+        journaled.insert_empty_columns(1, 2, '-').unwrap();
+        journaled.remove_rows(vec![1]).unwrap();
+        journaled.replace_content(0, 1, vec![vec!['W']]).unwrap();
+        journaled.remove_columns(vec![7]).unwrap();
+        let mut edited = msa.seq_col().clone();
+        assert_eq!(rows(&edited), vec!["A--CTG-", "W--CCGT"]);
+        journal.inverse().apply(&mut edited).unwrap();
+        assert_eq!(&edited, original.seq_col());
+        // Reapply to the original alignment.
+        let mut replayed = original.seq_col().clone();
+        journal.apply(&mut replayed).unwrap();
+        assert_eq!(&replayed, msa.seq_col());
+    }
+
+    #[test]
+    fn test_journaled_alignment_records_columns() {
+        let original = build_msa(&["ACTGTA", "AC-GTA"]);
+        let mut msa = original.clone();
+        let mut journal = EditJournal::new();
+        let mut journaled = Journaled::new(&mut msa, &mut journal);
+        journaled.remove_columns(vec![5, 0, 1, 5]).unwrap();
+        journaled.mask_columns(&[1, 2], Mask::Lowercase).unwrap();
+        assert_eq!(rows(&msa), vec!["Tgt", "-gt"]);
+        let change = |id: &str, at, old: &str, new: &str| JournalEntry::Change {
+            id: id.to_string(),
+            at,
+            old: old.chars().collect(),
+            new: new.chars().collect(),
+        };
+        assert_eq!(
+            journal.entries(),
+            &[
+                change("s1", 5, "A", ""),
+                change("s1", 0, "AC", ""),
+                change("s2", 5, "A", ""),
+                change("s2", 0, "AC", ""),
+                change("s1", 1, "GT", "gt"),
+                change("s2", 1, "GT", "gt"),
+            ]
+        );
+        let mut edited = msa.seq_col().clone();
+        journal.inverse().apply(&mut edited).unwrap();
+        assert_eq!(&edited, original.seq_col());
+    }
+
+    #[test]
+    fn test_patch_roundtrip_and_conflicts() {
+        let old = build_msa(&["ACTG-A", "AC-GTA", "ACCGTA"]);
+        let mut new = SequenceCollection::new();
+        new.add(AnnotatedSequence::from_string("s4", "TTTT")).unwrap();
+        new.add(AnnotatedSequence::from_string("s3", "ACCGTA")).unwrap();
+        new.add(AnnotatedSequence::from_string("s1", "ACT")).unwrap();
+        let journal = EditJournal::diff(&old, &new);
+        let mut patch = vec![];
+        journal.write_patch(&mut patch).unwrap();
+        assert_eq!(
+            String::from_utf8(patch.clone()).unwrap(),
+            "remove-row\ts2\t2\tAC-GTA\t\n\
+            delete\ts1\t4\tG-A\t\n\
+            add-row\ts4\t1\t\tTTTT\n"
+        );
+        let read = EditJournal::from_patch(&patch[..]).unwrap();
+        assert_eq!(read, journal);
+        let mut edited = old.seq_col().clone();
+        read.apply(&mut edited).unwrap();
+        let ids = edited.iter().map(|x| x.id().to_string()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["s4", "s1", "s3"]);
+        assert_eq!(edited.get_by_id("s1").unwrap().seq_as_string(), "ACT");
+        // Applying twice fails and keeps the collection.
+        assert!(read.apply(&mut edited).is_err());
+        assert_eq!(edited.size(), 3);
+        assert!(EditJournal::from_patch("move\ts1\t1\t\t\n".as_bytes()).is_err());
+        assert!(EditJournal::from_patch("insert\ts1\t0\t\tA\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error::Error;
+use std::thread;
+
+use crate::pairwise::{by_rows, PairwiseMatrix};
+use crate::seqs::{SequenceAccesors, SequenceCollection};
+
+/// Removes gaps and converts residues to uppercase.
+fn ungapped(seq: &[char]) -> Vec<char> {
+    seq.iter()
+        .filter(|c| **c != '-' && **c != '.')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Hashes a word with FNV-1a, followed by the SplitMix64 finalizer to mix
+/// the bits of short words.
+fn hash_kmer(kmer: &[char]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for c in kmer {
+        for b in (*c as u32).to_le_bytes() {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
+
+fn kmer_hashes(seq: &[char], k: usize) -> impl Iterator<Item = u64> + '_ {
+    seq.windows(k.max(1)).map(hash_kmer)
+}
+
+/// The number of times each word of length k occurs in a sequence. Gaps
+/// are removed before counting.
+#[derive(Clone, Debug)]
+pub struct KmerProfile {
+    size: usize,
+    // Pairs of word hash and count, sorted by hash.
+    counts: Vec<(u64, usize)>,
+}
+
+impl KmerProfile {
+    pub fn new(seq: &[char], k: usize) -> Self {
+        let seq = ungapped(seq);
+        let mut counts = HashMap::new();
+        let mut size = 0;
+        if seq.len() >= k {
+            for hash in kmer_hashes(&seq, k) {
+                *counts.entry(hash).or_insert(0usize) += 1;
+                size += 1;
+            }
+        }
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_unstable();
+        KmerProfile { size, counts }
+    }
+    /// The total number of words in the sequence.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    /// The fractional common k-mer distance (Edgar 2004).
+    ///
+    /// It is one minus the number of shared words, divided by the number
+    /// of words in the shorter sequence.
+    /// ```
+    /// use famlib::kmer::KmerProfile;
+    /// let seq1 = "ACGTAC".chars().collect::<Vec<_>>();
+    /// let seq2 = "ACG-TTT".chars().collect::<Vec<_>>();
+    /// let p1 = KmerProfile::new(&seq1, 3);
+    /// let p2 = KmerProfile::new(&seq2, 3);
+    /// // Shared words: ACG and CGT, of four words in seq2.
+    /// assert_eq!(p1.distance(&p2), 0.5);
+    /// ```
+    pub fn distance(&self, other: &KmerProfile) -> f64 {
+        let (mut i, mut j) = (0, 0);
+        let mut shared = 0;
+        while i < self.counts.len() && j < other.counts.len() {
+            let ((a, n), (b, m)) = (self.counts[i], other.counts[j]);
+            match a.cmp(&b) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    shared += n.min(m);
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        match self.size.min(other.size) {
+            0 => 1f64,
+            x => 1f64 - shared as f64 / x as f64,
+        }
+    }
+}
+
+/// A bottom-s MinHash sketch of the words of length k of a sequence. Gaps
+/// are removed before hashing.
+#[derive(Clone, Debug)]
+pub struct MinHashSketch {
+    k: usize,
+    sketch_size: usize,
+    hashes: Vec<u64>,
+}
+
+impl MinHashSketch {
+    pub fn new(seq: &[char], k: usize, sketch_size: usize) -> Self {
+        let seq = ungapped(seq);
+        let mut hashes = match seq.len() >= k {
+            true => kmer_hashes(&seq, k).collect::<Vec<_>>(),
+            false => vec![],
+        };
+        hashes.sort_unstable();
+        hashes.dedup();
+        hashes.truncate(sketch_size);
+        MinHashSketch {
+            k,
+            sketch_size,
+            hashes,
+        }
+    }
+    /// The smallest hash values of the words of the sequence.
+    pub fn hashes(&self) -> &Vec<u64> {
+        &self.hashes
+    }
+    /// Estimates the Jaccard index of the sets of words of two sequences.
+    ///
+    /// The estimate is exact when the sketch size is larger than the
+    /// number of words of both sequences.
+    pub fn jaccard(&self, other: &MinHashSketch) -> f64 {
+        let limit = self.sketch_size.min(other.sketch_size);
+        let (mut i, mut j) = (0, 0);
+        let (mut union, mut shared) = (0usize, 0usize);
+        let (len1, len2) = (self.hashes.len(), other.hashes.len());
+        while union < limit && (i < len1 || j < len2) {
+            match (self.hashes.get(i), other.hashes.get(j)) {
+                (Some(a), Some(b)) if a == b => {
+                    shared += 1;
+                    i += 1;
+                    j += 1;
+                }
+                (Some(a), Some(b)) if a < b => i += 1,
+                (Some(_), None) => i += 1,
+                _ => j += 1,
+            }
+            union += 1;
+        }
+        match union {
+            0 => 0f64,
+            x => shared as f64 / x as f64,
+        }
+    }
+    /// The Mash distance (Ondov et al. 2016), estimating the substitution
+    /// rate between two sequences from the Jaccard index. Distances are
+    /// truncated to one.
+    /// ```
+    /// use famlib::kmer::MinHashSketch;
+    /// let seq = "MKVLAAGIVGLLLAQ".chars().collect::<Vec<_>>();
+    /// let sketch = MinHashSketch::new(&seq, 3, 100);
+    /// assert_eq!(sketch.distance(&sketch), 0.0);
+    /// let other = "WWWWW".chars().collect::<Vec<_>>();
+    /// let other = MinHashSketch::new(&other, 3, 100);
+    /// assert_eq!(sketch.distance(&other), 1.0);
+    /// ```
+    pub fn distance(&self, other: &MinHashSketch) -> f64 {
+        let jaccard = self.jaccard(other);
+        match jaccard > 0f64 {
+            true => ((2f64 * jaccard / (1f64 + jaccard)).ln().abs()
+                / self.k.max(1) as f64)
+                .min(1f64),
+            false => 1f64,
+        }
+    }
+}
+
+/// Methods to estimate distances between unaligned sequences.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KmerMethod {
+    /// Fractional common k-mer distance of k-mer count profiles.
+    Profile,
+    /// Mash distance of MinHash sketches.
+    MinHash,
+}
+
+enum Sketches {
+    Profiles(Vec<KmerProfile>),
+    MinHash(Vec<MinHashSketch>),
+}
+
+impl Sketches {
+    fn distance(&self, i: usize, j: usize) -> f64 {
+        match self {
+            Sketches::Profiles(x) => x[i].distance(&x[j]),
+            Sketches::MinHash(x) => x[i].distance(&x[j]),
+        }
+    }
+}
+
+/// Pairs of sequence index and distance, sorted by distance.
+pub type Neighbours = Vec<(usize, f64)>;
+
+/// Computes alignment-free distances between all pairs of sequences of a
+/// collection, using many threads. Sequences do not need to be aligned.
+pub struct KmerDistances<'a> {
+    seqs: &'a SequenceCollection,
+    method: KmerMethod,
+    k: usize,
+    sketch_size: usize,
+    threads: usize,
+}
+
+impl<'a> KmerDistances<'a> {
+    pub fn new(seqs: &'a SequenceCollection) -> Self {
+        KmerDistances {
+            seqs,
+            method: KmerMethod::Profile,
+            k: 3,
+            sketch_size: 128,
+            threads: thread::available_parallelism()
+                .map(|x| x.get())
+                .unwrap_or(1),
+        }
+    }
+    pub fn with_method(mut self, method: KmerMethod) -> Self {
+        self.method = method;
+        self
+    }
+    /// Sets the word length. Zero is ignored.
+    pub fn with_kmer_size(mut self, k: usize) -> Self {
+        if k > 0 {
+            self.k = k;
+        }
+        self
+    }
+    /// Sets the number of hashes kept in MinHash sketches. Zero is ignored.
+    pub fn with_sketch_size(mut self, sketch_size: usize) -> Self {
+        if sketch_size > 0 {
+            self.sketch_size = sketch_size;
+        }
+        self
+    }
+    /// Sets the number of threads. Zero is ignored.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        if threads > 0 {
+            self.threads = threads;
+        }
+        self
+    }
+
+    fn sketches(&self) -> Result<Sketches, Box<dyn Error>> {
+        let seqs = self
+            .seqs
+            .iter()
+            .map(|x| x.seq().ok_or("Sequence without residues"))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(match self.method {
+            KmerMethod::Profile => Sketches::Profiles(
+                seqs.iter().map(|x| KmerProfile::new(x, self.k)).collect()
+            ),
+            KmerMethod::MinHash => Sketches::MinHash(
+                seqs.iter()
+                    .map(|x| MinHashSketch::new(x, self.k, self.sketch_size))
+                    .collect()
+            ),
+        })
+    }
+
+    fn ids(&self) -> Vec<String> {
+        self.seqs.iter().map(|x| x.id().to_string()).collect()
+    }
+
+    /// Computes the distances between all pairs of sequences. The matrix
+    /// stores similarities, that are one minus the distances.
+    /// ```
+    /// use famlib::kmer::KmerDistances;
+    /// use famlib::seqs::{
+    ///     AnnotatedSequence, SequenceAccesors, SequenceCollection
+    /// };
+    /// let mut seqs = SequenceCollection::new();
+    /// for (id, seq) in [("s1", "ACGTAC"), ("s2", "ACGTTT"), ("s3", "AC")] {
+    ///     seqs.add(
+    ///         AnnotatedSequence::from_string(id.to_string(), seq.to_string())
+    ///     ).unwrap();
+    /// }
+    /// let matrix = KmerDistances::new(&seqs).matrix().unwrap();
+    /// assert_eq!(matrix.identity(0, 1), 0.5);
+    /// assert_eq!(matrix.identity(0, 2), 0.0);
+    /// ```
+    pub fn matrix(&self) -> Result<PairwiseMatrix, Box<dyn Error>> {
+        let sketches = self.sketches()?;
+        let size = self.seqs.size();
+        let rows = by_rows(size, self.threads, |i| {
+            Ok(
+                (i + 1..size)
+                    .map(|j| 1f64 - sketches.distance(i, j))
+                    .collect::<Vec<_>>()
+            )
+        })?;
+        Ok(PairwiseMatrix::from_rows(self.ids(), rows))
+    }
+
+    /// Finds the `n` nearest sequences of every sequence. Neighbours are
+    /// given as pairs of sequence index and distance, sorted by distance.
+    ///
+    /// The full distance matrix is never stored.
+    pub fn nearest(
+        &self,
+        n: usize,
+    ) -> Result<Vec<Neighbours>, Box<dyn Error>> {
+        let sketches = self.sketches()?;
+        let size = self.seqs.size();
+        Ok(by_rows(size, self.threads, |i| {
+            let mut distances = (0..size)
+                .filter(|j| *j != i)
+                .map(|j| (j, sketches.distance(i, j)))
+                .collect::<Vec<_>>();
+            distances.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+            distances.truncate(n);
+            Ok(distances)
+        })?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::seqs::AnnotatedSequence;
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_kmer_profile() {
+        let p1 = KmerProfile::new(&chars("AAAAC"), 2);
+        let p2 = KmerProfile::new(&chars("aa-a"), 2);
+        assert_eq!(p1.size(), 4);
+        assert_eq!(p2.size(), 2);
+        assert_eq!(p1.distance(&p2), 0.0);
+        assert_eq!(p2.distance(&p1), 0.0);
+        let short = KmerProfile::new(&chars("A"), 2);
+        assert_eq!(short.size(), 0);
+        assert_eq!(p1.distance(&short), 1.0);
+    }
+
+    #[test]
+    fn test_minhash_jaccard() {
+        // Words of length 2: {AC, CG, GT} and {AC, CG, GA}
+        let s1 = MinHashSketch::new(&chars("ACGT"), 2, 10);
+        let s2 = MinHashSketch::new(&chars("ACGA"), 2, 10);
+        assert_eq!(s1.hashes().len(), 3);
+        assert_eq!(s1.jaccard(&s2), 0.5);
+        let expected = -(2f64 * 0.5 / 1.5).ln() / 2f64;
+        assert!((s1.distance(&s2) - expected).abs() < 1e-10);
+        let small = MinHashSketch::new(&chars("ACGT"), 2, 1);
+        assert_eq!(small.hashes().len(), 1);
+        assert_eq!(small.hashes()[0], s1.hashes()[0]);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let mut seqs = SequenceCollection::new();
+        for (i, s) in ["MKVLAAGIVG", "MKVLAAGIVA", "WWPPWWPPHH", "MKVLA"]
+            .iter()
+            .enumerate()
+        {
+            seqs.add(
+                AnnotatedSequence::from_string(format!("s{}", i), s.to_string())
+            ).unwrap();
+        }
+        for method in [KmerMethod::Profile, KmerMethod::MinHash] {
+            let distances = KmerDistances::new(&seqs)
+                .with_method(method)
+                .with_threads(2);
+            let nearest = distances.nearest(2).unwrap();
+            let matrix = distances.matrix().unwrap();
+            assert_eq!(nearest.len(), 4);
+            // All words of s3 are in s0, but s1 shares more words.
+            let expected = match method {
+                KmerMethod::Profile => 3,
+                KmerMethod::MinHash => 1,
+            };
+            assert_eq!(nearest[0][0].0, expected);
+            assert_eq!(nearest[2].len(), 2);
+            for (i, neighbours) in nearest.iter().enumerate() {
+                for (j, d) in neighbours {
+                    let expected = 1f64 - matrix.identity(i, *j);
+                    assert!((*d - expected).abs() < 1e-12);
+                }
+            }
+        }
+    }
+}
@@ -5,6 +5,7 @@ pub mod combine;
 pub mod random;
 pub mod conservation;
 pub mod plotting;
+pub mod logo;
 pub mod clustering;
 pub mod random_voronoi;
 pub mod matrices;
@@ -12,6 +13,22 @@ pub mod filter;
 pub mod degap;
 pub mod gapping;
 pub mod trim;
+pub mod pssm;
+pub mod stats;
+pub mod pairwise;
+pub mod coevolution;
+pub mod similarity;
+pub mod weights;
+pub mod kmer;
+pub mod align;
+pub mod progressive;
+pub mod mapping;
+pub mod ranges;
+pub mod masking;
+pub mod edit_script;
+pub mod journal;
+#[cfg(test)]
+mod test_utils;
 
 pub mod seqs {
     use std::{
@@ -1150,35 +1167,92 @@ pub mod seqs {
             self.seqs
         }
 
+        /// Returns the columns that have only gaps.
+        pub fn all_gap_columns(&self) -> Vec<usize> {
+            self.columns()
+                .enumerate()
+                .filter(|(_, column)| {
+                    column.iter().all(|x| **x == '-' || **x == '.')
+                })
+                .map(|(i, _)| i)
+                .collect()
+        }
+
+        /// Returns the columns with a gap frequency greater than the
+        /// threshold.
+        pub fn frq_gap_columns(&self, threshold: f64) -> Vec<usize> {
+            self.col_gap_frq()
+                .unwrap_or_default()
+                .iter()
+                .enumerate()
+                .filter(|(_, frq)| **frq > threshold)
+                .map(|(i, _)| i)
+                .collect()
+        }
+
         pub fn remove_all_gap_columns(&mut self) {
-            let mut to_remove = vec![];
-            for i in 0..self.length() {
-                match self.column(i) {
-                    None => (),
-                    Some(column) => {
-                        if column.iter().all(|x| *x == '-' || *x == '.') {
-                            to_remove.push(i);
-                        }
-                    }
-                }
-            }
+            let to_remove = self.all_gap_columns();
             for ann_seq in self.seqs.sequences.iter_mut() {
                 ann_seq.remove_positions(&to_remove);
             }
+            self.length = self.length.map(|x| x - to_remove.len());
         }
 
         pub fn remove_frq_gap_columns(&mut self, threshold: f64) {
-            let mut to_remove = vec![];
-            if let Some(frqs) = self.col_gap_frq() {
-                for (i, frq) in frqs.iter().enumerate() {
-                    if *frq > threshold {
-                        to_remove.push(i);
-                    }
-                }
+            let to_remove = self.frq_gap_columns(threshold);
+            for ann_seq in self.seqs.sequences.iter_mut() {
+                ann_seq.remove_positions(&to_remove);
             }
+            self.length = self.length.map(|x| x - to_remove.len());
+        }
+
+        /// Returns the columns where the sequence with the given id has a
+        /// gap.
+        /// ```
+        /// use famlib::seqs::{
+        ///     AnnotatedSequence, SequenceAccesors, SequenceCollection
+        /// };
+        /// let mut sq = SequenceCollection::new();
+        /// sq.add(AnnotatedSequence::from_string("s1", "A-C.G")).unwrap();
+        /// sq.add(AnnotatedSequence::from_string("s2", "ATCCG")).unwrap();
+        /// let mut msa = sq.to_msa().unwrap();
+        /// assert_eq!(msa.reference_gap_columns("s1").unwrap(), vec![1, 3]);
+        /// msa.remove_reference_gap_columns("s1").unwrap();
+        /// assert_eq!(msa.length(), 3);
+        /// assert_eq!(msa.get(1).unwrap().seq_as_string(), "ACG");
+        /// ```
+        pub fn reference_gap_columns(
+            &self,
+            id: &str
+        ) -> Result<Vec<usize>, SeqError> {
+            let reference = self
+                .get_by_id(id)
+                .ok_or_else(|| SeqError::NonExistenId(id.to_string()))?;
+            Ok(
+                reference
+                    .seq()
+                    .map(|x| x.as_slice())
+                    .unwrap_or(&[])
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| **c == '-' || **c == '.')
+                    .map(|(i, _)| i)
+                    .collect()
+            )
+        }
+
+        /// Removes the columns where the sequence with the given id has a
+        /// gap, projecting the alignment onto that sequence.
+        pub fn remove_reference_gap_columns(
+            &mut self,
+            id: &str
+        ) -> Result<(), SeqError> {
+            let to_remove = self.reference_gap_columns(id)?;
             for ann_seq in self.seqs.sequences.iter_mut() {
                 ann_seq.remove_positions(&to_remove);
             }
+            self.length = self.length.map(|x| x - to_remove.len());
+            Ok(())
         }
     }
 
@@ -1586,5 +1660,15 @@ mod test{
         assert_eq!(msa.get(1).unwrap().seq_as_string(), "AT--TTCA");
         assert_eq!(msa.get(2).unwrap().seq_as_string(), "ATG---CA");
         assert_eq!(msa.get(3).unwrap().seq_as_string(), "ATGC--CA");
+        assert_eq!(msa.length(), 8);
+    }
+    #[test]
+    fn test_gap_column_selection() {
+        let mut msa = sample_gapped_msa();
+        assert_eq!(msa.all_gap_columns(), vec![4, 7]);
+        assert_eq!(msa.frq_gap_columns(0.5), vec![3, 4, 7]);
+        msa.remove_frq_gap_columns(0.5);
+        assert_eq!(msa.length(), 7);
+        assert_eq!(msa.get(0).unwrap().seq_as_string(), "---TTCA");
     }
 }
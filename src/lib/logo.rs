@@ -0,0 +1,624 @@
+use std::error::Error;
+use std::f64::consts::PI;
+use std::io::{self, Write};
+use std::path::Path;
+
+use graphics::polygon;
+use graphics_buffer::{RenderBuffer, IDENTITY};
+
+use crate::conservation::Alphabet;
+use crate::plotting::{ColorScheme, NucleicAcidColors, ProteinColors};
+use crate::pssm::ProfileBuilder;
+use crate::seqs::Alignment;
+
+/// Half width of glyph strokes, relative to the size of the glyph box.
+const STROKE: f64 = 0.11;
+const MARGIN_LEFT: u32 = 40;
+const MARGIN_RIGHT: u32 = 10;
+const MARGIN_TOP: u32 = 10;
+const MARGIN_BOTTOM: u32 = 25;
+const LABEL_WIDTH: f64 = 8.0;
+const LABEL_HEIGHT: f64 = 12.0;
+const AXIS_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+/// The stacked letters of a single logo column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogoColumn {
+    /// One based position of the column in the alignment.
+    pub position: usize,
+    /// Information content of the column, in bits.
+    pub information: f64,
+    /// Letters and their heights in bits, sorted from bottom to top.
+    pub letters: Vec<(char, f64)>,
+}
+
+/// A polygon in image coordinates and its fill color.
+type Shape = (Vec<[f64; 2]>, [f32; 4]);
+
+/// Draws a sequence logo of an alignment.
+///
+/// The height of each column is its information content and the letters
+/// are stacked by frequency, the most frequent one on top.
+pub struct SequenceLogo<'a> {
+    msa: &'a Alignment,
+    is_protein: bool,
+    start: usize,
+    end: Option<usize>,
+    column_width: u32,
+    bit_height: u32,
+}
+
+impl<'a> SequenceLogo<'a> {
+    pub fn new(msa: &'a Alignment) -> Self {
+        SequenceLogo {
+            msa,
+            is_protein: true,
+            start: 1,
+            end: None,
+            column_width: 20,
+            bit_height: 40,
+        }
+    }
+    pub fn as_dna(mut self) -> Self {
+        self.is_protein = false;
+        self
+    }
+    pub fn as_protein(mut self) -> Self {
+        self.is_protein = true;
+        self
+    }
+    /// Restricts the logo to the columns between start and end.
+    /// Both positions are one based and inclusive.
+    pub fn with_range(mut self, start: usize, end: Option<usize>) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+    pub fn with_column_width(mut self, column_width: u32) -> Self {
+        if column_width > 0 {
+            self.column_width = column_width;
+        }
+        self
+    }
+    pub fn with_bit_height(mut self, bit_height: u32) -> Self {
+        if bit_height > 0 {
+            self.bit_height = bit_height;
+        }
+        self
+    }
+    fn alphabet(&self) -> Alphabet {
+        match self.is_protein {
+            true => Alphabet::Protein,
+            false => Alphabet::DNA,
+        }
+    }
+    fn max_bits(&self) -> f64 {
+        (self.alphabet().size() as f64).log2()
+    }
+    fn color_scheme(&self) -> Box<dyn ColorScheme> {
+        match self.is_protein {
+            true => Box::new(ProteinColors::new()),
+            false => Box::new(NucleicAcidColors::new()),
+        }
+    }
+    fn checked_range(&self) -> Result<(usize, usize), Box<dyn Error>> {
+        let length = self.msa.length();
+        let end = self.end.unwrap_or(length);
+        if self.start < 1 || self.start > end || end > length {
+            return Err(Box::new(io::Error::other(format!(
+                "Invalid column range {}-{} for an alignment of length {}",
+                self.start, end, length
+            ))));
+        }
+        Ok((self.start, end))
+    }
+
+    /// Computes the letter stacks of the columns in the selected range.
+    ///
+    /// Gaps and unknown characters are not considered, columns without
+    /// residues have no information.
+    /// ```
+    /// use famlib::logo::SequenceLogo;
+    /// use famlib::seqs::{
+    ///     AnnotatedSequence, SequenceAccesors, SequenceCollection
+    /// };
+    /// let mut seqs = SequenceCollection::new();
+    /// for (id, seq) in [("s1", "AC"), ("s2", "AG")] {
+    ///     seqs.add(
+    ///         AnnotatedSequence::from_string(id.to_string(), seq.to_string())
+    ///     ).unwrap();
+    /// }
+    /// let msa = seqs.to_msa().unwrap();
+    /// let columns = SequenceLogo::new(&msa).as_dna().columns().unwrap();
+    /// assert_eq!(columns[0].information, 2.0);
+    /// assert_eq!(columns[1].information, 1.0);
+    /// assert_eq!(columns[1].letters, vec![('C', 0.5), ('G', 0.5)]);
+    /// ```
+    pub fn columns(&self) -> Result<Vec<LogoColumn>, Box<dyn Error>> {
+        let (start, end) = self.checked_range()?;
+        let profile = ProfileBuilder::new(self.msa)
+            .with_alphabet(self.alphabet())
+            .with_pseudocount(0.0)
+            .build()?;
+        let symbols = self.alphabet().symbols();
+        let max_bits = self.max_bits();
+        let result = profile.counts()[start - 1..end]
+            .iter()
+            .enumerate()
+            .map(|(i, counts)| {
+                let total: f64 = counts.iter().sum();
+                if total <= 0.0 {
+                    return LogoColumn {
+                        position: start + i,
+                        information: 0.0,
+                        letters: vec![],
+                    };
+                }
+                let entropy = counts
+                    .iter()
+                    .map(|x| x / total)
+                    .filter(|x| *x > 0.0)
+                    .fold(0.0, |a, p| a - p * p.log2());
+                let information = (max_bits - entropy).max(0.0);
+                let mut letters = symbols
+                    .iter()
+                    .zip(counts.iter())
+                    .filter(|(_, c)| **c > 0.0)
+                    .map(|(s, c)| (*s, c / total * information))
+                    .collect::<Vec<_>>();
+                letters.sort_by(|a, b| a.1.total_cmp(&b.1));
+                LogoColumn {
+                    position: start + i,
+                    information,
+                    letters,
+                }
+            })
+            .collect();
+        Ok(result)
+    }
+
+    fn dimensions(&self, ncols: usize) -> (u32, u32) {
+        let logo_height = (self.max_bits() * self.bit_height as f64).ceil();
+        (
+            MARGIN_LEFT + ncols as u32 * self.column_width + MARGIN_RIGHT,
+            MARGIN_TOP + logo_height as u32 + MARGIN_BOTTOM,
+        )
+    }
+
+    /// Builds all the polygons of the logo, letters and axes.
+    fn shapes(
+        &self,
+        columns: &[LogoColumn],
+    ) -> Result<(u32, u32, Vec<Shape>), Box<dyn Error>> {
+        let (width, height) = self.dimensions(columns.len());
+        let colors = self.color_scheme();
+        let cw = self.column_width as f64;
+        let bh = self.bit_height as f64;
+        let baseline = (height - MARGIN_BOTTOM) as f64;
+        let left = MARGIN_LEFT as f64;
+        let mut shapes = vec![];
+        for (i, column) in columns.iter().enumerate() {
+            let x = left + i as f64 * cw;
+            let mut y = baseline;
+            for (letter, bits) in &column.letters {
+                let h = bits * bh;
+                y -= h;
+                if h < 0.5 {
+                    continue;
+                }
+                let color = colors.color(letter);
+                for quad in glyph(*letter) {
+                    shapes.push((place(&quad, x, y, cw, h), color));
+                }
+            }
+            if column.position == self.start || column.position % 10 == 0 {
+                let label = column.position.to_string();
+                let lw = LABEL_WIDTH * label.len() as f64;
+                let lx = x + (cw - lw) / 2.0;
+                shapes.extend(text(&label, lx, baseline + 6.0));
+            }
+        }
+        let right = left + columns.len() as f64 * cw;
+        let top = baseline - self.max_bits() * bh;
+        shapes.push((rect(left - 2.0, top, 1.0, baseline - top), AXIS_COLOR));
+        shapes.push((rect(left - 2.0, baseline, right - left + 2.0, 1.0),
+            AXIS_COLOR));
+        let mut bit = 0;
+        while bit as f64 <= self.max_bits() {
+            let ty = baseline - bit as f64 * bh;
+            shapes.push((rect(left - 6.0, ty, 4.0, 1.0), AXIS_COLOR));
+            shapes.extend(
+                text(&bit.to_string(), left - 16.0, ty - LABEL_HEIGHT / 2.0)
+            );
+            bit += 1;
+        }
+        Ok((width, height, shapes))
+    }
+
+    /// Saves the logo as a PNG image.
+    pub fn save_png(&self, outfile: &Path) -> Result<(), Box<dyn Error>> {
+        let columns = self.columns()?;
+        let (width, height, shapes) = self.shapes(&columns)?;
+        let mut buffer = RenderBuffer::new(width, height);
+        buffer.clear([1.0, 1.0, 1.0, 1.0]);
+        for (points, color) in shapes {
+            polygon(color, &points, IDENTITY, &mut buffer);
+        }
+        buffer.save(outfile)?;
+        Ok(())
+    }
+
+    /// Writes the logo as a SVG image.
+    ///
+    /// Each letter is written as a single path, so the same shapes are
+    /// drawn in PNG and SVG outputs.
+    pub fn write_svg<T: Write>(
+        &self,
+        mut writer: T,
+    ) -> Result<(), Box<dyn Error>> {
+        let columns = self.columns()?;
+        let (width, height, shapes) = self.shapes(&columns)?;
+        writeln!(
+            writer,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" \
+            height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+            w = width,
+            h = height
+        )?;
+        writeln!(
+            writer,
+            "<rect width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>",
+            width, height
+        )?;
+        for (points, color) in shapes {
+            let path = points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    format!(
+                        "{}{:.2} {:.2}",
+                        if i == 0 { "M" } else { "L" },
+                        p[0],
+                        p[1]
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                writer,
+                "<path d=\"{} Z\" fill=\"{}\"/>",
+                path,
+                hex_color(color)
+            )?;
+        }
+        writeln!(writer, "</svg>")?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn hex_color(color: [f32; 4]) -> String {
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        (color[0] * 255.0).round() as u8,
+        (color[1] * 255.0).round() as u8,
+        (color[2] * 255.0).round() as u8
+    )
+}
+
+fn rect(x: f64, y: f64, w: f64, h: f64) -> Vec<[f64; 2]> {
+    vec![[x, y], [x + w, y], [x + w, y + h], [x, y + h]]
+}
+
+/// Maps a polygon from the unit glyph box (y pointing up) to an image box
+/// whose top left corner is (x, y).
+fn place(unit: &[[f64; 2]], x: f64, y: f64, w: f64, h: f64) -> Vec<[f64; 2]> {
+    unit.iter()
+        .map(|p| [x + p[0] * w, y + (1.0 - p[1]) * h])
+        .collect()
+}
+
+/// Polygons of a label drawn with fixed size glyphs.
+fn text(label: &str, x: f64, y: f64) -> Vec<Shape> {
+    label
+        .chars()
+        .enumerate()
+        .flat_map(|(i, c)| {
+            glyph(c).into_iter().map(move |quad| {
+                (
+                    place(
+                        &quad,
+                        x + i as f64 * LABEL_WIDTH,
+                        y,
+                        LABEL_WIDTH,
+                        LABEL_HEIGHT,
+                    ),
+                    AXIS_COLOR,
+                )
+            })
+        })
+        .collect()
+}
+
+/// Points of an elliptic arc, angles in degrees.
+fn arc(
+    cx: f64,
+    cy: f64,
+    rx: f64,
+    ry: f64,
+    from: f64,
+    to: f64,
+) -> Vec<[f64; 2]> {
+    let steps = 16;
+    (0..=steps)
+        .map(|i| {
+            let angle = (from + (to - from) * i as f64 / steps as f64) * PI
+                / 180.0;
+            [cx + rx * angle.cos(), cy + ry * angle.sin()]
+        })
+        .collect()
+}
+
+/// Strokes of a character, as polylines in the unit box.
+fn strokes(c: char) -> Vec<Vec<[f64; 2]>> {
+    let line = |points: &[[f64; 2]]| points.to_vec();
+    let chain = |parts: Vec<Vec<[f64; 2]>>| parts.concat();
+    match c.to_ascii_uppercase() {
+        'A' => vec![
+            line(&[[0.0, 0.0], [0.5, 1.0], [1.0, 0.0]]),
+            line(&[[0.22, 0.4], [0.78, 0.4]]),
+        ],
+        'C' => vec![arc(0.5, 0.5, 0.5, 0.5, 45.0, 315.0)],
+        'D' => vec![chain(vec![
+            line(&[[0.45, 0.0], [0.0, 0.0], [0.0, 1.0], [0.45, 1.0]]),
+            arc(0.45, 0.5, 0.55, 0.5, 90.0, -90.0),
+        ])],
+        'E' => vec![
+            line(&[[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]]),
+            line(&[[0.0, 0.5], [0.75, 0.5]]),
+        ],
+        'F' => vec![
+            line(&[[1.0, 1.0], [0.0, 1.0], [0.0, 0.0]]),
+            line(&[[0.0, 0.5], [0.75, 0.5]]),
+        ],
+        'G' => vec![chain(vec![
+            arc(0.5, 0.5, 0.5, 0.5, 45.0, 360.0),
+            line(&[[0.55, 0.5]]),
+        ])],
+        'H' => vec![
+            line(&[[0.0, 0.0], [0.0, 1.0]]),
+            line(&[[1.0, 0.0], [1.0, 1.0]]),
+            line(&[[0.0, 0.5], [1.0, 0.5]]),
+        ],
+        'I' => vec![
+            line(&[[0.5, 0.0], [0.5, 1.0]]),
+            line(&[[0.15, 1.0], [0.85, 1.0]]),
+            line(&[[0.15, 0.0], [0.85, 0.0]]),
+        ],
+        'K' => vec![
+            line(&[[0.0, 0.0], [0.0, 1.0]]),
+            line(&[[1.0, 1.0], [0.0, 0.35]]),
+            line(&[[0.3, 0.55], [1.0, 0.0]]),
+        ],
+        'L' => vec![line(&[[0.0, 1.0], [0.0, 0.0], [1.0, 0.0]])],
+        'M' => vec![line(&[
+            [0.0, 0.0],
+            [0.0, 1.0],
+            [0.5, 0.35],
+            [1.0, 1.0],
+            [1.0, 0.0],
+        ])],
+        'N' => vec![line(&[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]])],
+        'P' => vec![chain(vec![
+            line(&[[0.0, 0.0], [0.0, 1.0], [0.6, 1.0]]),
+            arc(0.6, 0.75, 0.4, 0.25, 90.0, -90.0),
+            line(&[[0.0, 0.5]]),
+        ])],
+        'Q' => vec![
+            arc(0.5, 0.5, 0.5, 0.5, 0.0, 360.0),
+            line(&[[0.6, 0.3], [1.0, 0.0]]),
+        ],
+        'R' => vec![
+            chain(vec![
+                line(&[[0.0, 0.0], [0.0, 1.0], [0.6, 1.0]]),
+                arc(0.6, 0.75, 0.4, 0.25, 90.0, -90.0),
+                line(&[[0.0, 0.5]]),
+            ]),
+            line(&[[0.5, 0.5], [1.0, 0.0]]),
+        ],
+        'S' => vec![chain(vec![
+            arc(0.5, 0.75, 0.5, 0.25, 20.0, 270.0),
+            arc(0.5, 0.25, 0.5, 0.25, 90.0, -200.0),
+        ])],
+        'T' => vec![
+            line(&[[0.0, 1.0], [1.0, 1.0]]),
+            line(&[[0.5, 1.0], [0.5, 0.0]]),
+        ],
+        'U' => vec![chain(vec![
+            line(&[[0.0, 1.0]]),
+            arc(0.5, 0.35, 0.5, 0.35, 180.0, 360.0),
+            line(&[[1.0, 1.0]]),
+        ])],
+        'V' => vec![line(&[[0.0, 1.0], [0.5, 0.0], [1.0, 1.0]])],
+        'W' => vec![line(&[
+            [0.0, 1.0],
+            [0.25, 0.0],
+            [0.5, 0.65],
+            [0.75, 0.0],
+            [1.0, 1.0],
+        ])],
+        'Y' => vec![
+            line(&[[0.0, 1.0], [0.5, 0.5], [1.0, 1.0]]),
+            line(&[[0.5, 0.5], [0.5, 0.0]]),
+        ],
+        '0' => vec![arc(0.5, 0.5, 0.5, 0.5, 0.0, 360.0)],
+        '1' => vec![
+            line(&[[0.2, 0.75], [0.5, 1.0], [0.5, 0.0]]),
+            line(&[[0.2, 0.0], [0.8, 0.0]]),
+        ],
+        '2' => vec![chain(vec![
+            arc(0.5, 0.7, 0.5, 0.3, 160.0, -30.0),
+            line(&[[0.0, 0.0], [1.0, 0.0]]),
+        ])],
+        '3' => vec![chain(vec![
+            arc(0.5, 0.75, 0.45, 0.25, 150.0, -90.0),
+            arc(0.5, 0.25, 0.5, 0.25, 90.0, -150.0),
+        ])],
+        '4' => vec![line(&[[0.75, 0.0], [0.75, 1.0], [0.0, 0.3], [1.0, 0.3]])],
+        '5' => vec![chain(vec![
+            line(&[[0.9, 1.0], [0.1, 1.0], [0.05, 0.55]]),
+            arc(0.5, 0.3, 0.5, 0.3, 120.0, -150.0),
+        ])],
+        '6' => vec![
+            line(&[[0.85, 1.0], [0.05, 0.35]]),
+            arc(0.5, 0.3, 0.5, 0.3, 0.0, 360.0),
+        ],
+        '7' => vec![line(&[[0.0, 1.0], [1.0, 1.0], [0.35, 0.0]])],
+        '8' => vec![
+            arc(0.5, 0.75, 0.42, 0.25, 0.0, 360.0),
+            arc(0.5, 0.27, 0.5, 0.27, 0.0, 360.0),
+        ],
+        '9' => vec![
+            arc(0.5, 0.7, 0.5, 0.3, 0.0, 360.0),
+            line(&[[0.95, 0.65], [0.15, 0.0]]),
+        ],
+        _ => vec![line(&[
+            [0.0, 0.0],
+            [0.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 0.0],
+            [0.0, 0.0],
+        ])],
+    }
+}
+
+/// Filled quads of a character in the unit box. Every stroke segment is
+/// drawn as a rectangle with square ends, that also fill the joints.
+fn glyph(c: char) -> Vec<Vec<[f64; 2]>> {
+    let scale = 1.0 - 2.0 * STROKE;
+    strokes(c)
+        .into_iter()
+        .flat_map(|polyline| {
+            let points = polyline
+                .into_iter()
+                .map(|p| [STROKE + p[0] * scale, STROKE + p[1] * scale])
+                .collect::<Vec<_>>();
+            points
+                .windows(2)
+                .filter_map(|w| segment_quad(w[0], w[1]))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn segment_quad(p: [f64; 2], q: [f64; 2]) -> Option<Vec<[f64; 2]>> {
+    let (dx, dy) = (q[0] - p[0], q[1] - p[1]);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return None;
+    }
+    let (dx, dy) = (dx / length * STROKE, dy / length * STROKE);
+    let (nx, ny) = (-dy, dx);
+    let start = [p[0] - dx, p[1] - dy];
+    let end = [q[0] + dx, q[1] + dy];
+    Some(vec![
+        [start[0] + nx, start[1] + ny],
+        [end[0] + nx, end[1] + ny],
+        [end[0] - nx, end[1] - ny],
+        [start[0] - nx, start[1] - ny],
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::File, io::Read};
+    use tempfile::tempdir;
+    use crate::test_utils::build_msa;
+    use super::*;
+
+    #[test]
+    fn test_columns() {
+        let msa = build_msa(&["ACGT-", "ACGA-", "ACTA-", "AGTT-"]);
+        let columns = SequenceLogo::new(&msa).as_dna().columns().unwrap();
+        assert_eq!(columns.len(), 5);
+        assert_eq!(columns[0].letters, vec![('A', 2.0)]);
+        let entropy = -(0.75f64 * 0.75f64.log2() + 0.25 * 0.25f64.log2());
+        assert!((columns[1].information - (2.0 - entropy)).abs() < 1e-9);
+        assert_eq!(columns[1].letters[0].0, 'G');
+        assert_eq!(columns[1].letters[1].0, 'C');
+        assert_eq!(columns[2].information, 1.0);
+        assert_eq!(columns[4].information, 0.0);
+        assert!(columns[4].letters.is_empty());
+    }
+
+    #[test]
+    fn test_columns_with_range() {
+        let msa = build_msa(&["ACGT-", "ACGA-", "ACTA-", "AGTT-"]);
+        let columns = SequenceLogo::new(&msa)
+            .as_dna()
+            .with_range(2, Some(3))
+            .columns()
+            .unwrap();
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0].position, 2);
+        assert_eq!(columns[1].position, 3);
+        let logo = SequenceLogo::new(&msa).with_range(4, Some(6));
+        assert!(logo.columns().is_err());
+        let logo = SequenceLogo::new(&msa).with_range(3, Some(2));
+        assert!(logo.columns().is_err());
+    }
+
+    #[test]
+    fn test_glyphs_of_alphabets() {
+        let fallback = glyph('?');
+        for c in Alphabet::Protein.symbols()
+            .into_iter()
+            .chain(Alphabet::DNA.symbols())
+            .chain("0123456789".chars()) {
+            let quads = glyph(c);
+            assert!(!quads.is_empty());
+            assert_ne!(quads, fallback);
+            assert!(
+                quads.iter().flatten().all(|p| {
+                    (-STROKE..=1.0 + STROKE).contains(&p[0]) &&
+                    (-STROKE..=1.0 + STROKE).contains(&p[1])
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_svg() {
+        let msa = build_msa(&["ACGT-", "ACGA-", "ACTA-", "AGTT-"]);
+        let mut out = vec![];
+        SequenceLogo::new(&msa)
+            .as_dna()
+            .with_column_width(10)
+            .write_svg(&mut out)
+            .unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(svg.starts_with(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" \
+            height=\"115\" viewBox=\"0 0 100 115\">"
+        ));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        // Color of 'A' in the nucleic acid color scheme.
+        assert!(svg.contains("fill=\"#e60a0a\""));
+    }
+
+    #[test]
+    fn test_png() {
+        let msa = build_msa(&["ACGT-", "ACGA-", "ACTA-", "AGTT-"]);
+        let tdir = tempdir().unwrap().into_path();
+        let cpath = tdir.join("logo.png");
+        SequenceLogo::new(&msa)
+            .as_dna()
+            .save_png(cpath.as_path())
+            .unwrap();
+        let mut observed = vec![];
+        File::open(cpath).unwrap().read_to_end(&mut observed).unwrap();
+        assert_eq!(&observed[..8], &[137, 80, 78, 71, 13, 10, 26, 10]);
+        // Image width and height from the IHDR chunk.
+        assert_eq!(&observed[16..24], &[0, 0, 0, 150, 0, 0, 0, 115]);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::gapping::is_gap;
+use crate::seqs::{Alignment, AnnotatedSequence, SeqError, SequenceAccesors};
+
+/// Maps the ungapped residue positions of an aligned sequence to the columns
+/// of the alignment, and back. All positions are zero based.
+#[derive(Clone, Debug)]
+pub struct PositionMap {
+    columns: Vec<usize>,
+    residues: Vec<Option<usize>>,
+}
+
+impl PositionMap {
+    /// Creates the map of an aligned sequence.
+    /// ```
+    /// use famlib::mapping::PositionMap;
+    /// use famlib::seqs::AnnotatedSequence;
+    /// let seq = AnnotatedSequence::from_string("s1", "-AC--G");
+    /// let map = PositionMap::new(&seq);
+    /// assert_eq!(map.column_of(2), Some(5));
+    /// assert_eq!(map.residue_at(2), Some(1));
+    /// assert_eq!(map.residue_at(3), None);
+    /// ```
+    pub fn new(seq: &AnnotatedSequence) -> Self {
+        let mut columns = vec![];
+        let residues = seq
+            .seq()
+            .map(|x| x.as_slice())
+            .unwrap_or(&[])
+            .iter()
+            .enumerate()
+            .map(|(i, c)| match is_gap(*c) {
+                true => None,
+                false => {
+                    columns.push(i);
+                    Some(columns.len() - 1)
+                }
+            })
+            .collect();
+        PositionMap { columns, residues }
+    }
+    /// Creates the map of the sequence with the given id.
+    pub fn from_alignment(msa: &Alignment, id: &str) -> Result<Self, SeqError> {
+        msa.get_by_id(id)
+            .map(PositionMap::new)
+            .ok_or_else(|| SeqError::NonExistenId(id.to_string()))
+    }
+    /// The number of residues of the sequence.
+    pub fn residues(&self) -> usize {
+        self.columns.len()
+    }
+    /// The number of columns of the alignment.
+    pub fn columns(&self) -> usize {
+        self.residues.len()
+    }
+    /// The column of a residue.
+    pub fn column_of(&self, residue: usize) -> Option<usize> {
+        self.columns.get(residue).copied()
+    }
+    /// The residue at a column, or None if the sequence has a gap there.
+    pub fn residue_at(&self, column: usize) -> Option<usize> {
+        self.residues.get(column).copied().flatten()
+    }
+    /// The last residue at or before a column, if any.
+    pub fn residue_before(&self, column: usize) -> Option<usize> {
+        let end = (column + 1).min(self.columns());
+        self.residues[..end].iter().rev().find_map(|x| *x)
+    }
+    /// The first residue at or after a column, if any.
+    pub fn residue_after(&self, column: usize) -> Option<usize> {
+        self.residues.iter().skip(column).find_map(|x| *x)
+    }
+    /// Maps a residue of this sequence to the residue of another sequence
+    /// in the same column, or None if the other sequence has a gap.
+    pub fn map_to(&self, other: &PositionMap, residue: usize) -> Option<usize> {
+        self.column_of(residue).and_then(|x| other.residue_at(x))
+    }
+}
+
+/// Numbers the columns of an alignment by the residues of a reference
+/// sequence, starting at a given number, one by default.
+#[derive(Clone, Debug)]
+pub struct ReferenceNumbering {
+    id: String,
+    symbols: Vec<char>,
+    map: PositionMap,
+    start: usize,
+}
+
+impl ReferenceNumbering {
+    pub fn new(reference: &AnnotatedSequence) -> Self {
+        ReferenceNumbering {
+            id: reference.id().to_string(),
+            symbols: reference.seq().cloned().unwrap_or_default(),
+            map: PositionMap::new(reference),
+            start: 1,
+        }
+    }
+    /// Creates the numbering of the sequence with the given id.
+    pub fn from_collection<T: SequenceAccesors>(
+        seqs: &T,
+        id: &str,
+    ) -> Result<Self, SeqError> {
+        seqs.get_by_id(id)
+            .map(ReferenceNumbering::new)
+            .ok_or_else(|| SeqError::NonExistenId(id.to_string()))
+    }
+    /// Sets the number of the first residue of the reference.
+    pub fn with_start(mut self, start: usize) -> Self {
+        self.start = start;
+        self
+    }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// The zero based column of a numbered residue.
+    /// ```
+    /// use famlib::mapping::ReferenceNumbering;
+    /// use famlib::seqs::AnnotatedSequence;
+    /// let seq = AnnotatedSequence::from_string("s1", "-AC--G");
+    /// let numbering = ReferenceNumbering::new(&seq).with_start(10);
+    /// assert_eq!(numbering.column_of(12), Some(5));
+    /// assert_eq!(numbering.number_at(2), Some(11));
+    /// assert_eq!(numbering.column_of(9), None);
+    /// ```
+    pub fn column_of(&self, number: usize) -> Option<usize> {
+        self.map.column_of(number.checked_sub(self.start)?)
+    }
+    /// The number of the residue at a zero based column, or None if the
+    /// reference has a gap there.
+    pub fn number_at(&self, column: usize) -> Option<usize> {
+        self.map.residue_at(column).map(|x| x + self.start)
+    }
+    /// The symbol of the reference at a zero based column.
+    pub fn symbol_at(&self, column: usize) -> Option<char> {
+        self.symbols.get(column).copied()
+    }
+    /// The map of the reference residues, without numbering offset.
+    pub fn position_map(&self) -> &PositionMap {
+        &self.map
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::build_msa;
+    use super::*;
+
+    #[test]
+    fn test_position_map() {
+        let msa = build_msa(&["AC--GT.A", "-CTTG--A"]);
+        let map = PositionMap::from_alignment(&msa, "s1").unwrap();
+        assert_eq!(map.residues(), 5);
+        assert_eq!(map.columns(), 8);
+        let columns = (0..6).map(|x| map.column_of(x)).collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![Some(0), Some(1), Some(4), Some(5), Some(7), None]
+        );
+        let residues = (0..9).map(|x| map.residue_at(x)).collect::<Vec<_>>();
+        assert_eq!(
+            residues,
+            vec![
+                Some(0), Some(1), None, None, Some(2), Some(3), None, Some(4),
+                None
+            ]
+        );
+        assert_eq!(map.residue_before(3), Some(1));
+        assert_eq!(map.residue_after(3), Some(2));
+        assert_eq!(map.residue_after(8), None);
+        assert!(PositionMap::from_alignment(&msa, "s3").is_err());
+    }
+
+    #[test]
+    fn test_map_between_sequences() {
+        let msa = build_msa(&["AC--GT.A", "-CTTG--A"]);
+        let s1 = PositionMap::from_alignment(&msa, "s1").unwrap();
+        let s2 = PositionMap::from_alignment(&msa, "s2").unwrap();
+        let mapped = (0..5).map(|x| s1.map_to(&s2, x)).collect::<Vec<_>>();
+        assert_eq!(mapped, vec![None, Some(0), Some(3), None, Some(4)]);
+        let mapped = (0..5).map(|x| s2.map_to(&s1, x)).collect::<Vec<_>>();
+        assert_eq!(mapped, vec![Some(1), None, None, Some(2), Some(4)]);
+    }
+
+    #[test]
+    fn test_reference_numbering() {
+        let msa = build_msa(&["AC--GT.A", "-CTTG--A"]);
+        let numbering = ReferenceNumbering::from_collection(&msa, "s2")
+            .unwrap()
+            .with_start(100);
+        assert_eq!(numbering.id(), "s2");
+        let numbers = (0..8).map(|x| numbering.number_at(x)).collect::<Vec<_>>();
+        assert_eq!(
+            numbers,
+            vec![
+                None, Some(100), Some(101), Some(102), Some(103), None, None,
+                Some(104)
+            ]
+        );
+        assert_eq!(numbering.column_of(104), Some(7));
+        assert_eq!(numbering.column_of(99), None);
+        assert_eq!(numbering.column_of(105), None);
+        assert_eq!(numbering.symbol_at(1), Some('C'));
+    }
+}
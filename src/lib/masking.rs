@@ -0,0 +1,88 @@
+use crate::seqs::{Alignment, SeqError, SequenceAccesors};
+
+/// How residues of masked columns are changed. Gaps are never changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mask {
+    /// Converts residues to lowercase.
+    Lowercase,
+    /// Replaces residues with a symbol, like 'X' or 'N'.
+    Replace(char),
+}
+
+impl Mask {
+    /// Gets a mask by name: 'lower' or a single symbol.
+    /// ```
+    /// use famlib::masking::Mask;
+    /// assert_eq!(Mask::from_name("lower"), Some(Mask::Lowercase));
+    /// assert_eq!(Mask::from_name("X"), Some(Mask::Replace('X')));
+    /// assert_eq!(Mask::from_name("-"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<Self> {
+        let mut chars = name.chars();
+        match (name.to_lowercase().as_str(), chars.next(), chars.next()) {
+            ("lower" | "lowercase", _, _) => Some(Mask::Lowercase),
+            (_, Some(c), None) if c.is_ascii_alphabetic() || c == '*' => {
+                Some(Mask::Replace(c))
+            }
+            _ => None,
+        }
+    }
+    /// Masks a single residue.
+    pub fn apply(&self, c: char) -> char {
+        match (self, c) {
+            (_, '-' | '.') => c,
+            (Mask::Lowercase, _) => c.to_ascii_lowercase(),
+            (Mask::Replace(x), _) => *x,
+        }
+    }
+}
+
+pub trait MaskColumns {
+    /// Masks the residues of the given columns, keeping the alignment
+    /// length.
+    fn mask_columns(
+        &mut self,
+        positions: &[usize],
+        mask: Mask,
+    ) -> Result<(), SeqError>;
+}
+
+impl MaskColumns for Alignment {
+    fn mask_columns(
+        &mut self,
+        positions: &[usize],
+        mask: Mask,
+    ) -> Result<(), SeqError> {
+        if positions.iter().any(|x| *x >= self.length()) {
+            return Err(SeqError::EditError);
+        }
+        for i in 0..self.size() {
+            let seq = self
+                .get_mut(i)
+                .and_then(|x| x.seq_mut())
+                .ok_or(SeqError::Empty)?;
+            for position in positions {
+                seq[*position] = mask.apply(seq[*position]);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::{build_msa, rows};
+    use super::*;
+
+    #[test]
+    fn test_mask_columns() {
+        let mut msa = build_msa(&["AC-GT", "ACTG."]);
+        msa.mask_columns(&[1, 2, 4], Mask::Lowercase).unwrap();
+        assert_eq!(rows(&msa), vec!["Ac-Gt", "ActG."]);
+        let mut msa = build_msa(&["AC-GT", "ACTG."]);
+        msa.mask_columns(&[2, 3], Mask::Replace('N')).unwrap();
+        assert_eq!(rows(&msa), vec!["AC-NT", "ACNN."]);
+        assert_eq!(msa.length(), 5);
+        assert!(msa.mask_columns(&[5], Mask::Lowercase).is_err());
+    }
+}
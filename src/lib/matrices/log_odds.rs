@@ -0,0 +1,274 @@
+use std::error::Error;
+
+use itertools::Itertools;
+
+use crate::conservation::{amino_index, Alphabet};
+use crate::matrices::scoring_matrix::ScoringMatrix;
+use crate::matrices::triangular_matrix::TriangularMatrix;
+use crate::pairwise::PairwiseIdentity;
+use crate::seqs::{Alignment, SequenceAccesors};
+use crate::similarity::{IdentityOverLength, SimilarityMetric};
+
+const ALPHABET_SIZE: usize = 20;
+
+/// Derives a log-odds scoring matrix for the twenty standard amino acids
+/// from an alignment, following the BLOSUM procedure (Henikoff & Henikoff
+/// 1992).
+///
+/// Sequences are clustered by single linkage at a minimum identity, and
+/// each sequence is weighted by the inverse of the size of its cluster.
+/// Pairs of residues are counted in every column, only between sequences
+/// of different clusters. A pseudocount is added to every pair, and
+/// scores are computed as `scale * log2(q_ab / e_ab)`, where `q_ab` is the
+/// observed pair frequency and `e_ab` is the frequency expected from the
+/// background, and rounded to integers.
+///
+/// Gaps and non standard residues are not counted.
+pub struct LogOdds<'a> {
+    msa: &'a Alignment,
+    identity: f64,
+    metric: Box<dyn SimilarityMetric + 'a>,
+    pseudocount: f64,
+    background: Option<Vec<f64>>,
+    scale: f64,
+    threads: usize,
+}
+
+impl<'a> LogOdds<'a> {
+    pub fn new(msa: &'a Alignment) -> Self {
+        LogOdds {
+            msa,
+            identity: 0.62,
+            metric: Box::new(IdentityOverLength::new().exclude_gaps()),
+            pseudocount: 1f64,
+            background: None,
+            scale: 2f64,
+            threads: 0,
+        }
+    }
+    /// Sets the minimum identity to put two sequences in the same cluster,
+    /// 0.62 by default. Values greater than one do not cluster sequences.
+    pub fn with_identity(mut self, identity: f64) -> Self {
+        self.identity = identity;
+        self
+    }
+    /// Sets the metric used to cluster sequences. By default, identity over
+    /// the alignment length, ignoring the columns in which both sequences
+    /// have gaps.
+    pub fn with_metric(mut self, metric: impl SimilarityMetric + 'a) -> Self {
+        self.metric = Box::new(metric);
+        self
+    }
+    /// Sets the weight added to the count of every pair of residues, one by
+    /// default.
+    pub fn with_pseudocount(mut self, pseudocount: f64) -> Self {
+        self.pseudocount = pseudocount;
+        self
+    }
+    /// Sets the background frequencies of the amino acids, sorted as in
+    /// `Alphabet::Protein`. By default, the frequencies of the residues in
+    /// the counted pairs are used, as in BLOSUM.
+    pub fn with_background(mut self, background: Vec<f64>) -> Self {
+        self.background = Some(background);
+        self
+    }
+    /// Sets the units of the scores, as a fraction of bits. The default, 2,
+    /// gives half-bit scores.
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+    /// Sets the number of threads used to cluster sequences. Zero uses all
+    /// available cores.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Assigns a cluster index to each sequence, by single linkage.
+    fn clusters(&self) -> Result<Vec<usize>, Box<dyn Error>> {
+        let edges = PairwiseIdentity::new(self.msa)
+            .with_threads(self.threads)
+            .with_metric(&self.metric)
+            .edges(self.identity)?;
+        let mut parents = (0..self.msa.size()).collect::<Vec<_>>();
+        fn root(parents: &mut [usize], mut x: usize) -> usize {
+            while parents[x] != x {
+                parents[x] = parents[parents[x]];
+                x = parents[x];
+            }
+            x
+        }
+        for (i, j, _) in edges.edges() {
+            let (a, b) = (root(&mut parents, *i), root(&mut parents, *j));
+            parents[a.max(b)] = a.min(b);
+        }
+        Ok((0..parents.len()).map(|x| root(&mut parents, x)).collect())
+    }
+
+    /// Counts the weighted pairs of residues between sequences of
+    /// different clusters, without pseudocounts.
+    pub fn pair_counts(&self) -> Result<TriangularMatrix<f64>, Box<dyn Error>> {
+        let clusters = self.clusters()?;
+        let mut sizes = vec![0usize; clusters.len()];
+        clusters.iter().for_each(|x| sizes[*x] += 1);
+        let seqs = self
+            .msa
+            .iter()
+            .map(|x| x.seq().ok_or("Sequence without residues"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut counts = TriangularMatrix::new(ALPHABET_SIZE);
+        for i in 0..self.msa.length() {
+            let mut column = seqs
+                .iter()
+                .zip(&clusters)
+                .map(|(s, c)| (*c, amino_index(s[i].to_ascii_uppercase())))
+                .filter(|(_, a)| *a < ALPHABET_SIZE)
+                .collect::<Vec<_>>();
+            column.sort_unstable();
+            // The weight of each residue in the column, and the weight of
+            // the pairs of residues within the same cluster, that are
+            // not counted.
+            let mut totals = [0f64; ALPHABET_SIZE];
+            let mut within = TriangularMatrix::<f64>::new(ALPHABET_SIZE);
+            for (cluster, group) in &column.iter().group_by(|x| x.0) {
+                let weight = 1f64 / sizes[cluster] as f64;
+                let residues = group
+                    .group_by(|x| x.1)
+                    .into_iter()
+                    .map(|(a, x)| (a, x.count() as f64 * weight))
+                    .collect::<Vec<_>>();
+                for (k, (a, wa)) in residues.iter().enumerate() {
+                    totals[*a] += wa;
+                    for (b, wb) in &residues[k..] {
+                        within.set(*a, *b, within.get(*a, *b)? + wa * wb)?;
+                    }
+                }
+            }
+            for a in 0..ALPHABET_SIZE {
+                for b in a..ALPHABET_SIZE {
+                    let pairs = match a == b {
+                        true => {
+                            (totals[a] * totals[a] - within.get(a, a)?) / 2f64
+                        }
+                        false => totals[a] * totals[b] - within.get(a, b)?,
+                    };
+                    counts.set(a, b, counts.get(a, b)? + pairs)?;
+                }
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Computes the log-odds matrix.
+    pub fn build(&self) -> Result<ScoringMatrix, Box<dyn Error>> {
+        let mut frequencies = self.pair_counts()?;
+        frequencies.data.iter_mut().for_each(|x| *x += self.pseudocount);
+        let total: f64 = frequencies.data.iter().sum();
+        if total <= 0f64 {
+            return Err(
+                "No pairs of residues between different clusters".into()
+            );
+        }
+        frequencies.data.iter_mut().for_each(|x| *x /= total);
+        let background = match &self.background {
+            Some(background) => {
+                if background.len() != ALPHABET_SIZE {
+                    return Err(
+                        "Background must have twenty frequencies".into()
+                    );
+                }
+                background.clone()
+            }
+            None => (0..ALPHABET_SIZE)
+                .map(|a| {
+                    (0..ALPHABET_SIZE)
+                        .map(|b| {
+                            let q = frequencies.get(a, b)?;
+                            Ok(if a == b { q } else { q / 2f64 })
+                        })
+                        .sum::<Result<f64, Box<dyn Error>>>()
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        let alphabet = Alphabet::Protein.symbols();
+        let mut matrix = ScoringMatrix::new(alphabet.clone())?;
+        for a in 0..ALPHABET_SIZE {
+            for b in a..ALPHABET_SIZE {
+                let expected = match a == b {
+                    true => background[a] * background[b],
+                    false => 2f64 * background[a] * background[b],
+                };
+                let score =
+                    self.scale * (frequencies.get(a, b)? / expected).log2();
+                if !score.is_finite() {
+                    return Err(
+                        format!(
+                            "Cannot score pair: '{}', '{}'",
+                            alphabet[a],
+                            alphabet[b]
+                        ).into()
+                    );
+                }
+                matrix.data.set(a, b, score.round() as i32)?;
+            }
+        }
+        Ok(matrix)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::build_msa;
+    use super::*;
+
+    #[test]
+    fn test_pair_counts_weighted_by_cluster() {
+        // s1 and s2 are clustered, each weights one half.
+        let msa = build_msa(&["AAAA", "AAAC", "CCCC"]);
+        let counts = LogOdds::new(&msa)
+            .with_identity(0.7)
+            .with_threads(1)
+            .pair_counts()
+            .unwrap();
+        let (a, c) = (0, 1);
+        // Columns 1 to 3: A-C twice with weight 1/2.
+        // Column 4: A-C and C-C with weight 1/2.
+        assert_eq!(counts.get(a, c).ok(), Some(3.5));
+        assert_eq!(counts.get(c, c).ok(), Some(0.5));
+        assert_eq!(counts.get(a, a).ok(), Some(0.0));
+        let counts = LogOdds::new(&msa)
+            .with_identity(1.1)
+            .with_threads(1)
+            .pair_counts()
+            .unwrap();
+        assert_eq!(counts.get(a, a).ok(), Some(3.0));
+        assert_eq!(counts.get(a, c).ok(), Some(8.0));
+        assert_eq!(counts.get(c, c).ok(), Some(1.0));
+    }
+
+    #[test]
+    fn test_log_odds() {
+        let msa = build_msa(&["ACDE", "ACDE", "ACDF", "GCDF"]);
+        let matrix = LogOdds::new(&msa)
+            .with_identity(1.1)
+            .with_threads(1)
+            .build()
+            .unwrap();
+        // Conserved residues score higher than unobserved pairs.
+        assert!(matrix.score('C', 'C').unwrap() > 0);
+        let (ef, ew) = (matrix.score('E', 'F'), matrix.score('E', 'W'));
+        assert!(ef.unwrap() > ew.unwrap());
+        assert_eq!(matrix.score('W', 'Y'), matrix.score('Y', 'W'));
+        let uniform = LogOdds::new(&msa)
+            .with_identity(1.1)
+            .with_background(vec![0.05; 20])
+            .with_pseudocount(0.0);
+        assert!(uniform.build().is_err());
+        let clustered = LogOdds::new(&msa)
+            .with_identity(0.0)
+            .with_pseudocount(0.0)
+            .build();
+        assert!(clustered.is_err());
+    }
+}
@@ -1,4 +1,8 @@
-mod substitution_matrix;
-mod triangular_matrix;
+pub mod log_odds;
+pub mod scoring_matrix;
+pub mod substitution_matrix;
+pub mod triangular_matrix;
+pub use log_odds::*;
+pub use scoring_matrix::*;
 pub use substitution_matrix::*;
-pub use triangular_matrix::*;
\ No newline at end of file
+pub use triangular_matrix::*;
@@ -0,0 +1,291 @@
+use std::error::Error;
+use std::io::BufRead;
+
+use crate::matrices::substitution_matrix::SubstitutionMatrix;
+use crate::matrices::triangular_matrix::TriangularMatrix;
+use crate::seqs::Alignment;
+
+/// Names of the built-in scoring matrices.
+pub const BUILTIN_MATRICES: [&str; 6] = [
+    "BLOSUM45", "BLOSUM62", "BLOSUM80", "PAM30", "PAM70", "PAM250"
+];
+
+const BLOSUM45: &str = include_str!("data/BLOSUM45");
+const BLOSUM62: &str = include_str!("data/BLOSUM62");
+const BLOSUM80: &str = include_str!("data/BLOSUM80");
+const PAM30: &str = include_str!("data/PAM30");
+const PAM70: &str = include_str!("data/PAM70");
+const PAM250: &str = include_str!("data/PAM250");
+
+/// A symmetric matrix of integer scores between pairs of symbols, as used
+/// to score alignments.
+///
+/// Matrices are read from and written to the NCBI text format used by
+/// BLAST and EMBOSS: comment lines start with `#`, the first line has the
+/// symbols of the columns and each row starts with its symbol.
+#[derive(Clone)]
+pub struct ScoringMatrix {
+    alphabet: Vec<char>,
+    index: Vec<Option<usize>>,
+    pub data: TriangularMatrix<i32>,
+}
+
+impl ScoringMatrix {
+    /// Creates a matrix with all scores set to zero. Symbols are not case
+    /// sensitive, and are stored in upper case.
+    pub fn new(alphabet: Vec<char>) -> Result<Self, Box<dyn Error>> {
+        let alphabet = alphabet
+            .iter()
+            .map(|x| x.to_ascii_uppercase())
+            .collect::<Vec<_>>();
+        let mut index = vec![None; 128];
+        for (i, c) in alphabet.iter().enumerate() {
+            if !c.is_ascii() || c.is_ascii_whitespace() {
+                return Err(format!("Invalid matrix symbol: '{}'", c).into());
+            }
+            if index[*c as usize].is_some() {
+                return Err(format!("Repeated matrix symbol: '{}'", c).into());
+            }
+            index[*c as usize] = Some(i);
+        }
+        Ok(ScoringMatrix {
+            data: TriangularMatrix::new(alphabet.len()),
+            alphabet,
+            index,
+        })
+    }
+    /// Creates a matrix that gives the same score to all matching symbols,
+    /// and the same score to all mismatches.
+    /// ```
+    /// use famlib::matrices::ScoringMatrix;
+    /// let dna = ScoringMatrix::match_mismatch("ACGT", 5, -4).unwrap();
+    /// assert_eq!(dna.score('a', 'A'), Some(5));
+    /// assert_eq!(dna.score('A', 'T'), Some(-4));
+    /// ```
+    pub fn match_mismatch(
+        alphabet: &str,
+        match_score: i32,
+        mismatch: i32,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut matrix = Self::new(alphabet.chars().collect())?;
+        let size = matrix.alphabet.len();
+        for x in 0..size {
+            for y in 0..=x {
+                let score = if x == y { match_score } else { mismatch };
+                matrix.data.set(x, y, score)?;
+            }
+        }
+        Ok(matrix)
+    }
+    /// Reads a matrix in NCBI format. Rows can be in any order, but the
+    /// matrix must be square and symmetric.
+    pub fn from_ncbi<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut lines = reader
+            .lines()
+            .filter(|x| match x {
+                Ok(line) => {
+                    !line.trim().is_empty() && !line.starts_with('#')
+                }
+                Err(_) => true,
+            });
+        let header = lines.next().ok_or("Matrix has no header")??;
+        let symbols = header
+            .split_whitespace()
+            .map(parse_symbol)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut matrix = Self::new(symbols)?;
+        let size = matrix.alphabet.len();
+        let mut rows = vec![None; size];
+        for line in lines {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            let symbol = parse_symbol(fields.next().unwrap_or_default())?;
+            let x = matrix
+                .index_of(symbol)
+                .ok_or(format!("Row symbol not in header: '{}'", symbol))?;
+            let values = fields
+                .map(|v| v.parse::<i32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("Invalid score in row: '{}'", symbol))?;
+            if values.len() != size {
+                return Err(
+                    format!("Wrong number of scores in row: '{}'", symbol)
+                        .into()
+                );
+            }
+            if rows[x].replace(values).is_some() {
+                return Err(format!("Repeated row: '{}'", symbol).into());
+            }
+        }
+        let rows = rows
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or("Matrix is not square")?;
+        for (x, row) in rows.iter().enumerate() {
+            for (y, value) in row.iter().enumerate().take(x + 1) {
+                if *value != rows[y][x] {
+                    return Err(
+                        format!(
+                            "Matrix is not symmetric: '{}', '{}'",
+                            matrix.alphabet[x],
+                            matrix.alphabet[y]
+                        ).into()
+                    );
+                }
+                matrix.data.set(x, y, *value)?;
+            }
+        }
+        Ok(matrix)
+    }
+    /// Reads a matrix in NCBI format from a string.
+    pub fn from_ncbi_str(text: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_ncbi(text.as_bytes())
+    }
+    pub fn blosum45() -> Self {
+        Self::from_ncbi_str(BLOSUM45).unwrap()
+    }
+    pub fn blosum62() -> Self {
+        Self::from_ncbi_str(BLOSUM62).unwrap()
+    }
+    pub fn blosum80() -> Self {
+        Self::from_ncbi_str(BLOSUM80).unwrap()
+    }
+    pub fn pam30() -> Self {
+        Self::from_ncbi_str(PAM30).unwrap()
+    }
+    pub fn pam70() -> Self {
+        Self::from_ncbi_str(PAM70).unwrap()
+    }
+    pub fn pam250() -> Self {
+        Self::from_ncbi_str(PAM250).unwrap()
+    }
+    /// Gets a built-in matrix by name, ignoring case.
+    /// ```
+    /// use famlib::matrices::ScoringMatrix;
+    /// let blosum = ScoringMatrix::by_name("blosum62").unwrap();
+    /// assert_eq!(blosum.score('W', 'w'), Some(11));
+    /// assert_eq!(blosum.score('B', 'D'), Some(4));
+    /// assert_eq!(blosum.score('A', '*'), Some(-4));
+    /// assert_eq!(blosum.score('A', '-'), None);
+    /// assert!(ScoringMatrix::by_name("blosum50").is_none());
+    /// ```
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "BLOSUM45" => Some(Self::blosum45()),
+            "BLOSUM62" => Some(Self::blosum62()),
+            "BLOSUM80" => Some(Self::blosum80()),
+            "PAM30" => Some(Self::pam30()),
+            "PAM70" => Some(Self::pam70()),
+            "PAM250" => Some(Self::pam250()),
+            _ => None,
+        }
+    }
+    /// The index of a symbol in the alphabet, ignoring case.
+    pub fn index_of(&self, symbol: char) -> Option<usize> {
+        match symbol.is_ascii() {
+            true => self.index[symbol.to_ascii_uppercase() as usize],
+            false => None,
+        }
+    }
+    /// The score between two symbols, ignoring case. Returns None if any
+    /// of them is not in the alphabet.
+    pub fn score(&self, a: char, b: char) -> Option<i32> {
+        let x = self.index_of(a)?;
+        let y = self.index_of(b)?;
+        self.data.get(x, y).ok()
+    }
+}
+
+fn parse_symbol(field: &str) -> Result<char, Box<dyn Error>> {
+    let mut chars = field.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("Invalid matrix symbol: '{}'", field).into()),
+    }
+}
+
+impl SubstitutionMatrix<i32> for ScoringMatrix {
+    type Output = ScoringMatrix;
+    fn build_from(&mut self, _msa: &Alignment) -> Result<(), Box<dyn Error>> {
+        Err("Scoring matrices cannot be built from an alignment".into())
+    }
+    fn get(&self, x: usize, y: usize) -> Result<i32, Box<dyn Error>> {
+        self.data.get(x, y)
+    }
+    fn set(
+        &mut self,
+        x: usize,
+        y: usize,
+        value: i32
+    ) -> Result<(), Box<dyn Error>> {
+        self.data.set(x, y, value)
+    }
+    fn normalize(&self) -> Result<Self::Output, Box<dyn Error>> {
+        Err("Scoring matrices cannot be normalized".into())
+    }
+    fn alphabet(&self) -> Vec<char> {
+        self.alphabet.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_builtin_matrices() {
+        for name in BUILTIN_MATRICES {
+            let matrix = ScoringMatrix::by_name(name).unwrap();
+            assert_eq!(
+                matrix.alphabet().iter().collect::<String>(),
+                "ARNDCQEGHILKMFPSTWYVBZX*"
+            );
+            assert_eq!(matrix.score('*', '*'), Some(1));
+        }
+        let pam = ScoringMatrix::pam250();
+        assert_eq!(pam.score('W', 'W'), Some(17));
+        assert_eq!(pam.score('C', 'C'), Some(12));
+        assert_eq!(pam.score('Z', 'E'), Some(3));
+        assert_eq!(ScoringMatrix::blosum45().score('W', 'W'), Some(15));
+        assert_eq!(ScoringMatrix::blosum80().score('A', '*'), Some(-8));
+        assert_eq!(ScoringMatrix::pam30().score('X', 'a'), Some(-3));
+    }
+
+    #[test]
+    fn test_read_and_write_ncbi() {
+        let text = "# A comment\n\
+                    \n   A  C  *\n\
+                    C -1  9 -4\n\
+                    A  4 -1 -4\n\
+                    * -4 -4  1\n";
+        let matrix = ScoringMatrix::from_ncbi_str(text).unwrap();
+        assert_eq!(matrix.score('a', 'c'), Some(-1));
+        assert_eq!(matrix.get(1, 1).ok(), Some(9));
+        let mut out = vec![];
+        matrix.write_ncbi(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "   A  C  *\n\
+            A  4 -1 -4\n\
+            C -1  9 -4\n\
+            * -4 -4  1\n"
+        );
+        let mut out = vec![];
+        ScoringMatrix::pam30().write_ncbi(&mut out).unwrap();
+        let copy = ScoringMatrix::from_ncbi(out.as_slice()).unwrap();
+        assert_eq!(copy.data.data, ScoringMatrix::pam30().data.data);
+    }
+
+    #[test]
+    fn test_invalid_ncbi() {
+        let asymmetric = "   A  C\nA  4 -1\nC  0  9\n";
+        assert!(ScoringMatrix::from_ncbi_str(asymmetric).is_err());
+        let missing_row = "   A  C\nA  4 -1\n";
+        assert!(ScoringMatrix::from_ncbi_str(missing_row).is_err());
+        let short_row = "   A  C\nA  4\nC -1  9\n";
+        assert!(ScoringMatrix::from_ncbi_str(short_row).is_err());
+        let bad_value = "   A  C\nA  4  x\nC  x  9\n";
+        assert!(ScoringMatrix::from_ncbi_str(bad_value).is_err());
+        assert!(ScoringMatrix::from_ncbi_str("").is_err());
+    }
+}
@@ -1,10 +1,12 @@
-
 use std::error::Error;
+use std::fmt::Display;
+use std::io::{self, Write};
 
 use itertools::Itertools;
 
 use crate::conservation::amino_index;
 use crate::conservation::dna_index;
+use crate::conservation::Alphabet;
 use crate::matrices::triangular_matrix::TriangularMatrix;
 use crate::matrices::triangular_matrix::Num;
 use crate::seqs::Alignment;
@@ -16,8 +18,82 @@ pub trait SubstitutionMatrix<T> where T: Num
     fn get(&self, x:usize, y:usize) -> Result<T, Box<dyn Error>>;
     fn set(&mut self, x:usize, y:usize, value: T) -> Result<(), Box<dyn Error>>;
     fn normalize(&self) -> Result<Self::Output, Box<dyn Error>>;
+    /// The symbols of the rows and columns of the matrix, sorted by index.
+    fn alphabet(&self) -> Vec<char>;
+
+    /// Writes a square tab separated matrix, with symbols as the first row
+    /// and column.
+    fn write_tsv<W: Write>(&self, mut writer: W) -> io::Result<()>
+    where T: Display {
+        let alphabet = self.alphabet();
+        writeln!(writer, "\t{}", alphabet.iter().join("\t"))?;
+        for (x, symbol) in alphabet.iter().enumerate() {
+            let row = (0..alphabet.len())
+                .map(|y| self.get(x, y).map(|v| format!("{:.4}", v)))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            writeln!(writer, "{}\t{}", symbol, row.join("\t"))?;
+        }
+        writer.flush()
+    }
+
+    /// Writes the matrix in the NCBI text format used by BLAST and EMBOSS,
+    /// with columns aligned to the right.
+    fn write_ncbi<W: Write>(&self, mut writer: W) -> io::Result<()>
+    where T: Display {
+        let alphabet = self.alphabet();
+        let rows = (0..alphabet.len())
+            .map(|x| {
+                (0..alphabet.len())
+                    .map(|y| self.get(x, y).map(|v| format!("{:.4}", v)))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        let width = rows
+            .iter()
+            .flatten()
+            .map(|x| x.len() + 1)
+            .max()
+            .unwrap_or(0)
+            .max(3);
+        write!(writer, " ")?;
+        for symbol in &alphabet {
+            write!(writer, "{:>width$}", symbol, width = width)?;
+        }
+        writeln!(writer)?;
+        for (symbol, row) in alphabet.iter().zip(rows) {
+            write!(writer, "{}", symbol)?;
+            for value in row {
+                write!(writer, "{:>width$}", value, width = width)?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+}
+
+/// Divides all values of a matrix by their sum. Each pair of different
+/// symbols is counted once.
+fn frequencies<T: Num>(
+    data: &TriangularMatrix<T>
+) -> TriangularMatrix<f64> {
+    let total: f64 = data.data.iter().map(|x| x.as_float()).sum();
+    let mut result = TriangularMatrix::new(0);
+    result.size = data.size;
+    result.data = data
+        .data
+        .iter()
+        .map(|x| match total > 0f64 {
+            true => x.as_float() / total,
+            false => 0f64
+        })
+        .collect();
+    result
 }
 
+/// Counts the pairs of amino acids found in the same column of an
+/// alignment. Gaps and unknown residues are not counted.
 pub struct ProteinMatrix<T> where T: Num
 {
     pub data: TriangularMatrix<T>
@@ -41,11 +117,13 @@ where T: Num
         let mut data = TriangularMatrix::new(Self::ALPHABET_SIZE);
         for col in msa.columns() {
             for pair in col.iter().combinations(2) {
-                let c1 = *pair[0];
-                let c2 = *pair[1];
-                let x = amino_index(*c1);
-                let y = amino_index(*c2);
-                data.increment(x, y).ok();
+                let c1 = pair[0].to_ascii_uppercase();
+                let c2 = pair[1].to_ascii_uppercase();
+                let x = amino_index(c1);
+                let y = amino_index(c2);
+                if x < Self::ALPHABET_SIZE && y < Self::ALPHABET_SIZE {
+                    data.increment(x, y)?;
+                }
             }
         }
         self.data = data;
@@ -63,21 +141,26 @@ where T: Num
     fn get(&self, x:usize, y:usize) -> Result<T, Box<dyn Error>> {
         self.data.get(x, y)
     }
+    /// Converts pair counts to pair frequencies, that sum one.
     fn normalize(&self) -> Result<Self::Output, Box<dyn Error>> {
-        let data = TriangularMatrix::new(Self::ALPHABET_SIZE);
-        let mut pm = ProteinMatrix::new();
-        pm.data = data;
-        Ok(pm)
+        Ok(ProteinMatrix{
+            data: frequencies(&self.data)
+        })
+    }
+    fn alphabet(&self) -> Vec<char> {
+        Alphabet::Protein.symbols()
     }
 }
 
+/// Counts the pairs of nucleotides found in the same column of an
+/// alignment. Gaps and unknown bases are not counted.
 pub struct DNAMatrix<T>
 where T: Num {
-    data: TriangularMatrix<T>
+    pub data: TriangularMatrix<T>
 }
 
 impl <T> DNAMatrix<T> where T:Num {
-    const ALPHABET_SIZE: usize = 20;
+    const ALPHABET_SIZE: usize = 4;
     pub fn new() -> Self {
         Self{
             data: TriangularMatrix::new(Self::ALPHABET_SIZE)
@@ -90,11 +173,13 @@ impl <T> SubstitutionMatrix<T> for DNAMatrix<T> where T:Num {
         let mut data = TriangularMatrix::new(Self::ALPHABET_SIZE);
         for col in msa.columns() {
             for pair in col.iter().combinations(2) {
-                let c1 = *pair[0];
-                let c2 = *pair[1];
-                let x = dna_index(*c1);
-                let y = dna_index(*c2);
-                data.increment(x, y)?;
+                let c1 = pair[0].to_ascii_uppercase();
+                let c2 = pair[1].to_ascii_uppercase();
+                let x = dna_index(c1);
+                let y = dna_index(c2);
+                if x < Self::ALPHABET_SIZE && y < Self::ALPHABET_SIZE {
+                    data.increment(x, y)?;
+                }
             }
         }
         self.data = data;
@@ -113,16 +198,21 @@ impl <T> SubstitutionMatrix<T> for DNAMatrix<T> where T:Num {
     fn get(&self, x:usize, y:usize) -> Result<T, Box<dyn Error>> {
         self.data.get(x, y)
     }
-
+    /// Converts pair counts to pair frequencies, that sum one.
     fn normalize(&self) -> Result<Self::Output, Box<dyn Error>> {
-        todo!()
+        Ok(DNAMatrix{
+            data: frequencies(&self.data)
+        })
+    }
+    fn alphabet(&self) -> Vec<char> {
+        Alphabet::DNA.symbols()
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::seqs::{Alignment, AnnotatedSequence, SequenceAccesors};
-    use super::{ProteinMatrix, SubstitutionMatrix};
+    use super::{DNAMatrix, ProteinMatrix, SubstitutionMatrix};
 
     fn build_msa(seqs: Vec<&str>) -> Alignment {
         let mut msa = Alignment::new();
@@ -216,4 +306,49 @@ mod test {
         assert_eq!(pm.get(13, 0).ok(), Some(1));
         assert_eq!(pm.get(0, 0).ok(), Some(0));
     }
+    #[test]
+    fn test_protmatrix_normalize() {
+        let mut pm = ProteinMatrix::<usize>::new();
+        let seqs = vec!["QQ", "Q-", "Q-", "QA"];
+        let msa = build_msa(seqs);
+        pm.build_from(&msa).unwrap();
+        let freqs = pm.normalize().unwrap();
+        assert_eq!(freqs.get(13, 13).ok(), Some(6.0 / 7.0));
+        assert_eq!(freqs.get(0, 13).ok(), Some(1.0 / 7.0));
+        assert_eq!(freqs.get(0, 0).ok(), Some(0.0));
+    }
+    #[test]
+    fn test_dnamatrix() {
+        let mut dm = DNAMatrix::<usize>::new();
+        let seqs = vec!["AC", "A-", "GN", "ac"];
+        let msa = build_msa(seqs);
+        dm.build_from(&msa).unwrap();
+        assert_eq!(dm.get(0, 0).ok(), Some(3));
+        assert_eq!(dm.get(0, 3).ok(), Some(3));
+        assert_eq!(dm.get(1, 1).ok(), Some(1));
+        assert_eq!(dm.get(2, 2).ok(), Some(0));
+        assert!(dm.get(0, 4).is_err());
+        let freqs = dm.normalize().unwrap();
+        assert_eq!(freqs.get(0, 3).ok(), Some(3.0 / 7.0));
+    }
+    #[test]
+    fn test_write_matrix() {
+        let mut dm = DNAMatrix::<usize>::new();
+        let msa = build_msa(vec!["A", "A", "C"]);
+        dm.build_from(&msa).unwrap();
+        let mut out = vec![];
+        dm.write_ncbi(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "   A  C  T  G\n\
+            A  1  2  0  0\n\
+            C  2  0  0  0\n\
+            T  0  0  0  0\n\
+            G  0  0  0  0\n"
+        );
+        let mut out = vec![];
+        dm.normalize().unwrap().write_tsv(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("\tA\tC\tT\tG\nA\t0.3333\t0.6667\t"));
+    }
 }
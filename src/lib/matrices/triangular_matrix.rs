@@ -20,12 +20,28 @@ impl One for f32 { fn one() -> Self { 1 as f32 } }
 impl One for f64 { fn one() -> Self { 1 as f64 } }
 
 
+/// Declares the `AsFloat` trait to convert numeric types to `f64`.
+///
+/// # Examples
+/// ```
+/// use crate::famlib::matrices::AsFloat;
+/// assert_eq!(3usize.as_float(), 3.0);
+/// ```
+pub trait AsFloat { fn as_float(&self) -> f64; }
+impl AsFloat for usize { fn as_float(&self) -> f64 { *self as f64 } }
+impl AsFloat for i32 { fn as_float(&self) -> f64 { *self as f64 } }
+impl AsFloat for u32 { fn as_float(&self) -> f64 { *self as f64 } }
+impl AsFloat for f32 { fn as_float(&self) -> f64 { *self as f64 } }
+impl AsFloat for f64 { fn as_float(&self) -> f64 { *self } }
+
+
 /// Declares the `Num` trait that represents a numeric type that can be used
 /// in the `TriangularMatrix` struct.
 pub trait Num:
     Add<Output = Self> +
     AddAssign<Self> +
     One +
+    AsFloat +
     Copy +
     Default +
     Clone {}
@@ -35,6 +51,7 @@ where T:
     Add<Output = T> +
     AddAssign<T> +
     One +
+    AsFloat +
     Copy +
     Default +
     Clone {}
@@ -44,6 +61,7 @@ where T:
 ///
 /// A triangular matrix is a symmetric matrix where all elements below and above
 /// the diagonal are identical.
+#[derive(Clone)]
 pub struct TriangularMatrix<T> where T: Num
 {
     pub data: Vec<T>,
@@ -0,0 +1,433 @@
+use std::error::Error;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::seqs::{Alignment, SequenceAccesors};
+use crate::similarity::{IdentityOverLength, SimilarityMetric};
+
+/// The kind of values written from pairwise comparisons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PairwiseValues {
+    /// Fraction of identical positions.
+    Identity,
+    /// One minus the identity fraction.
+    Distance,
+}
+
+impl PairwiseValues {
+    fn value(&self, identity: f64) -> f64 {
+        match self {
+            PairwiseValues::Identity => identity,
+            PairwiseValues::Distance => 1.0 - identity,
+        }
+    }
+}
+
+/// Runs `row` for every index lower than `size`, distributing rows among
+/// `threads` threads. Results are returned sorted by row index.
+pub(crate) fn by_rows<T, F>(
+    size: usize,
+    threads: usize,
+    row: F,
+) -> Result<Vec<T>, String>
+where
+    T: Send,
+    F: Fn(usize) -> Result<T, String> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = thread::scope(|scope| {
+        let handles = (0..threads.min(size.max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut computed = vec![];
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= size {
+                            break;
+                        }
+                        computed.push((i, row(i)?));
+                    }
+                    Ok(computed)
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join().unwrap_or(Err(String::from("Thread failed")))
+            })
+            .collect::<Result<Vec<Vec<(usize, T)>>, String>>()
+    })?;
+    let mut results = results.into_iter().flatten().collect::<Vec<_>>();
+    results.sort_by_key(|x| x.0);
+    Ok(results.into_iter().map(|x| x.1).collect())
+}
+
+/// Computes the identity of all pairs of sequences in an alignment, using
+/// many threads.
+///
+/// Identity over the alignment length is used by default, any other
+/// `SimilarityMetric` can be used instead.
+pub struct PairwiseIdentity<'a> {
+    msa: &'a Alignment,
+    metric: Box<dyn SimilarityMetric + 'a>,
+    threads: usize,
+}
+
+impl<'a> PairwiseIdentity<'a> {
+    pub fn new(msa: &'a Alignment) -> Self {
+        PairwiseIdentity {
+            msa,
+            metric: Box::new(IdentityOverLength::new()),
+            threads: thread::available_parallelism()
+                .map(|x| x.get())
+                .unwrap_or(1),
+        }
+    }
+    /// Sets the metric used to compare sequences.
+    pub fn with_metric(mut self, metric: impl SimilarityMetric + 'a) -> Self {
+        self.metric = Box::new(metric);
+        self
+    }
+    /// Uses identity over the alignment length, excluding positions in
+    /// which both sequences are gaps.
+    pub fn exclude_gaps(mut self) -> Self {
+        self.metric = Box::new(IdentityOverLength::new().exclude_gaps());
+        self
+    }
+    /// Uses identity over the alignment length.
+    pub fn include_gaps(mut self) -> Self {
+        self.metric = Box::new(IdentityOverLength::new());
+        self
+    }
+    /// Sets the number of threads. Zero is ignored.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        if threads > 0 {
+            self.threads = threads;
+        }
+        self
+    }
+
+    fn ids(&self) -> Vec<String> {
+        self.msa.iter().map(|x| x.id().to_string()).collect()
+    }
+
+    /// Runs `row` for every sequence index, distributing rows among
+    /// threads. Results are returned sorted by row index.
+    fn by_rows<T, F>(&self, row: F) -> Result<Vec<T>, Box<dyn Error>>
+    where
+        T: Send,
+        F: Fn(usize, &[&Vec<char>]) -> Result<T, String> + Sync,
+    {
+        let seqs = self
+            .msa
+            .iter()
+            .map(|x| x.seq().ok_or("Sequence without residues"))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(by_rows(seqs.len(), self.threads, |i| row(i, &seqs))?)
+    }
+
+    /// Computes the identity of all pairs of sequences.
+    /// ```
+    /// use famlib::pairwise::PairwiseIdentity;
+    /// use famlib::seqs::{
+    ///     AnnotatedSequence, SequenceAccesors, SequenceCollection
+    /// };
+    /// let mut seqs = SequenceCollection::new();
+    /// for (id, seq) in [("s1", "ACGT"), ("s2", "ACGA"), ("s3", "TTTT")] {
+    ///     seqs.add(
+    ///         AnnotatedSequence::from_string(id.to_string(), seq.to_string())
+    ///     ).unwrap();
+    /// }
+    /// let msa = seqs.to_msa().unwrap();
+    /// let matrix = PairwiseIdentity::new(&msa).matrix().unwrap();
+    /// assert_eq!(matrix.identity(0, 1), 0.75);
+    /// assert_eq!(matrix.identity(2, 0), 0.25);
+    /// assert_eq!(matrix.identity(1, 1), 1.0);
+    /// ```
+    pub fn matrix(&self) -> Result<PairwiseMatrix, Box<dyn Error>> {
+        let rows = self.by_rows(|i, seqs| {
+            seqs[i + 1..]
+                .iter()
+                .map(|other| {
+                    self.metric
+                        .similarity(seqs[i], other)
+                        .map_err(|e| e.to_string())
+                })
+                .collect::<Result<Vec<f64>, String>>()
+        })?;
+        Ok(PairwiseMatrix {
+            ids: self.ids(),
+            rows,
+        })
+    }
+
+    /// Computes the identity of all pairs of sequences, and keeps only the
+    /// pairs with an identity equal or greater than `min_identity`.
+    ///
+    /// The full matrix is never stored, so this can be used for large
+    /// alignments.
+    pub fn edges(
+        &self,
+        min_identity: f64,
+    ) -> Result<PairwiseEdges, Box<dyn Error>> {
+        let rows = self.by_rows(|i, seqs| {
+            let mut edges = vec![];
+            for (j, other) in seqs.iter().enumerate().skip(i + 1) {
+                let identity = self
+                    .metric
+                    .similarity(seqs[i], other)
+                    .map_err(|e| e.to_string())?;
+                if identity >= min_identity {
+                    edges.push((i, j, identity));
+                }
+            }
+            Ok(edges)
+        })?;
+        Ok(PairwiseEdges {
+            ids: self.ids(),
+            edges: rows.into_iter().flatten().collect(),
+        })
+    }
+}
+
+/// A symmetric matrix of identities between sequences.
+pub struct PairwiseMatrix {
+    ids: Vec<String>,
+    // Only the upper triangle is stored, rows[i][k] is the identity
+    // between sequences i and i + k + 1.
+    rows: Vec<Vec<f64>>,
+}
+
+impl PairwiseMatrix {
+    /// Creates a matrix from the upper triangle of values, rows[i][k] is
+    /// the value between sequences i and i + k + 1.
+    pub(crate) fn from_rows(ids: Vec<String>, rows: Vec<Vec<f64>>) -> Self {
+        PairwiseMatrix { ids, rows }
+    }
+    pub fn size(&self) -> usize {
+        self.ids.len()
+    }
+    pub fn ids(&self) -> &Vec<String> {
+        &self.ids
+    }
+    /// The identity between sequences i and j. The identity of a sequence
+    /// with itself is always one, even if it has gaps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if i or j are out of bounds.
+    pub fn identity(&self, i: usize, j: usize) -> f64 {
+        assert!(i < self.size() && j < self.size());
+        match i.cmp(&j) {
+            std::cmp::Ordering::Equal => 1.0,
+            std::cmp::Ordering::Less => self.rows[i][j - i - 1],
+            std::cmp::Ordering::Greater => self.rows[j][i - j - 1],
+        }
+    }
+    /// Writes a square tab separated matrix, with sequence ids as the first
+    /// row and column.
+    pub fn write_tsv<T: Write>(
+        &self,
+        mut writer: T,
+        values: PairwiseValues,
+    ) -> io::Result<()> {
+        writeln!(writer, "\t{}", self.ids.join("\t"))?;
+        for (i, id) in self.ids.iter().enumerate() {
+            let row = (0..self.size())
+                .map(|j| {
+                    format!("{:.4}", values.value(self.identity(i, j)))
+                })
+                .collect::<Vec<_>>()
+                .join("\t");
+            writeln!(writer, "{}\t{}", id, row)?;
+        }
+        writer.flush()
+    }
+    /// Writes a square distance matrix in PHYLIP format.
+    ///
+    /// Ids are padded to ten characters, longer ids are written complete
+    /// and separated from distances by a space, as in relaxed PHYLIP.
+    pub fn write_phylip<T: Write>(&self, mut writer: T) -> io::Result<()> {
+        writeln!(writer, "{:>5}", self.size())?;
+        for (i, id) in self.ids.iter().enumerate() {
+            let row = (0..self.size())
+                .map(|j| format!("{:.6}", 1.0 - self.identity(i, j)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            match id.len() < 10 {
+                true => writeln!(writer, "{:<10}{}", id, row)?,
+                false => writeln!(writer, "{} {}", id, row)?,
+            }
+        }
+        writer.flush()
+    }
+}
+
+/// Pairs of sequences selected by identity.
+pub struct PairwiseEdges {
+    ids: Vec<String>,
+    edges: Vec<(usize, usize, f64)>,
+}
+
+impl PairwiseEdges {
+    pub fn ids(&self) -> &Vec<String> {
+        &self.ids
+    }
+    /// The selected pairs of sequence indexes and their identities.
+    pub fn edges(&self) -> &Vec<(usize, usize, f64)> {
+        &self.edges
+    }
+    /// Writes a tab separated table with one pair of sequences per line.
+    pub fn write_tsv<T: Write>(
+        &self,
+        mut writer: T,
+        values: PairwiseValues,
+    ) -> io::Result<()> {
+        let header = match values {
+            PairwiseValues::Identity => "identity",
+            PairwiseValues::Distance => "distance",
+        };
+        writeln!(writer, "id1\tid2\t{}", header)?;
+        for (i, j, identity) in &self.edges {
+            writeln!(
+                writer,
+                "{}\t{}\t{:.4}",
+                self.ids[*i],
+                self.ids[*j],
+                values.value(*identity)
+            )?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::clustering::identity_fraction;
+    use crate::seqs::{AnnotatedSequence, SequenceCollection};
+    use crate::similarity::IdentityOverAligned;
+    use crate::test_utils::build_msa;
+    use super::*;
+
+    #[test]
+    fn test_matrix_with_many_threads() {
+        let msa = build_msa(
+            &["ACGTACGT", "ACGTACGA", "ACGTTTTT", "A-G-A-G-", "TTTTTTTT"]
+        );
+        let single = PairwiseIdentity::new(&msa)
+            .with_threads(1)
+            .matrix()
+            .unwrap();
+        let multi = PairwiseIdentity::new(&msa)
+            .with_threads(4)
+            .matrix()
+            .unwrap();
+        for i in 0..msa.size() {
+            assert_eq!(single.identity(i, i), 1.0);
+            for j in (0..msa.size()).filter(|j| *j != i) {
+                let seq1 = msa.get(i).unwrap().seq().unwrap();
+                let seq2 = msa.get(j).unwrap().seq().unwrap();
+                let expected = identity_fraction(seq1, seq2, false).unwrap();
+                assert_eq!(single.identity(i, j), expected);
+                assert_eq!(multi.identity(i, j), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_matrix_excluding_gaps() {
+        let mut sq = SequenceCollection::new();
+        sq.add(AnnotatedSequence::from_string("s1", "AC--GT")).unwrap();
+        sq.add(AnnotatedSequence::from_string("s2", "AC--GA")).unwrap();
+        sq.add(AnnotatedSequence::from_string("s3", "A-C-GT")).unwrap();
+        let msa = sq.to_msa().unwrap();
+        let matrix = PairwiseIdentity::new(&msa)
+            .exclude_gaps()
+            .matrix()
+            .unwrap();
+        assert_eq!(matrix.identity(0, 1), 0.75);
+        assert_eq!(matrix.identity(0, 2), 0.6);
+        let matrix = PairwiseIdentity::new(&msa)
+            .exclude_gaps()
+            .include_gaps()
+            .matrix()
+            .unwrap();
+        assert_eq!(matrix.identity(0, 1), 0.5);
+        assert_eq!(matrix.identity(0, 2), 0.5);
+    }
+
+    #[test]
+    fn test_matrix_with_metric() {
+        let msa = build_msa(
+            &["ACGTACGT", "ACGTACGA", "ACGTTTTT", "A-G-A-G-", "TTTTTTTT"]
+        );
+        let matrix = PairwiseIdentity::new(&msa)
+            .with_metric(IdentityOverAligned)
+            .matrix()
+            .unwrap();
+        assert_eq!(matrix.identity(0, 3), 1.0);
+        assert_eq!(matrix.identity(3, 4), 0.0);
+        assert_eq!(matrix.identity(0, 1), 0.875);
+    }
+
+    #[test]
+    fn test_edges() {
+        let msa = build_msa(
+            &["ACGTACGT", "ACGTACGA", "ACGTTTTT", "A-G-A-G-", "TTTTTTTT"]
+        );
+        let edges = PairwiseIdentity::new(&msa)
+            .with_threads(3)
+            .edges(0.5)
+            .unwrap();
+        assert_eq!(
+            edges.edges(),
+            &vec![
+                (0, 1, 0.875),
+                (0, 2, 0.625),
+                (0, 3, 0.5),
+                (1, 2, 0.5),
+                (1, 3, 0.5),
+                (2, 4, 0.625)
+            ]
+        );
+        let mut out = vec![];
+        edges.write_tsv(&mut out, PairwiseValues::Distance).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("id1\tid2\tdistance\ns1\ts2\t0.1250\n"));
+    }
+
+    #[test]
+    fn test_write_matrix() {
+        let mut sq = SequenceCollection::new();
+        sq.add(AnnotatedSequence::from_string("s1", "ACGT")).unwrap();
+        sq.add(AnnotatedSequence::from_string("long_sequence_id", "ACGA"))
+            .unwrap();
+        let msa = sq.to_msa().unwrap();
+        let matrix = PairwiseIdentity::new(&msa).matrix().unwrap();
+        let mut out = vec![];
+        matrix.write_tsv(&mut out, PairwiseValues::Identity).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\ts1\tlong_sequence_id\n\
+            s1\t1.0000\t0.7500\n\
+            long_sequence_id\t0.7500\t1.0000\n"
+        );
+        let mut out = vec![];
+        matrix.write_phylip(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "    2\n\
+            s1        0.000000 0.250000\n\
+            long_sequence_id 0.250000 0.000000\n"
+        );
+    }
+
+    #[test]
+    fn test_empty_alignment() {
+        let msa = Alignment::new();
+        let matrix = PairwiseIdentity::new(&msa).matrix().unwrap();
+        assert_eq!(matrix.size(), 0);
+    }
+}
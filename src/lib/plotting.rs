@@ -6,11 +6,11 @@ use graphics_buffer::*;
 
 use crate::seqs::{Alignment, SequenceAccesors};
 
-trait ColorScheme{
+pub(crate) trait ColorScheme{
     fn color(&self, char: &char) -> [f32; 4];
 }
 
-struct ProteinColors {colors: [[f32; 4]; 14]}
+pub(crate) struct ProteinColors {colors: [[f32; 4]; 14]}
 
 impl ProteinColors {
     pub fn new() -> Self {
@@ -55,7 +55,7 @@ impl ProteinColors {
             }
         }
     }
-    struct NucleicAcidColors {colors: [[f32; 4]; 6]}
+    pub(crate) struct NucleicAcidColors {colors: [[f32; 4]; 6]}
 
     impl NucleicAcidColors {
         pub fn new() -> Self {
@@ -0,0 +1,446 @@
+use std::error::Error;
+
+use itertools::Itertools;
+
+use crate::align::{ungapped, AlignMode, PairAligner, State};
+use crate::kmer::KmerDistances;
+use crate::seqs::{
+    Alignment, AnnotatedSequence, SequenceAccesors, SequenceCollection,
+};
+use crate::weights::upgma;
+
+/// Aligned rows of some of the sequences. Residues are stored as their
+/// index in the matrix alphabet, and gaps as None.
+#[derive(Clone)]
+struct Block {
+    members: Vec<usize>,
+    rows: Vec<Vec<Option<usize>>>,
+}
+
+impl Block {
+    fn length(&self) -> usize {
+        self.rows.first().map_or(0, |x| x.len())
+    }
+    /// The frequency of each residue in each column, counting gaps as
+    /// zero. Only residues with non zero frequency are kept.
+    fn frequencies(&self) -> Vec<Vec<(usize, f64)>> {
+        let size = self.rows.len() as f64;
+        (0..self.length())
+            .map(|j| {
+                let mut column = self
+                    .rows
+                    .iter()
+                    .filter_map(|x| x[j])
+                    .collect::<Vec<_>>();
+                column.sort_unstable();
+                column
+                    .into_iter()
+                    .group_by(|x| *x)
+                    .into_iter()
+                    .map(|(a, x)| (a, x.count() as f64 / size))
+                    .collect()
+            })
+            .collect()
+    }
+    /// Removes the columns with only gaps.
+    fn remove_gap_columns(&mut self) {
+        let keep = (0..self.length())
+            .map(|j| self.rows.iter().any(|x| x[j].is_some()))
+            .collect::<Vec<_>>();
+        for row in self.rows.iter_mut() {
+            let mut k = keep.iter();
+            row.retain(|_| *k.next().unwrap());
+        }
+    }
+}
+
+/// Builds a multiple sequence alignment by progressive alignment.
+///
+/// A guide tree is built by UPGMA from k-mer distances, and sequences are
+/// aligned following the tree, from the leaves to the root. Two groups of
+/// aligned sequences are aligned by their profiles, scoring each pair of
+/// columns by the average substitution score of all pairs of residues,
+/// counting gaps as zero. Gap penalties are multiplied by the fraction of
+/// residues in the column.
+///
+/// Optionally, the alignment is refined by removing each sequence and
+/// aligning it again to the rest. The new alignment is kept only if it
+/// improves the sum-of-pairs score.
+pub struct ProgressiveAligner<'a> {
+    seqs: &'a SequenceCollection,
+    aligner: PairAligner,
+    kmer_size: usize,
+    refinement: usize,
+    threads: usize,
+}
+
+impl<'a> ProgressiveAligner<'a> {
+    pub fn new(seqs: &'a SequenceCollection) -> Self {
+        ProgressiveAligner {
+            seqs,
+            aligner: PairAligner::default(),
+            kmer_size: 3,
+            refinement: 0,
+            threads: 0,
+        }
+    }
+    /// Sets the matrix, gap penalties and mode used to align profiles.
+    pub fn with_aligner(mut self, aligner: PairAligner) -> Self {
+        self.aligner = aligner;
+        self
+    }
+    /// Sets the k-mer size used to compute the guide tree, 3 by default.
+    pub fn with_kmer_size(mut self, kmer_size: usize) -> Self {
+        self.kmer_size = kmer_size;
+        self
+    }
+    /// Sets the number of refinement rounds, none by default.
+    pub fn with_refinement(mut self, rounds: usize) -> Self {
+        self.refinement = rounds;
+        self
+    }
+    /// Sets the number of threads used to compute the guide tree. Zero uses
+    /// all available cores.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Aligns two blocks by their profiles.
+    fn merge(&self, a: Block, b: Block) -> Block {
+        let size = self.aligner.alphabet.len();
+        let scores = &self.aligner.scores;
+        let (freqs_a, freqs_b) = (a.frequencies(), b.frequencies());
+        // The average score of each residue against each column of b.
+        let profile_b = freqs_b
+            .iter()
+            .map(|column| {
+                (0..size)
+                    .map(|x| {
+                        column
+                            .iter()
+                            .map(|(y, f)| f * scores[x * size + y])
+                            .sum::<f64>()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let occupancy = |freqs: &[Vec<(usize, f64)>]| {
+            freqs
+                .iter()
+                .map(|x| x.iter().map(|y| y.1).sum::<f64>())
+                .collect::<Vec<_>>()
+        };
+        let (n, m) = (a.length(), b.length());
+        let ops = self
+            .aligner
+            .path(
+                n,
+                m,
+                |i, j| freqs_a[i].iter().map(|(x, f)| f * profile_b[j][*x]).sum(),
+                &occupancy(&freqs_a),
+                &occupancy(&freqs_b),
+            )
+            .full_ops(n, m);
+        let mut rows = vec![vec![]; a.rows.len() + b.rows.len()];
+        let (mut i, mut j) = (0, 0);
+        for op in ops {
+            let (from_a, from_b) = match op {
+                State::GapIn2 => (Some(i), None),
+                State::GapIn1 => (None, Some(j)),
+                _ => (Some(i), Some(j)),
+            };
+            for (row, source) in rows.iter_mut().zip(&a.rows) {
+                row.push(from_a.and_then(|x| source[x]));
+            }
+            for (row, source) in rows[a.rows.len()..].iter_mut().zip(&b.rows) {
+                row.push(from_b.and_then(|x| source[x]));
+            }
+            i += from_a.is_some() as usize;
+            j += from_b.is_some() as usize;
+        }
+        let mut members = a.members;
+        members.extend(b.members);
+        Block { members, rows }
+    }
+
+    /// The score of a pair of rows of a block, with the substitution scores
+    /// and affine gap penalties of the aligner, ignoring the columns in
+    /// which both rows have gaps. `ends` has the first and last residue of
+    /// each row.
+    fn pair_score(
+        &self,
+        block: &Block,
+        ends: &[Option<(usize, usize)>],
+        p: usize,
+        q: usize,
+    ) -> f64 {
+        let size = self.aligner.alphabet.len();
+        let free_ends = self.aligner.mode == AlignMode::SemiGlobal;
+        let gap = |row: usize, j: usize, extend: bool| {
+            let terminal = match ends[row] {
+                None => true,
+                Some((a, b)) => j < a || j > b,
+            };
+            match (free_ends && terminal, extend) {
+                (true, _) => 0f64,
+                (false, true) => self.aligner.gap_extend,
+                (false, false) => self.aligner.gap_open,
+            }
+        };
+        let (row_p, row_q) = (&block.rows[p], &block.rows[q]);
+        let mut total = 0f64;
+        let mut previous = State::Start;
+        for j in 0..block.length() {
+            let state = match (row_p[j], row_q[j]) {
+                (Some(x), Some(y)) => {
+                    total += self.aligner.scores[x * size + y];
+                    State::Match
+                }
+                (Some(_), None) => {
+                    total -= gap(q, j, previous == State::GapIn2);
+                    State::GapIn2
+                }
+                (None, Some(_)) => {
+                    total -= gap(p, j, previous == State::GapIn1);
+                    State::GapIn1
+                }
+                (None, None) => previous,
+            };
+            previous = state;
+        }
+        total
+    }
+
+    /// The sum of the scores of the pairs of a row with the other rows of a
+    /// block. Aligning a row again to the rest only changes the score of
+    /// these pairs, so comparing it is enough to compare the sum-of-pairs
+    /// score of the whole block.
+    fn row_score(&self, block: &Block, row: usize) -> f64 {
+        let ends = block
+            .rows
+            .iter()
+            .map(|x| {
+                let first = x.iter().position(|x| x.is_some());
+                let last = x.iter().rposition(|x| x.is_some());
+                first.zip(last)
+            })
+            .collect::<Vec<_>>();
+        (0..block.rows.len())
+            .filter(|q| *q != row)
+            .map(|q| self.pair_score(block, &ends, row, q))
+            .sum()
+    }
+
+    /// Removes each sequence and aligns it again to the rest, keeping the
+    /// new alignment only if its sum-of-pairs score is higher.
+    fn refine(&self, block: Block) -> Block {
+        let mut block = block;
+        for k in 0..block.members.len() {
+            let position = block
+                .members
+                .iter()
+                .position(|x| *x == k)
+                .unwrap();
+            let score = self.row_score(&block, position);
+            let mut rest = block.clone();
+            let member = rest.members.remove(position);
+            let row = rest.rows.remove(position);
+            let single = Block {
+                members: vec![member],
+                rows: vec![row.into_iter().filter(|x| x.is_some()).collect()],
+            };
+            rest.remove_gap_columns();
+            // The merged block has the single sequence in the first row.
+            let candidate = self.merge(single, rest);
+            if self.row_score(&candidate, 0) > score {
+                block = candidate;
+            }
+        }
+        block
+    }
+
+    /// Aligns all sequences. Rows are in the same order as the input.
+    /// ```
+    /// use famlib::progressive::ProgressiveAligner;
+    /// use famlib::seqs::{
+    ///     AnnotatedSequence, SequenceAccesors, SequenceCollection
+    /// };
+    /// let mut seqs = SequenceCollection::new();
+    /// for (id, seq) in [
+    ///     ("s1", "MKVLLWAAG"), ("s2", "MKVLWAAG"), ("s3", "MRVLLWAG")
+    /// ] {
+    ///     seqs.add(AnnotatedSequence::from_string(id, seq)).unwrap();
+    /// }
+    /// let msa = ProgressiveAligner::new(&seqs).align().unwrap();
+    /// assert_eq!(msa.length(), 9);
+    /// assert_eq!(msa.get(0).unwrap().seq_as_string(), "MKVLLWAAG");
+    /// ```
+    pub fn align(&self) -> Result<Alignment, Box<dyn Error>> {
+        let size = self.seqs.size();
+        if size == 0 {
+            return Err("No sequences to align".into());
+        }
+        let chars = self
+            .seqs
+            .iter()
+            .map(ungapped)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut blocks = chars
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                Ok(Some(Block {
+                    members: vec![i],
+                    rows: vec![
+                        self.aligner.indexes(x)?.into_iter().map(Some).collect()
+                    ],
+                }))
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        let matrix = KmerDistances::new(self.seqs)
+            .with_kmer_size(self.kmer_size)
+            .with_threads(self.threads)
+            .matrix()?;
+        let distances = (0..size)
+            .map(|i| {
+                (0..size)
+                    .map(|j| 1f64 - matrix.identity(i, j))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let nodes = upgma(distances);
+        let mut children = vec![vec![]; nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            if let Some(parent) = node.parent {
+                children[parent].push(i);
+            }
+        }
+        // Internal nodes are created after their children.
+        for node in children.iter().skip(size) {
+            let a = blocks[node[0]].take().ok_or("Invalid guide tree")?;
+            let b = blocks[node[1]].take().ok_or("Invalid guide tree")?;
+            blocks.push(Some(self.merge(a, b)));
+        }
+        let mut block = blocks.pop().flatten().ok_or("Invalid guide tree")?;
+        for _ in 0..self.refinement {
+            block = self.refine(block);
+        }
+        let mut rows = vec![vec![]; size];
+        for (member, row) in block.members.iter().zip(&block.rows) {
+            let mut residues = chars[*member].iter();
+            rows[*member] = row
+                .iter()
+                .map(|x| match x {
+                    Some(_) => *residues.next().unwrap(),
+                    None => '-',
+                })
+                .collect();
+        }
+        let mut msa = Alignment::new();
+        for (seq, row) in self.seqs.iter().zip(rows) {
+            msa.add(AnnotatedSequence::new(seq.id().to_string(), row))
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(msa)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::align::AlignMode;
+    use crate::matrices::ScoringMatrix;
+    use crate::test_utils::{build_seqs, rows};
+    use super::*;
+
+    #[test]
+    fn test_progressive() {
+        let seqs = build_seqs(&[
+            "ACGTACGTTTACGT",
+            "ACGTACGTACGT",
+            "ACGTAC-GTACGT",
+            "TTACGTACGTACGT",
+        ]);
+        let aligner = PairAligner::new(
+            &ScoringMatrix::match_mismatch("ACGT", 2, -1).unwrap()
+        ).with_gap_open(3.0).with_gap_extend(1.0);
+        for refinement in [0, 2] {
+            let msa = ProgressiveAligner::new(&seqs)
+                .with_aligner(aligner.clone().with_mode(AlignMode::SemiGlobal))
+                .with_refinement(refinement)
+                .with_threads(1)
+                .align()
+                .unwrap();
+            // Ungapped sequences are kept, and in the same order.
+            for (row, seq) in rows(&msa).iter().zip(seqs.iter()) {
+                assert_eq!(row.replace('-', ""), seq.seq_as_string().replace('-', ""));
+            }
+            // Shared columns are aligned.
+            let columns = msa.columns().filter(
+                |x| x.iter().all(|c| **c == x[0].to_ascii_uppercase() && **c != '-')
+            ).count();
+            assert!(columns >= 10, "{:?}", rows(&msa));
+        }
+    }
+
+    #[test]
+    fn test_refinement_fixes_misplaced_gap() {
+        let seqs = build_seqs(&["ACGTACGT", "ACGTACGT", "ACGTCGT"]);
+        let aligner = PairAligner::new(
+            &ScoringMatrix::match_mismatch("ACGT", 2, -1).unwrap()
+        ).with_gap_open(3.0).with_gap_extend(1.0);
+        let progressive = ProgressiveAligner::new(&seqs).with_aligner(aligner);
+        let to_block = |rows: &[&str]| Block {
+            members: (0..rows.len()).collect(),
+            rows: rows
+                .iter()
+                .map(|row| row.chars().map(|c| "ACGT".find(c)).collect())
+                .collect(),
+        };
+        let to_rows = |block: &Block| {
+            let mut rows = block
+                .members
+                .iter()
+                .zip(&block.rows)
+                .map(|(member, row)| {
+                    let row = row
+                        .iter()
+                        .map(|x| x.map_or('-', |i| b"ACGT"[i] as char))
+                        .collect::<String>();
+                    (*member, row)
+                })
+                .collect::<Vec<_>>();
+            rows.sort();
+            rows.into_iter().map(|x| x.1).collect::<Vec<_>>()
+        };
+        let misplaced = to_block(&["ACGTACGT", "ACGTACGT", "A-CGTCGT"]);
+        let refined = progressive.refine(misplaced.clone());
+        assert_eq!(
+            to_rows(&refined),
+            vec!["ACGTACGT", "ACGTACGT", "ACGT-CGT"]
+        );
+        let sum_of_pairs = |block: &Block| {
+            (0..block.rows.len())
+                .map(|row| progressive.row_score(block, row))
+                .sum::<f64>() / 2.0
+        };
+        assert!(sum_of_pairs(&refined) > sum_of_pairs(&misplaced));
+        // A block that cannot be improved is kept.
+        let refined = progressive.refine(refined);
+        assert_eq!(
+            to_rows(&refined),
+            vec!["ACGTACGT", "ACGTACGT", "ACGT-CGT"]
+        );
+    }
+
+    #[test]
+    fn test_small_inputs() {
+        let seqs = build_seqs(&["MKVLW"]);
+        let msa = ProgressiveAligner::new(&seqs).align().unwrap();
+        assert_eq!(rows(&msa), vec!["MKVLW"]);
+        let seqs = build_seqs(&["MKVLW", "MKLW"]);
+        let msa = ProgressiveAligner::new(&seqs).align().unwrap();
+        assert_eq!(rows(&msa), vec!["MKVLW", "MK-LW"]);
+        assert!(ProgressiveAligner::new(&build_seqs(&[])).align().is_err());
+    }
+}
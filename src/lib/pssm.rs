@@ -0,0 +1,517 @@
+use std::error::Error;
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::conservation::Alphabet;
+use crate::mapping::ReferenceNumbering;
+use crate::seqs::{Alignment, AnnotatedSequence, SequenceAccesors};
+
+/// Residue order used by PSI-BLAST ASCII PSSM files.
+const PSIBLAST_ORDER: &str = "ARNDCQEGHILKMFPSTWYV";
+
+/// The kind of values written in a tabular profile export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileValues {
+    Counts,
+    Frequencies,
+    LogOdds,
+}
+
+/// Builds a `Profile` from an alignment.
+///
+/// Counts can be weighted by sequence (for example, with the weights
+/// computed by a `Clusterer`). Pseudocounts are distributed following the
+/// background frequencies.
+pub struct ProfileBuilder<'a> {
+    msa: &'a Alignment,
+    alphabet: Alphabet,
+    weights: Option<Vec<f64>>,
+    pseudocount: f64,
+    background: Option<Vec<f64>>,
+}
+
+impl<'a> ProfileBuilder<'a> {
+    pub fn new(msa: &'a Alignment) -> Self {
+        ProfileBuilder {
+            msa,
+            alphabet: Alphabet::Protein,
+            weights: None,
+            pseudocount: 1.0,
+            background: None,
+        }
+    }
+    pub fn as_dna(mut self) -> Self {
+        self.alphabet = Alphabet::DNA;
+        self
+    }
+    pub fn as_protein(mut self) -> Self {
+        self.alphabet = Alphabet::Protein;
+        self
+    }
+    pub fn with_alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+    /// Sets one weight per sequence of the alignment.
+    pub fn with_weights(mut self, weights: Vec<f64>) -> Self {
+        self.weights = Some(weights);
+        self
+    }
+    /// Sets the total pseudocount weight added to each column.
+    pub fn with_pseudocount(mut self, pseudocount: f64) -> Self {
+        self.pseudocount = pseudocount.max(0.0);
+        self
+    }
+    /// Sets the background frequencies, sorted by symbol index.
+    pub fn with_background(mut self, background: Vec<f64>) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    /// Counts the residues of every column.
+    ///
+    /// ```
+    /// use famlib::seqs::SequenceCollection;
+    /// use famlib::pssm::ProfileBuilder;
+    /// let msa = vec![("s1", "AC"), ("s2", "A-"), ("s3", "GC")]
+    ///     .into_iter()
+    ///     .collect::<SequenceCollection>()
+    ///     .to_msa()
+    ///     .unwrap();
+    /// let profile = ProfileBuilder::new(&msa).as_dna().build().unwrap();
+    /// assert_eq!(profile.counts()[0], vec![2.0, 0.0, 0.0, 1.0]);
+    /// assert_eq!(profile.gaps()[1], 1.0);
+    /// ```
+    pub fn build(&self) -> Result<Profile, Box<dyn Error>> {
+        let size = self.alphabet.size();
+        let weights = match &self.weights {
+            Some(w) if w.len() != self.msa.size() => {
+                return Err(Box::new(io::Error::other(
+                    "The number of weights and sequences is different",
+                )))
+            }
+            Some(w) => w.clone(),
+            None => vec![1.0; self.msa.size()],
+        };
+        let background = match &self.background {
+            Some(b) if b.len() != size => {
+                return Err(Box::new(io::Error::other(
+                    "Background frequencies do not match the alphabet",
+                )))
+            }
+            Some(b) => b.clone(),
+            None => self.alphabet.background(),
+        };
+        let mut counts = vec![];
+        let mut gaps = vec![];
+        for col in self.msa.columns() {
+            let mut current = vec![0f64; size + 2];
+            col.iter()
+                .zip(weights.iter())
+                .for_each(|(c, w)| current[self.alphabet.index(**c)] += w);
+            gaps.push(current[size]);
+            current.truncate(size);
+            counts.push(current);
+        }
+        Ok(Profile {
+            alphabet: self.alphabet,
+            counts,
+            gaps,
+            pseudocount: self.pseudocount,
+            background,
+        })
+    }
+}
+
+/// Per-column residue counts of an alignment.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    alphabet: Alphabet,
+    counts: Vec<Vec<f64>>,
+    gaps: Vec<f64>,
+    pseudocount: f64,
+    background: Vec<f64>,
+}
+
+#[derive(Serialize)]
+struct JsonColumn {
+    column: usize,
+    gaps: f64,
+    counts: Vec<f64>,
+    frequencies: Vec<f64>,
+    log_odds: Vec<Option<f64>>,
+    information: f64,
+}
+
+#[derive(Serialize)]
+struct JsonProfile {
+    alphabet: String,
+    pseudocount: f64,
+    background: Vec<f64>,
+    columns: Vec<JsonColumn>,
+}
+
+impl Profile {
+    pub fn alphabet(&self) -> Alphabet {
+        self.alphabet
+    }
+    /// Number of columns of the profile.
+    pub fn length(&self) -> usize {
+        self.counts.len()
+    }
+    /// Weighted residue counts, one vector per column.
+    pub fn counts(&self) -> &Vec<Vec<f64>> {
+        &self.counts
+    }
+    /// Weighted gap counts, one value per column.
+    pub fn gaps(&self) -> &Vec<f64> {
+        &self.gaps
+    }
+    pub fn background(&self) -> &Vec<f64> {
+        &self.background
+    }
+
+    /// Residue frequencies of each column, with pseudocounts.
+    ///
+    /// Gaps and unknown characters are not considered. Columns with no
+    /// residues and no pseudocounts have all frequencies equal to zero.
+    pub fn frequencies(&self) -> Vec<Vec<f64>> {
+        self.counts
+            .iter()
+            .map(|col| {
+                let total: f64 = col.iter().sum::<f64>() + self.pseudocount;
+                col.iter()
+                    .zip(self.background.iter())
+                    .map(|(n, bg)| match total > 0.0 {
+                        true => (n + self.pseudocount * bg) / total,
+                        false => 0.0,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Log-odds scores, in bits, of the frequencies against the background.
+    ///
+    /// Residues with zero frequency have a score of minus infinity.
+    pub fn log_odds(&self) -> Vec<Vec<f64>> {
+        self.frequencies()
+            .iter()
+            .map(|col| {
+                col.iter()
+                    .zip(self.background.iter())
+                    .map(|(f, bg)| (f / bg).log2())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Relative entropy, in bits, of each column against the background.
+    pub fn information_content(&self) -> Vec<f64> {
+        self.frequencies()
+            .iter()
+            .map(|col| {
+                col.iter()
+                    .zip(self.background.iter())
+                    .filter(|(f, _)| **f > 0.0)
+                    .map(|(f, bg)| f * (f / bg).log2())
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Writes one row per column and one tab separated field per residue.
+    /// If a reference numbering is given, the residue number of the
+    /// reference is written after the column, with '-' for gaps.
+    pub fn write_tsv<T: Write>(
+        &self,
+        mut writer: T,
+        values: ProfileValues,
+        reference: Option<&ReferenceNumbering>,
+    ) -> io::Result<()> {
+        let header = self
+            .alphabet
+            .symbols()
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join("\t");
+        let header = match reference {
+            Some(_) => format!("ref\t{}", header),
+            None => header,
+        };
+        writeln!(writer, "column\t{}", header)?;
+        let data = match values {
+            ProfileValues::Counts => self.counts.clone(),
+            ProfileValues::Frequencies => self.frequencies(),
+            ProfileValues::LogOdds => self.log_odds(),
+        };
+        for (i, col) in data.iter().enumerate() {
+            let fields = col
+                .iter()
+                .map(|x| match values {
+                    ProfileValues::Counts => format!("{}", x),
+                    _ => format!("{:.4}", x),
+                })
+                .collect::<Vec<_>>()
+                .join("\t");
+            let fields = match reference {
+                Some(numbering) => format!(
+                    "{}\t{}",
+                    numbering
+                        .number_at(i)
+                        .map_or(String::from("-"), |x| x.to_string()),
+                    fields
+                ),
+                None => fields,
+            };
+            writeln!(writer, "{}\t{}", i + 1, fields)?;
+        }
+        Ok(())
+    }
+
+    /// Writes counts, frequencies, log-odds scores and information content
+    /// of every column as a JSON document.
+    ///
+    /// Scores of minus infinity are written as `null`.
+    pub fn write_json<T: Write>(&self, mut writer: T) -> io::Result<()> {
+        let frequencies = self.frequencies();
+        let log_odds = self.log_odds();
+        let information = self.information_content();
+        let columns = (0..self.length())
+            .map(|i| JsonColumn {
+                column: i + 1,
+                gaps: self.gaps[i],
+                counts: self.counts[i].clone(),
+                frequencies: frequencies[i].clone(),
+                log_odds: log_odds[i]
+                    .iter()
+                    .map(|x| Some(*x).filter(|y| y.is_finite()))
+                    .collect(),
+                information: information[i],
+            })
+            .collect();
+        let profile = JsonProfile {
+            alphabet: self.alphabet.symbols().iter().collect(),
+            pseudocount: self.pseudocount,
+            background: self.background.clone(),
+            columns,
+        };
+        serde_json::to_writer_pretty(&mut writer, &profile)
+            .map_err(io::Error::other)?;
+        writeln!(writer)
+    }
+
+    /// Writes the profile in the ASCII PSSM format of PSI-BLAST.
+    ///
+    /// The matrix is anchored to a reference sequence of the alignment:
+    /// columns with a gap in the reference are skipped and positions are
+    /// numbered by the reference residues. Scores are rounded to half bits.
+    pub fn write_psiblast<T: Write>(
+        &self,
+        mut writer: T,
+        reference: &AnnotatedSequence,
+    ) -> io::Result<()> {
+        let seq = reference
+            .seq()
+            .filter(|x| x.len() == self.length())
+            .ok_or_else(|| {
+                io::Error::other(
+                    "Reference sequence does not match the profile length",
+                )
+            })?;
+        let order = match self.alphabet {
+            Alphabet::Protein => PSIBLAST_ORDER
+                .chars()
+                .map(|x| self.alphabet.index(x))
+                .collect::<Vec<_>>(),
+            Alphabet::DNA => (0..self.alphabet.size()).collect(),
+        };
+        let symbols = self.alphabet.symbols();
+        let log_odds = self.log_odds();
+        let information = self.information_content();
+        let header = order
+            .iter()
+            .map(|x| format!("{:>3}", symbols[*x]))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "Last position-specific scoring matrix computed, weighted \
+            observed percentages rounded down, information per position, \
+            and relative weight of gapless real matches to pseudocounts"
+        )?;
+        writeln!(writer, "        {}  {}", header, header)?;
+        let mut position = 0;
+        for (i, c) in seq.iter().enumerate() {
+            if *c == '-' || *c == '.' {
+                continue;
+            }
+            position += 1;
+            let scores = order
+                .iter()
+                .map(|x| {
+                    let s = (2.0 * log_odds[i][*x]).round().max(-99.0);
+                    format!("{:>3}", s as i32)
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            let percentages = order
+                .iter()
+                .map(|x| {
+                    let observed = self.counts[i][*x];
+                    let total: f64 = self.counts[i].iter().sum();
+                    let pct = match total > 0.0 {
+                        true => (100.0 * observed / total).floor(),
+                        false => 0.0,
+                    };
+                    format!("{:>3}", pct as i32)
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            let observed: f64 = self.counts[i].iter().sum();
+            let relative_weight = match observed + self.pseudocount > 0.0 {
+                true => observed / (observed + self.pseudocount),
+                false => 0.0,
+            };
+            writeln!(
+                writer,
+                "{:>5} {}  {}  {}  {:.2} {:.2}",
+                position,
+                c.to_ascii_uppercase(),
+                scores,
+                percentages,
+                information[i],
+                relative_weight
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::build_msa;
+
+    #[test]
+    fn test_counts_and_gaps() {
+        let msa = build_msa(&["ACGT-", "ACGA-", "ACCA-", "-CCAA"]);
+        let profile = ProfileBuilder::new(&msa).as_dna().build().unwrap();
+        assert_eq!(profile.length(), 5);
+        // DNA order is A C T G
+        assert_eq!(profile.counts()[0], vec![3.0, 0.0, 0.0, 0.0]);
+        assert_eq!(profile.counts()[2], vec![0.0, 2.0, 0.0, 2.0]);
+        assert_eq!(profile.gaps(), &vec![1.0, 0.0, 0.0, 0.0, 3.0]);
+    }
+
+    #[test]
+    fn test_weighted_counts() {
+        let msa = build_msa(&["ACGT-", "ACGA-", "ACCA-", "-CCAA"]);
+        let profile = ProfileBuilder::new(&msa)
+            .as_dna()
+            .with_weights(vec![0.5, 0.5, 1.0, 1.0])
+            .build()
+            .unwrap();
+        assert_eq!(profile.counts()[3], vec![2.5, 0.0, 0.5, 0.0]);
+        assert!(ProfileBuilder::new(&msa)
+            .with_weights(vec![1.0])
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_frequencies_with_pseudocounts() {
+        let msa = build_msa(&["ACGT-", "ACGA-", "ACCA-", "-CCAA"]);
+        let profile = ProfileBuilder::new(&msa)
+            .as_dna()
+            .with_pseudocount(0.0)
+            .build()
+            .unwrap();
+        assert_eq!(profile.frequencies()[0], vec![1.0, 0.0, 0.0, 0.0]);
+        let profile = ProfileBuilder::new(&msa)
+            .as_dna()
+            .with_pseudocount(4.0)
+            .build()
+            .unwrap();
+        let freqs = profile.frequencies();
+        assert!((freqs[0][0] - 4.0 / 7.0).abs() < 1e-9);
+        assert!((freqs[0][1] - 1.0 / 7.0).abs() < 1e-9);
+        assert!(freqs.iter().all(|x| (x.iter().sum::<f64>() - 1.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_log_odds_and_information() {
+        let msa = build_msa(&["ACGT-", "ACGA-", "ACCA-", "-CCAA"]);
+        let profile = ProfileBuilder::new(&msa)
+            .as_dna()
+            .with_pseudocount(0.0)
+            .build()
+            .unwrap();
+        let scores = profile.log_odds();
+        assert!((scores[1][1] - 2.0).abs() < 1e-9);
+        assert!(scores[1][0].is_infinite());
+        let info = profile.information_content();
+        assert!((info[1] - 2.0).abs() < 1e-9);
+        assert!((info[2] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_write_tsv() {
+        let msa = build_msa(&["ACGT-", "ACGA-", "ACCA-", "-CCAA"]);
+        let profile = ProfileBuilder::new(&msa).as_dna().build().unwrap();
+        let mut out = vec![];
+        profile.write_tsv(&mut out, ProfileValues::Counts, None).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "column\tA\tC\tT\tG");
+        assert_eq!(lines[1], "1\t3\t0\t0\t0");
+        assert_eq!(lines.len(), 6);
+        let mut seq = msa.get(0).unwrap().clone();
+        seq.set_sequence_as_string("-ACGT".to_string());
+        let numbering = ReferenceNumbering::new(&seq).with_start(10);
+        let mut out = vec![];
+        profile
+            .write_tsv(&mut out, ProfileValues::Counts, Some(&numbering))
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "column\tref\tA\tC\tT\tG");
+        assert_eq!(lines[1], "1\t-\t3\t0\t0\t0");
+        assert_eq!(lines[2].split('\t').nth(1), Some("10"));
+    }
+
+    #[test]
+    fn test_write_json() {
+        let msa = build_msa(&["ACGT-", "ACGA-", "ACCA-", "-CCAA"]);
+        let profile = ProfileBuilder::new(&msa)
+            .as_dna()
+            .with_pseudocount(0.0)
+            .build()
+            .unwrap();
+        let mut out = vec![];
+        profile.write_json(&mut out).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(value["alphabet"], "ACTG");
+        assert_eq!(value["columns"].as_array().unwrap().len(), 5);
+        assert_eq!(value["columns"][1]["log_odds"][0], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_write_psiblast() {
+        let msa = build_msa(&["M-K", "MAK", "LAR"]);
+        let profile = ProfileBuilder::new(&msa).build().unwrap();
+        let mut out = vec![];
+        profile
+            .write_psiblast(&mut out, msa.get(0).unwrap())
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert!(lines[2].trim_start().starts_with("A   R   N   D"));
+        assert!(lines[3].starts_with("    1 M"));
+        assert!(lines[4].starts_with("    2 K"));
+        assert_eq!(lines.len(), 5);
+    }
+}
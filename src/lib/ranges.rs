@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::BufRead;
+
+/// A position of a range expression. Negative positions count from the
+/// end, -1 is the last position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Index {
+    FromStart(usize),
+    FromEnd(usize),
+}
+
+impl Index {
+    fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let value = text
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| format!("Cannot parse position: '{}'", text))?;
+        match value {
+            0 => Err("Positions start at one".into()),
+            x if x > 0 => Ok(Index::FromStart(x as usize)),
+            x => Ok(Index::FromEnd(x.unsigned_abs() as usize)),
+        }
+    }
+    fn resolve(&self, first: usize, last: usize) -> Result<usize, Box<dyn Error>> {
+        let position = match self {
+            Index::FromStart(x) => Some(*x),
+            Index::FromEnd(x) => (last + 1).checked_sub(*x),
+        };
+        position
+            .filter(|x| *x >= first && *x <= last)
+            .ok_or_else(
+                || format!(
+                    "Position {} out of range {}-{}",
+                    self.text(),
+                    first,
+                    last
+                ).into()
+            )
+    }
+    fn text(&self) -> String {
+        match self {
+            Index::FromStart(x) => x.to_string(),
+            Index::FromEnd(x) => format!("-{}", x),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum RangeItem {
+    Single(Index),
+    Span(Index, Option<Index>),
+}
+
+/// Ranges defined by name, read from a file with a name and a range
+/// expression on each line, separated by spaces or tabs. Empty lines and
+/// lines starting with '#' are skipped.
+#[derive(Clone, Debug, Default)]
+pub struct NamedRanges {
+    ranges: HashMap<String, String>,
+}
+
+impl NamedRanges {
+    pub fn new() -> Self {
+        NamedRanges::default()
+    }
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut ranges = NamedRanges::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.splitn(2, char::is_whitespace);
+            let name = fields.next().unwrap_or("");
+            let expression = fields.next().map(|x| x.trim()).unwrap_or("");
+            if expression.is_empty() {
+                return Err(format!("Range without expression: '{}'", line).into());
+            }
+            ranges.add(name, expression);
+        }
+        Ok(ranges)
+    }
+    pub fn add(&mut self, name: &str, expression: &str) {
+        self.ranges.insert(name.to_string(), expression.to_string());
+    }
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.ranges.get(name).map(|x| x.as_str())
+    }
+}
+
+/// A selection of positions written as a comma separated list of single
+/// one based positions (`75`), closed ranges (`1-50`), ranges open to the
+/// end (`100-`) and names of ranges. Negative positions count from the
+/// end, so `-10--1` are the last ten positions.
+///
+/// ```
+/// use famlib::ranges::RangeExpression;
+/// let expression = RangeExpression::parse("1-3,5,-2-").unwrap();
+/// assert_eq!(expression.positions(1, 10).unwrap(), vec![1, 2, 3, 5, 9, 10]);
+/// assert_eq!(expression.intervals(1, 10).unwrap(), vec![(1, 3), (5, 5), (9, 10)]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RangeExpression {
+    items: Vec<RangeItem>,
+}
+
+impl RangeExpression {
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        Self::parse_with_names(text, &NamedRanges::new())
+    }
+    /// Parses an expression that may contain names of ranges. Named ranges
+    /// can not contain other names.
+    pub fn parse_with_names(
+        text: &str,
+        names: &NamedRanges,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut items = vec![];
+        for token in text.split(',').map(|x| x.trim()) {
+            if token.is_empty() {
+                return Err(format!("Empty range in: '{}'", text).into());
+            }
+            let is_name = token.starts_with(|c: char| c.is_alphabetic() || c == '_');
+            if is_name {
+                let expression = names
+                    .get(token)
+                    .ok_or_else(|| format!("Unknown range name: '{}'", token))?;
+                let named = Self::parse_with_names(expression, &NamedRanges::new())
+                    .map_err(|e| format!("In range '{}': {}", token, e))?;
+                items.extend(named.items);
+                continue;
+            }
+            // The separator is the first dash that is not a sign.
+            let separator = token
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| *c == '-')
+                .map(|x| x.0);
+            let item = match separator {
+                None => RangeItem::Single(Index::parse(token)?),
+                Some(x) => {
+                    let end = &token[x + 1..];
+                    RangeItem::Span(
+                        Index::parse(&token[..x])?,
+                        match end.trim().is_empty() {
+                            true => None,
+                            false => Some(Index::parse(end)?),
+                        },
+                    )
+                }
+            };
+            items.push(item);
+        }
+        Ok(RangeExpression { items })
+    }
+    /// Resolves the expression to sorted and merged inclusive intervals
+    /// of positions numbered from `first` to `last`.
+    pub fn intervals(
+        &self,
+        first: usize,
+        last: usize,
+    ) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+        let mut intervals = self
+            .items
+            .iter()
+            .map(|item| match item {
+                RangeItem::Single(x) => {
+                    x.resolve(first, last).map(|x| (x, x))
+                }
+                RangeItem::Span(a, b) => {
+                    let a = a.resolve(first, last)?;
+                    let b = match b {
+                        Some(b) => b.resolve(first, last)?,
+                        None => last,
+                    };
+                    match a <= b {
+                        true => Ok((a, b)),
+                        false => Err(
+                            format!("Range end before start: {}-{}", a, b)
+                                .into()
+                        ),
+                    }
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        intervals.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = vec![];
+        for (a, b) in intervals {
+            match merged.last_mut() {
+                Some(current) if a <= current.1 + 1 => {
+                    current.1 = current.1.max(b)
+                }
+                _ => merged.push((a, b)),
+            }
+        }
+        Ok(merged)
+    }
+    /// Resolves the expression to sorted unique positions numbered from
+    /// `first` to `last`.
+    pub fn positions(
+        &self,
+        first: usize,
+        last: usize,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        Ok(
+            self.intervals(first, last)?
+                .into_iter()
+                .flat_map(|(a, b)| a..=b)
+                .collect()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_resolve() {
+        let expression = RangeExpression::parse("1-50, 75,100-").unwrap();
+        let intervals = expression.intervals(1, 120).unwrap();
+        assert_eq!(intervals, vec![(1, 50), (75, 75), (100, 120)]);
+        let expression = RangeExpression::parse("-10--1,-20").unwrap();
+        assert_eq!(expression.intervals(1, 100).unwrap(), vec![(81, 81), (91, 100)]);
+        // Overlapping and adjacent ranges are merged.
+        let expression = RangeExpression::parse("5-8,1-4,6").unwrap();
+        assert_eq!(expression.intervals(1, 10).unwrap(), vec![(1, 8)]);
+        // Numbering starting after one.
+        let expression = RangeExpression::parse("12-13,-1").unwrap();
+        assert_eq!(expression.positions(10, 20).unwrap(), vec![12, 13, 20]);
+        assert!(expression.positions(1, 11).is_err());
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for text in ["", "0", "1-,", "a-3", "3-x", "5-2", "1--"] {
+            let parsed = RangeExpression::parse(text)
+                .and_then(|x| x.positions(1, 10));
+            assert!(parsed.is_err(), "{}", text);
+        }
+        let expression = RangeExpression::parse("8-12").unwrap();
+        assert!(expression.positions(1, 10).is_err());
+        assert!(RangeExpression::parse("-11").unwrap().positions(1, 10).is_err());
+    }
+
+    #[test]
+    fn test_named_ranges() {
+        let text = "# domains\nkinase\t10-20\nloop 30-32, 40\n\n";
+        let names = NamedRanges::from_reader(text.as_bytes()).unwrap();
+        let expression =
+            RangeExpression::parse_with_names("kinase,loop,-1", &names).unwrap();
+        assert_eq!(
+            expression.intervals(1, 50).unwrap(),
+            vec![(10, 20), (30, 32), (40, 40), (50, 50)]
+        );
+        assert!(RangeExpression::parse_with_names("other", &names).is_err());
+        assert!(NamedRanges::from_reader("kinase\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use std::error::Error;
+
+use crate::gapping::is_gap;
+use crate::matrices::ScoringMatrix;
+
+/// A measure of similarity between two aligned sequences.
+///
+/// Sequences must have the same length. Higher values mean more similar
+/// sequences, identity based metrics range from zero to one.
+pub trait SimilarityMetric: Sync {
+    fn similarity(
+        &self,
+        seq1: &[char],
+        seq2: &[char],
+    ) -> Result<f64, Box<dyn Error>>;
+}
+
+impl<M: SimilarityMetric + ?Sized> SimilarityMetric for Box<M> {
+    fn similarity(
+        &self,
+        seq1: &[char],
+        seq2: &[char],
+    ) -> Result<f64, Box<dyn Error>> {
+        self.as_ref().similarity(seq1, seq2)
+    }
+}
+
+impl<M: SimilarityMetric + ?Sized> SimilarityMetric for &M {
+    fn similarity(
+        &self,
+        seq1: &[char],
+        seq2: &[char],
+    ) -> Result<f64, Box<dyn Error>> {
+        (**self).similarity(seq1, seq2)
+    }
+}
+
+fn check_lengths(seq1: &[char], seq2: &[char]) -> Result<(), Box<dyn Error>> {
+    match seq1.len() == seq2.len() {
+        true => Ok(()),
+        false => Err(Box::new(std::io::Error::other(
+            "Sequences have different length",
+        ))),
+    }
+}
+
+/// Counts identical non gap positions, ignoring case.
+fn identical(seq1: &[char], seq2: &[char]) -> usize {
+    seq1.iter()
+        .zip(seq2)
+        .filter(|(a, b)| a.eq_ignore_ascii_case(b) && !is_gap(**a))
+        .count()
+}
+
+/// Identity over the alignment length.
+///
+/// As in the other identity metrics, both '-' and '.' are gaps and residues
+/// are compared ignoring case.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IdentityOverLength {
+    exclude_gaps: bool,
+}
+
+impl IdentityOverLength {
+    pub fn new() -> Self {
+        IdentityOverLength { exclude_gaps: false }
+    }
+    /// Positions in which both sequences are gaps are not counted in the
+    /// alignment length.
+    pub fn exclude_gaps(mut self) -> Self {
+        self.exclude_gaps = true;
+        self
+    }
+    pub fn include_gaps(mut self) -> Self {
+        self.exclude_gaps = false;
+        self
+    }
+}
+
+impl SimilarityMetric for IdentityOverLength {
+    fn similarity(
+        &self,
+        seq1: &[char],
+        seq2: &[char],
+    ) -> Result<f64, Box<dyn Error>> {
+        check_lengths(seq1, seq2)?;
+        let total = match self.exclude_gaps {
+            true => seq1
+                .iter()
+                .zip(seq2)
+                .filter(|(a, b)| !is_gap(**a) || !is_gap(**b))
+                .count(),
+            false => seq1.len(),
+        };
+        Ok(identical(seq1, seq2) as f64 / total.max(1) as f64)
+    }
+}
+
+/// Identity over the ungapped length of the shorter sequence.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IdentityOverShorter;
+
+impl SimilarityMetric for IdentityOverShorter {
+    fn similarity(
+        &self,
+        seq1: &[char],
+        seq2: &[char],
+    ) -> Result<f64, Box<dyn Error>> {
+        check_lengths(seq1, seq2)?;
+        let residues = |s: &[char]| s.iter().filter(|c| !is_gap(**c)).count();
+        let shorter = residues(seq1).min(residues(seq2));
+        Ok(identical(seq1, seq2) as f64 / shorter.max(1) as f64)
+    }
+}
+
+/// Identity over the positions in which both sequences have a residue.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IdentityOverAligned;
+
+impl SimilarityMetric for IdentityOverAligned {
+    fn similarity(
+        &self,
+        seq1: &[char],
+        seq2: &[char],
+    ) -> Result<f64, Box<dyn Error>> {
+        check_lengths(seq1, seq2)?;
+        let aligned = seq1
+            .iter()
+            .zip(seq2)
+            .filter(|(a, b)| !is_gap(**a) && !is_gap(**b))
+            .count();
+        Ok(identical(seq1, seq2) as f64 / aligned.max(1) as f64)
+    }
+}
+
+/// Fraction of aligned pairs of residues with a positive score.
+///
+/// Only positions in which both sequences have a residue are counted.
+/// BLOSUM62 is used by default.
+#[derive(Clone)]
+pub struct Positives {
+    matrix: ScoringMatrix,
+}
+
+impl Positives {
+    pub fn new() -> Self {
+        Positives { matrix: ScoringMatrix::blosum62() }
+    }
+    pub fn with_matrix(mut self, matrix: ScoringMatrix) -> Self {
+        self.matrix = matrix;
+        self
+    }
+}
+
+impl Default for Positives {
+    fn default() -> Self {
+        Positives::new()
+    }
+}
+
+impl SimilarityMetric for Positives {
+    fn similarity(
+        &self,
+        seq1: &[char],
+        seq2: &[char],
+    ) -> Result<f64, Box<dyn Error>> {
+        check_lengths(seq1, seq2)?;
+        let (positives, aligned) = seq1
+            .iter()
+            .zip(seq2)
+            .filter(|(a, b)| !is_gap(**a) && !is_gap(**b))
+            .fold((0usize, 0usize), |(p, t), (a, b)| {
+                match self.matrix.score(*a, *b) {
+                    Some(x) if x > 0 => (p + 1, t + 1),
+                    _ => (p, t + 1),
+                }
+            });
+        Ok(positives as f64 / aligned.max(1) as f64)
+    }
+}
+
+/// Substitution score normalized by the self scores of both sequences.
+///
+/// Computed as S(a, b) / sqrt(S(a, a) * S(b, b)), over the positions in
+/// which both sequences have a residue known by the matrix. Identical
+/// sequences have a score of one, unrelated sequences can have negative
+/// scores. BLOSUM62 is used by default.
+#[derive(Clone)]
+pub struct NormalizedScore {
+    matrix: ScoringMatrix,
+}
+
+impl NormalizedScore {
+    pub fn new() -> Self {
+        NormalizedScore { matrix: ScoringMatrix::blosum62() }
+    }
+    pub fn with_matrix(mut self, matrix: ScoringMatrix) -> Self {
+        self.matrix = matrix;
+        self
+    }
+}
+
+impl Default for NormalizedScore {
+    fn default() -> Self {
+        NormalizedScore::new()
+    }
+}
+
+impl SimilarityMetric for NormalizedScore {
+    fn similarity(
+        &self,
+        seq1: &[char],
+        seq2: &[char],
+    ) -> Result<f64, Box<dyn Error>> {
+        check_lengths(seq1, seq2)?;
+        let (mut score, mut self1, mut self2) = (0i64, 0i64, 0i64);
+        for (a, b) in seq1.iter().zip(seq2) {
+            let scores = (
+                self.matrix.score(*a, *b),
+                self.matrix.score(*a, *a),
+                self.matrix.score(*b, *b),
+            );
+            if let (Some(ab), Some(aa), Some(bb)) = scores {
+                score += ab as i64;
+                self1 += aa as i64;
+                self2 += bb as i64;
+            }
+        }
+        let norm = ((self1 * self2) as f64).sqrt();
+        match norm > 0f64 {
+            true => Ok(score as f64 / norm),
+            false => Ok(0f64),
+        }
+    }
+}
+
+/// Creates a similarity metric from its name.
+///
+/// Valid names are `identity`, `identity-shorter`, `identity-aligned`,
+/// `positives` and `score`. Only `identity` can exclude gaps, asking for
+/// `exclude_gaps` with any other metric is an error.
+pub fn metric_by_name(
+    name: &str,
+    exclude_gaps: bool,
+) -> Result<Box<dyn SimilarityMetric>, Box<dyn Error>> {
+    let metric: Box<dyn SimilarityMetric> = match name {
+        "identity" => return Ok(Box::new(IdentityOverLength {exclude_gaps})),
+        "identity-shorter" => Box::new(IdentityOverShorter),
+        "identity-aligned" => Box::new(IdentityOverAligned),
+        "positives" => Box::new(Positives::new()),
+        "score" => Box::new(NormalizedScore::new()),
+        _ => return Err(format!("Invalid metric: {}", name).into()),
+    };
+    match exclude_gaps {
+        true => Err(
+            format!("Metric {} does not support excluding gaps", name).into()
+        ),
+        false => Ok(metric),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_identities() {
+        let seq1 = chars("ACDE--GH");
+        let seq2 = chars("ACDF-KG-");
+        let id = IdentityOverLength::new().similarity(&seq1, &seq2).unwrap();
+        assert_eq!(id, 0.5);
+        let id = IdentityOverLength::new()
+            .exclude_gaps()
+            .similarity(&seq1, &seq2)
+            .unwrap();
+        assert_eq!(id, 4.0 / 7.0);
+        let id = IdentityOverShorter.similarity(&seq1, &seq2).unwrap();
+        assert_eq!(id, 4.0 / 6.0);
+        let id = IdentityOverAligned.similarity(&seq1, &seq2).unwrap();
+        assert_eq!(id, 0.8);
+        assert!(IdentityOverAligned.similarity(&seq1, &seq2[1..]).is_err());
+        // Dots are gaps, and residues are compared ignoring case.
+        let seq1 = chars("acde.-GH");
+        let seq2 = chars("ACDF.KG-");
+        let id = IdentityOverLength::new().similarity(&seq1, &seq2).unwrap();
+        assert_eq!(id, 0.5);
+        let id = IdentityOverLength::new()
+            .exclude_gaps()
+            .similarity(&seq1, &seq2)
+            .unwrap();
+        assert_eq!(id, 4.0 / 7.0);
+        let id = IdentityOverAligned.similarity(&seq1, &seq2).unwrap();
+        assert_eq!(id, 0.8);
+    }
+
+    #[test]
+    fn test_positives() {
+        let seq1 = chars("IKD-W");
+        let seq2 = chars("VRCAW");
+        let value = Positives::new().similarity(&seq1, &seq2).unwrap();
+        assert_eq!(value, 0.75);
+        let empty = Positives::new().similarity(&[], &[]).unwrap();
+        assert_eq!(empty, 0.0);
+        let identity = ScoringMatrix::match_mismatch("ACDIKRVW", 1, -1)
+            .unwrap();
+        let value = Positives::new()
+            .with_matrix(identity)
+            .similarity(&seq1, &seq2)
+            .unwrap();
+        assert_eq!(value, 0.25);
+    }
+
+    #[test]
+    fn test_normalized_score() {
+        let seq1 = chars("IKDW");
+        let seq2 = chars("VRCW");
+        let metric = NormalizedScore::new();
+        assert_eq!(metric.similarity(&seq1, &seq1).unwrap(), 1.0);
+        // I-V: 3, K-R: 2, D-C: -3, W-W: 11
+        // Self scores: 4 + 5 + 6 + 11 and 4 + 5 + 9 + 11
+        let expected = 13.0 / (26f64 * 29f64).sqrt();
+        let value = metric.similarity(&seq1, &seq2).unwrap();
+        assert!((value - expected).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_metric_by_name() {
+        let seq1 = chars("AC--");
+        let seq2 = chars("AD--");
+        let value = |name: &str, exclude_gaps: bool| {
+            metric_by_name(name, exclude_gaps)
+                .unwrap()
+                .similarity(&seq1, &seq2)
+                .unwrap()
+        };
+        assert_eq!(value("identity", false), 0.25);
+        assert_eq!(value("identity", true), 0.5);
+        assert_eq!(value("identity-aligned", false), 0.5);
+        assert!(metric_by_name("unknown", false).is_err());
+        assert!(metric_by_name("identity-aligned", true).is_err());
+        assert!(metric_by_name("score", true).is_err());
+    }
+}
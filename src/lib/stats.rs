@@ -0,0 +1,323 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::gapping::is_gap;
+use crate::pairwise::PairwiseIdentity;
+use crate::seqs::{Alignment, SequenceAccesors};
+use crate::similarity::IdentityOverLength;
+
+const NUCLEOTIDES: &str = "ACGTUN";
+const AMINO_ACIDS: &str = "ACDEFGHIKLMNPQRSTVWYBZXUO*";
+
+/// The kind of residues found in an alignment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlphabetKind {
+    DNA,
+    RNA,
+    Protein,
+    Unknown,
+}
+
+impl AlphabetKind {
+    /// Guess the alphabet from the residues of a set of sequences.
+    ///
+    /// Sequences are nucleic acids if at least 90% of residues are
+    /// nucleotides or N, they are RNA if they have U and not T.
+    /// Otherwise, they are proteins if all residues are valid amino acid
+    /// codes.
+    /// ```
+    /// use famlib::stats::AlphabetKind;
+    /// assert_eq!(AlphabetKind::detect("ACGT-N".chars()), AlphabetKind::DNA);
+    /// assert_eq!(AlphabetKind::detect("acgu".chars()), AlphabetKind::RNA);
+    /// assert_eq!(AlphabetKind::detect("MKLV".chars()), AlphabetKind::Protein);
+    /// assert_eq!(AlphabetKind::detect("MK1".chars()), AlphabetKind::Unknown);
+    /// assert_eq!(AlphabetKind::detect("--".chars()), AlphabetKind::Unknown);
+    /// ```
+    pub fn detect<T: Iterator<Item = char>>(chars: T) -> Self {
+        let mut total = 0usize;
+        let mut nucleotides = 0usize;
+        let mut has_t = false;
+        let mut has_u = false;
+        let mut all_amino = true;
+        for c in chars.filter(|c| !is_gap(*c)) {
+            let c = c.to_ascii_uppercase();
+            total += 1;
+            if NUCLEOTIDES.contains(c) {
+                nucleotides += 1;
+            }
+            has_t |= c == 'T';
+            has_u |= c == 'U';
+            all_amino &= AMINO_ACIDS.contains(c);
+        }
+        if total == 0 {
+            AlphabetKind::Unknown
+        } else if nucleotides as f64 >= 0.9 * total as f64 {
+            match has_u && !has_t {
+                true => AlphabetKind::RNA,
+                false => AlphabetKind::DNA,
+            }
+        } else if all_amino {
+            AlphabetKind::Protein
+        } else {
+            AlphabetKind::Unknown
+        }
+    }
+}
+
+/// Statistics of a single sequence of an alignment.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SequenceStats {
+    pub id: String,
+    pub residues: usize,
+    pub gap_fraction: f64,
+}
+
+/// Summary statistics of an alignment.
+///
+/// Both '-' and '.' characters are considered gaps.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AlignmentStats {
+    pub sequences: usize,
+    pub length: usize,
+    pub total_residues: usize,
+    pub min_residues: usize,
+    pub max_residues: usize,
+    pub mean_residues: f64,
+    /// Mean identity of all pairs of sequences. Positions in which both
+    /// sequences have gaps are not considered.
+    pub average_identity: f64,
+    pub gap_fraction: f64,
+    /// Number of columns without gaps and a single residue.
+    pub conserved_columns: usize,
+    /// Number of columns with only gaps.
+    pub gap_columns: usize,
+    pub alphabet: AlphabetKind,
+    pub per_sequence: Vec<SequenceStats>,
+}
+
+impl AlignmentStats {
+    /// Computes the statistics of an alignment.
+    /// ```
+    /// use famlib::stats::{AlignmentStats, AlphabetKind};
+    /// use famlib::seqs::{
+    ///     AnnotatedSequence, SequenceAccesors, SequenceCollection
+    /// };
+    /// let mut seqs = SequenceCollection::new();
+    /// for (id, seq) in [("s1", "AC-T-"), ("s2", "AGGT-")] {
+    ///     seqs.add(
+    ///         AnnotatedSequence::from_string(id.to_string(), seq.to_string())
+    ///     ).unwrap();
+    /// }
+    /// let stats = AlignmentStats::from_msa(&seqs.to_msa().unwrap());
+    /// assert_eq!(stats.sequences, 2);
+    /// assert_eq!(stats.min_residues, 3);
+    /// assert_eq!(stats.max_residues, 4);
+    /// assert_eq!(stats.conserved_columns, 2);
+    /// assert_eq!(stats.gap_columns, 1);
+    /// assert_eq!(stats.average_identity, 0.5);
+    /// assert_eq!(stats.alphabet, AlphabetKind::DNA);
+    /// ```
+    pub fn from_msa(msa: &Alignment) -> Self {
+        let length = msa.length();
+        let per_sequence = msa
+            .iter()
+            .map(|s| {
+                let residues = s.seq().map_or(0, |x| {
+                    x.iter().filter(|c| !is_gap(**c)).count()
+                });
+                SequenceStats {
+                    id: s.id().to_string(),
+                    residues,
+                    gap_fraction: match length {
+                        0 => 0.0,
+                        _ => 1.0 - residues as f64 / length as f64,
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+        let sequences = per_sequence.len();
+        let total_residues = per_sequence.iter().map(|x| x.residues).sum();
+        let cells = sequences * length;
+        let mut conserved_columns = 0;
+        let mut gap_columns = 0;
+        for column in msa.columns() {
+            let first = column[0].to_ascii_uppercase();
+            if column.iter().all(|c| is_gap(**c)) {
+                gap_columns += 1;
+            } else if !is_gap(first) &&
+                column.iter().all(|c| c.to_ascii_uppercase() == first) {
+                conserved_columns += 1;
+            }
+        }
+        AlignmentStats {
+            sequences,
+            length,
+            total_residues,
+            min_residues: per_sequence
+                .iter()
+                .map(|x| x.residues)
+                .min()
+                .unwrap_or(0),
+            max_residues: per_sequence
+                .iter()
+                .map(|x| x.residues)
+                .max()
+                .unwrap_or(0),
+            mean_residues: match sequences {
+                0 => 0.0,
+                _ => total_residues as f64 / sequences as f64,
+            },
+            average_identity: average_identity(msa),
+            gap_fraction: match cells {
+                0 => 0.0,
+                _ => 1.0 - total_residues as f64 / cells as f64,
+            },
+            conserved_columns,
+            gap_columns,
+            alphabet: AlphabetKind::detect(
+                msa.iter()
+                    .filter_map(|x| x.seq())
+                    .flat_map(|x| x.iter().copied())
+            ),
+            per_sequence,
+        }
+    }
+
+    /// Writes a human readable report.
+    ///
+    /// Per sequence statistics are included if expanded is true.
+    pub fn write_text<T: Write>(
+        &self,
+        mut writer: T,
+        expanded: bool,
+    ) -> io::Result<()> {
+        let alphabet = match self.alphabet {
+            AlphabetKind::DNA => "DNA",
+            AlphabetKind::RNA => "RNA",
+            AlphabetKind::Protein => "Protein",
+            AlphabetKind::Unknown => "Unknown",
+        };
+        writeln!(writer, "Alphabet: {}", alphabet)?;
+        writeln!(writer, "Number of sequences: {}", self.sequences)?;
+        writeln!(writer, "Alignment length: {}", self.length)?;
+        writeln!(writer, "Total residues: {}", self.total_residues)?;
+        writeln!(writer, "Smallest: {}", self.min_residues)?;
+        writeln!(writer, "Largest: {}", self.max_residues)?;
+        writeln!(writer, "Average length: {:.1}", self.mean_residues)?;
+        writeln!(
+            writer,
+            "Average identity: {:.1}%",
+            self.average_identity * 100.0
+        )?;
+        writeln!(
+            writer,
+            "Gap fraction: {:.1}%",
+            self.gap_fraction * 100.0
+        )?;
+        writeln!(writer, "Fully conserved columns: {}", self.conserved_columns)?;
+        writeln!(writer, "All gap columns: {}", self.gap_columns)?;
+        if expanded {
+            writeln!(writer, "\nid\tresidues\tgap_fraction")?;
+            for s in &self.per_sequence {
+                writeln!(
+                    writer,
+                    "{}\t{}\t{:.4}",
+                    s.id, s.residues, s.gap_fraction
+                )?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Writes the statistics as a JSON object.
+    pub fn write_json<T: Write>(&self, mut writer: T) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)
+            .map_err(io::Error::other)?;
+        writeln!(writer)?;
+        writer.flush()
+    }
+}
+
+/// Mean identity fraction of all pairs of sequences, excluding the
+/// positions in which both sequences have gaps.
+fn average_identity(msa: &Alignment) -> f64 {
+    let size = msa.size();
+    let pairs = size * size.saturating_sub(1) / 2;
+    let matrix = PairwiseIdentity::new(msa)
+        .with_metric(IdentityOverLength::new().exclude_gaps())
+        .matrix();
+    match matrix {
+        Ok(matrix) if pairs > 0 => {
+            let total = (0..size)
+                .flat_map(|i| (i + 1..size).map(move |j| (i, j)))
+                .map(|(i, j)| matrix.identity(i, j))
+                .sum::<f64>();
+            total / pairs as f64
+        }
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::build_msa;
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let msa = build_msa(&["MK-LV.", "MKALV-", "MR-LI-"]);
+        let stats = AlignmentStats::from_msa(&msa);
+        assert_eq!(stats.sequences, 3);
+        assert_eq!(stats.length, 6);
+        assert_eq!(stats.total_residues, 13);
+        assert_eq!(stats.min_residues, 4);
+        assert_eq!(stats.max_residues, 5);
+        assert!((stats.mean_residues - 13.0 / 3.0).abs() < 1e-9);
+        assert!((stats.gap_fraction - 5.0 / 18.0).abs() < 1e-9);
+        assert_eq!(stats.conserved_columns, 2);
+        assert_eq!(stats.gap_columns, 1);
+        assert_eq!(stats.alphabet, AlphabetKind::Protein);
+        // Identities: 4/5, 2/4 and 2/5.
+        let expected = (0.8 + 0.5 + 0.4) / 3.0;
+        assert!((stats.average_identity - expected).abs() < 1e-9);
+        assert_eq!(stats.per_sequence[1].id, "s2");
+        assert_eq!(stats.per_sequence[1].residues, 5);
+        assert!((stats.per_sequence[1].gap_fraction - 1.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_empty_stats() {
+        let stats = AlignmentStats::from_msa(&Alignment::new());
+        assert_eq!(stats.sequences, 0);
+        assert_eq!(stats.length, 0);
+        assert_eq!(stats.mean_residues, 0.0);
+        assert_eq!(stats.average_identity, 0.0);
+        assert_eq!(stats.alphabet, AlphabetKind::Unknown);
+    }
+
+    #[test]
+    fn test_write_text() {
+        let msa = build_msa(&["MK-LV.", "MKALV-", "MR-LI-"]);
+        let stats = AlignmentStats::from_msa(&msa);
+        let mut out = vec![];
+        stats.write_text(&mut out, true).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("Alphabet: Protein\nNumber of sequences: 3\n"));
+        assert!(text.contains("Fully conserved columns: 2\n"));
+        assert!(text.ends_with("s3\t4\t0.3333\n"));
+    }
+
+    #[test]
+    fn test_write_json() {
+        let msa = build_msa(&["MK-LV.", "MKALV-", "MR-LI-"]);
+        let stats = AlignmentStats::from_msa(&msa);
+        let mut out = vec![];
+        stats.write_json(&mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["sequences"], 3);
+        assert_eq!(json["alphabet"], "protein");
+        assert_eq!(json["per_sequence"][0]["id"], "s1");
+    }
+}
@@ -0,0 +1,21 @@
+use crate::seqs::{
+    Alignment, AnnotatedSequence, SequenceAccesors, SequenceCollection
+};
+
+/// Builds a collection of sequences with ids s1, s2, s3, ...
+pub fn build_seqs(seqs: &[&str]) -> SequenceCollection {
+    seqs.iter()
+        .enumerate()
+        .map(|(i, s)| AnnotatedSequence::from_string(format!("s{}", i + 1), s))
+        .collect()
+}
+
+/// Builds an alignment of sequences with ids s1, s2, s3, ...
+pub fn build_msa(seqs: &[&str]) -> Alignment {
+    build_seqs(seqs).to_msa().unwrap()
+}
+
+/// The sequences of an alignment or collection as strings.
+pub fn rows<T: SequenceAccesors>(seqs: &T) -> Vec<String> {
+    seqs.iter().map(|x| x.seq_as_string()).collect()
+}
@@ -107,6 +107,56 @@ impl Trim<Alignment> for Alignment {
     right: bool,
     left: bool
   ) -> Alignment {
+    let cols_to_drop = self.gapped_end_columns(right, left);
+    let mut new_aln = self
+      .iter()
+      .cloned()
+      .collect::<SequenceCollection>()
+      .to_msa()
+      .unwrap();
+    new_aln.remove_columns(cols_to_drop).unwrap();
+    new_aln
+  }
+
+  fn trim_by_terminal_gaps(
+    &self,
+    right: bool,
+    left: bool
+  ) -> Alignment {
+    let terminal_gaps = self.terminal_gap_columns(right, left);
+    let mut new_aln = self.clone();
+    new_aln
+      .remove_columns(terminal_gaps)
+      .unwrap();
+    new_aln
+  }
+}
+
+/// Columns selected by the gap based trims of an alignment, that can be
+/// removed or masked.
+pub trait TrimmedColumns {
+  /// Columns with gaps in the ends of the alignment, the ones removed by
+  /// `trim_by_gaps`.
+  fn gapped_end_columns(
+    &self,
+    right: bool,
+    left: bool
+  ) -> Vec<usize>;
+  /// Columns of the terminal gaps of any sequence, the ones removed by
+  /// `trim_by_terminal_gaps`.
+  fn terminal_gap_columns(
+    &self,
+    right: bool,
+    left: bool
+  ) -> Vec<usize>;
+}
+
+impl TrimmedColumns for Alignment {
+  fn gapped_end_columns(
+    &self,
+    right: bool,
+    left: bool
+  ) -> Vec<usize> {
     let ncols = self.length();
     let seq_len = self.length();
     let mut gapped_cols_left = vec![false; ncols];
@@ -135,29 +185,21 @@ impl Trim<Alignment> for Alignment {
         }
       }
     }
-    let cols_to_drop = gapped_cols_left
+    gapped_cols_left
       .iter()
       .zip(gapped_cols_right)
       .map(|(a, b)| *a || b)
       .enumerate()
       .filter(|(_, x)| *x)
       .map(|(i, _)| i)
-      .collect::<Vec<_>>();
-    let mut new_aln = self
-      .iter()
-      .cloned()
-      .collect::<SequenceCollection>()
-      .to_msa()
-      .unwrap();
-    new_aln.remove_columns(cols_to_drop).unwrap();
-    new_aln
+      .collect::<Vec<_>>()
   }
 
-  fn trim_by_terminal_gaps(
+  fn terminal_gap_columns(
     &self,
     right: bool,
     left: bool
-  ) -> Alignment {
+  ) -> Vec<usize> {
     let mut terminal_gaps_left: usize = 0;
     let mut terminal_gaps_right: usize = 0;
     let length = self.length();
@@ -186,7 +228,7 @@ impl Trim<Alignment> for Alignment {
       }
       terminal_gaps_right = std::cmp::max(terminal_gaps_right, right_gaps);
     }
-    let terminal_gaps = match (left, right) {
+    match (left, right) {
       (true, true) | (false, false) => {
         let mut terminal_gaps = vec![];
         for i in 0..terminal_gaps_left {
@@ -211,12 +253,7 @@ impl Trim<Alignment> for Alignment {
         }
         terminal_gaps
       }
-    };
-    let mut new_aln = self.clone();
-    new_aln
-      .remove_columns(terminal_gaps)
-      .unwrap();
-    new_aln
+    }
   }
 }
 
@@ -227,6 +264,7 @@ mod test {
   use crate::seqs::SequenceAccesors;
 
   use super::Trim;
+  use super::TrimmedColumns;
 
   #[test]
   fn test_trim_seqcol_fixed() {
@@ -383,4 +421,20 @@ mod test {
         vec![3, 3, 3]
     );
   }
+  #[test]
+  fn test_trimmed_columns() {
+    let msa = vec![
+        ("1", "ABC---G--"),
+        ("2", "ABCD-----"),
+        ("3", "-BCDEF---"),
+      ]
+      .into_iter()
+      .collect::<SequenceCollection>()
+      .to_msa()
+      .unwrap();
+    assert_eq!(msa.gapped_end_columns(true, true), vec![0, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(msa.terminal_gap_columns(true, true), vec![0, 4, 5, 6, 7, 8]);
+    assert_eq!(msa.terminal_gap_columns(false, true), vec![0]);
+    assert_eq!(msa.terminal_gap_columns(true, false), vec![4, 5, 6, 7, 8]);
+  }
 }
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::pairwise::PairwiseIdentity;
+use crate::seqs::{Alignment, SequenceAccesors};
+use crate::similarity::{IdentityOverLength, SimilarityMetric};
+
+/// Scales weights so that they sum to one. If all weights are zero, every
+/// sequence gets the same weight.
+pub fn normalize_weights(weights: &mut [f64]) {
+    let total: f64 = weights.iter().sum();
+    let size = weights.len() as f64;
+    weights.iter_mut().for_each(|x| {
+        *x = match total > 0f64 {
+            true => *x / total,
+            false => 1f64 / size,
+        }
+    });
+}
+
+/// Position-based sequence weights (Henikoff & Henikoff 1994).
+///
+/// In each column, a sequence gets 1 / (r * s), where r is the number of
+/// different residues in the column and s is the number of sequences that
+/// share the residue of the sequence. Weights are averaged over columns
+/// and normalized to sum one.
+///
+/// By default gaps are ignored, they do not add weight and are not
+/// counted as a different residue.
+pub struct Henikoff<'a> {
+    msa: &'a Alignment,
+    include_gaps: bool,
+}
+
+impl<'a> Henikoff<'a> {
+    pub fn new(msa: &'a Alignment) -> Self {
+        Henikoff {
+            msa,
+            include_gaps: false,
+        }
+    }
+    /// Treats gaps as an additional residue.
+    pub fn include_gaps(mut self) -> Self {
+        self.include_gaps = true;
+        self
+    }
+    pub fn exclude_gaps(mut self) -> Self {
+        self.include_gaps = false;
+        self
+    }
+    /// Computes the weights of all sequences.
+    /// ```
+    /// use famlib::weights::Henikoff;
+    /// use famlib::seqs::{
+    ///     AnnotatedSequence, SequenceAccesors, SequenceCollection
+    /// };
+    /// let mut seqs = SequenceCollection::new();
+    /// for (id, seq) in [("s1", "AA"), ("s2", "AA"), ("s3", "CC")] {
+    ///     seqs.add(
+    ///         AnnotatedSequence::from_string(id.to_string(), seq.to_string())
+    ///     ).unwrap();
+    /// }
+    /// let msa = seqs.to_msa().unwrap();
+    /// let weights = Henikoff::new(&msa).weights().unwrap();
+    /// assert_eq!(weights, vec![0.25, 0.25, 0.5]);
+    /// ```
+    pub fn weights(&self) -> Result<Vec<f64>, Box<dyn Error>> {
+        let seqs = self
+            .msa
+            .iter()
+            .map(|x| x.seq().ok_or("Sequence without residues"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut weights = vec![0f64; seqs.len()];
+        for i in 0..self.msa.length() {
+            let column = seqs
+                .iter()
+                .map(|x| x[i].to_ascii_uppercase())
+                .collect::<Vec<_>>();
+            let is_counted =
+                |c: &char| self.include_gaps || (*c != '-' && *c != '.');
+            let mut counts = HashMap::new();
+            for c in column.iter().filter(|c| is_counted(c)) {
+                *counts.entry(*c).or_insert(0usize) += 1;
+            }
+            let different = counts.len() as f64;
+            for (w, c) in weights.iter_mut().zip(&column) {
+                if let Some(n) = counts.get(c) {
+                    *w += 1f64 / (different * *n as f64);
+                }
+            }
+        }
+        normalize_weights(&mut weights);
+        Ok(weights)
+    }
+}
+
+/// A node of a rooted tree built by UPGMA.
+pub(crate) struct TreeNode {
+    pub(crate) leaves: Vec<usize>,
+    pub(crate) height: f64,
+    pub(crate) parent: Option<usize>,
+}
+
+/// Builds a UPGMA tree from a distance matrix. Leaves are the first
+/// nodes, every internal node is created after its children.
+pub(crate) fn upgma(mut distances: Vec<Vec<f64>>) -> Vec<TreeNode> {
+    let size = distances.len();
+    let mut nodes = (0..size)
+        .map(|x| TreeNode {
+            leaves: vec![x],
+            height: 0f64,
+            parent: None,
+        })
+        .collect::<Vec<_>>();
+    // Each active cluster is a row of the distance matrix, holding the
+    // index of its tree node.
+    let mut active = (0..size).map(Some).collect::<Vec<_>>();
+    let nearest = |row: usize, active: &[Option<usize>], d: &[Vec<f64>]| {
+        (0..active.len())
+            .filter(|x| *x != row && active[*x].is_some())
+            .map(|x| (x, d[row][x]))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    };
+    let mut neighbours = (0..size)
+        .map(|x| nearest(x, &active, &distances))
+        .collect::<Vec<_>>();
+    for _ in 1..size {
+        let (a, (b, d)) = (0..size)
+            .filter(|x| active[*x].is_some())
+            .filter_map(|x| neighbours[x].map(|n| (x, n)))
+            .min_by(|x, y| x.1 .1.total_cmp(&y.1 .1))
+            .unwrap();
+        let (node_a, node_b) = (active[a].unwrap(), active[b].unwrap());
+        let (size_a, size_b) = (
+            nodes[node_a].leaves.len() as f64,
+            nodes[node_b].leaves.len() as f64,
+        );
+        let mut leaves = nodes[node_a].leaves.clone();
+        leaves.extend(&nodes[node_b].leaves);
+        let new_node = nodes.len();
+        nodes.push(TreeNode {
+            leaves,
+            height: (d / 2f64)
+                .max(nodes[node_a].height)
+                .max(nodes[node_b].height),
+            parent: None,
+        });
+        nodes[node_a].parent = Some(new_node);
+        nodes[node_b].parent = Some(new_node);
+        active[a] = Some(new_node);
+        active[b] = None;
+        for k in (0..size).filter(|x| active[*x].is_some() && *x != a) {
+            let value = (size_a * distances[a][k] + size_b * distances[b][k])
+                / (size_a + size_b);
+            distances[a][k] = value;
+            distances[k][a] = value;
+        }
+        neighbours[b] = None;
+        neighbours[a] = nearest(a, &active, &distances);
+        for k in (0..size).filter(|x| active[*x].is_some() && *x != a) {
+            neighbours[k] = match neighbours[k] {
+                Some((n, _)) if n == a || n == b => {
+                    nearest(k, &active, &distances)
+                }
+                Some((_, nd)) if distances[k][a] < nd => {
+                    Some((a, distances[k][a]))
+                }
+                x => x,
+            };
+        }
+    }
+    nodes
+}
+
+/// Tree-based sequence weights (Gerstein, Sonnhammer & Chothia 1994).
+///
+/// A UPGMA tree is built from the distances between sequences (one minus
+/// similarity). Going from the leaves to the root, the length of each
+/// branch is shared among the sequences below it, in proportion to the
+/// weights they already have. Weights are normalized to sum one.
+pub struct Gsc<'a> {
+    msa: &'a Alignment,
+    metric: Box<dyn SimilarityMetric + 'a>,
+    threads: usize,
+}
+
+impl<'a> Gsc<'a> {
+    pub fn new(msa: &'a Alignment) -> Self {
+        Gsc {
+            msa,
+            metric: Box::new(IdentityOverLength::new()),
+            threads: 0,
+        }
+    }
+    /// Sets the metric used to compare sequences, identity over the
+    /// alignment length is used by default.
+    pub fn with_metric(mut self, metric: impl SimilarityMetric + 'a) -> Self {
+        self.metric = Box::new(metric);
+        self
+    }
+    /// Sets the number of threads used to compare sequences. Zero uses all
+    /// available cores.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+    /// Computes the weights of all sequences.
+    pub fn weights(&self) -> Result<Vec<f64>, Box<dyn Error>> {
+        let size = self.msa.size();
+        let matrix = PairwiseIdentity::new(self.msa)
+            .with_threads(self.threads)
+            .with_metric(&self.metric)
+            .matrix()?;
+        let distances = (0..size)
+            .map(|i| {
+                (0..size)
+                    .map(|j| 1f64 - matrix.identity(i, j))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let nodes = upgma(distances);
+        let mut weights = vec![0f64; size];
+        for node in &nodes {
+            let branch = match node.parent {
+                Some(p) => nodes[p].height - node.height,
+                None => continue,
+            };
+            let current: f64 = node.leaves.iter().map(|x| weights[*x]).sum();
+            for leaf in &node.leaves {
+                weights[*leaf] += match current > 0f64 {
+                    true => branch * weights[*leaf] / current,
+                    false => branch / node.leaves.len() as f64,
+                };
+            }
+        }
+        normalize_weights(&mut weights);
+        Ok(weights)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::build_msa;
+    use super::*;
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        assert_eq!(a.len(), b.len());
+        a.iter()
+            .zip(b)
+            .for_each(|(x, y)| assert!((x - y).abs() < 1e-10, "{:?}", a));
+    }
+
+    #[test]
+    fn test_henikoff_gaps() {
+        let msa = build_msa(&["AC", "A-", "GC"]);
+        // Column 1: A: 1/4, 1/4, G: 1/2
+        // Column 2: C: 1/2, -: 0, C: 1/2
+        let weights = Henikoff::new(&msa).weights().unwrap();
+        assert_close(&weights, &[0.375, 0.125, 0.5]);
+        // Column 2: C: 1/4, -: 1/2, C: 1/4
+        let weights = Henikoff::new(&msa).include_gaps().weights().unwrap();
+        assert_close(&weights, &[0.25, 0.375, 0.375]);
+    }
+
+    #[test]
+    fn test_upgma() {
+        let distances = vec![
+            vec![0.0, 0.2, 0.6],
+            vec![0.2, 0.0, 0.8],
+            vec![0.6, 0.8, 0.0],
+        ];
+        let nodes = upgma(distances);
+        assert_eq!(nodes.len(), 5);
+        assert_eq!(nodes[3].leaves, vec![0, 1]);
+        assert_eq!(nodes[3].height, 0.1);
+        assert_eq!(nodes[4].leaves, vec![0, 1, 2]);
+        assert_eq!(nodes[4].height, 0.35);
+        assert_eq!(nodes[2].parent, Some(4));
+        assert_eq!(nodes[4].parent, None);
+    }
+
+    #[test]
+    fn test_gsc() {
+        let msa = build_msa(&[
+            "AAAAAAAAAA",
+            "AAAAAAAACC",
+            "CCCCCCAAAA",
+        ]);
+        // Tree: ((0:0.1, 1:0.1):0.25, 2:0.35)
+        // Leaves get 0.1, 0.1, 0.35, the inner branch is shared equally.
+        let weights = Gsc::new(&msa).with_threads(1).weights().unwrap();
+        assert_close(&weights, &[0.225 / 0.8, 0.225 / 0.8, 0.35 / 0.8]);
+    }
+
+    #[test]
+    fn test_identical_sequences() {
+        let msa = build_msa(&["ACGT", "ACGT", "ACGT", "ACGT"]);
+        let weights = Gsc::new(&msa).weights().unwrap();
+        assert_close(&weights, &[0.25; 4]);
+        let weights = Henikoff::new(&msa).weights().unwrap();
+        assert_close(&weights, &[0.25; 4]);
+        let msa = build_msa(&[]);
+        assert!(Gsc::new(&msa).weights().unwrap().is_empty());
+    }
+}